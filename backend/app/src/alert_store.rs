@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, error::Error, sync::{Arc, Mutex}, time::Duration};
+use calc::realtime::alerts::Alerts;
+use chrono::FixedOffset;
+use models::alert::{Alert, RawAlert};
+use crate::common::snapshot::Snapshot;
+use crate::realtime_store::fetch_feed;
+
+/// 管理用エンドポイントで登録された運行情報と、GTFS-RTのAlertsのフィードから読み取った運行情報
+#[derive(Debug, Default)]
+struct AlertSources {
+    /// alert_idの順
+    admin: BTreeMap<String, Alert>,
+    feed: Vec<Alert>,
+    /// alert_idを指定せずに登録されたときに使う番号
+    next_id: u64,
+}
+
+/// 運行情報を保持し、リクエストごとにそのスナップショットを渡す
+/// 管理用エンドポイントで登録したものはメモリ上にだけ持つので、再起動すると消える
+#[derive(Debug)]
+pub(crate) struct AlertStore {
+    /// フィードのURLかファイルのパス。Noneなら管理用エンドポイントで登録したものだけを使う
+    source: Option<String>,
+    prefix: Option<String>,
+    /// フィードの有効期間を日付にするタイムゾーン
+    offset: FixedOffset,
+    sources: Mutex<AlertSources>,
+    current: Snapshot<Alerts>,
+}
+
+impl AlertStore {
+    pub(crate) fn new(source: Option<String>, prefix: Option<String>, offset: FixedOffset) -> Self {
+        Self {
+            source,
+            prefix,
+            offset,
+            sources: Mutex::new(AlertSources::default()),
+            current: Snapshot::new(Alerts::default()),
+        }
+    }
+
+    pub(crate) fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub(crate) fn current(&self) -> Arc<Alerts> {
+        self.current.current()
+    }
+
+    /// sourceからフィードを取得し直して、フィードの運行情報を差し替える
+    /// 取得に失敗した場合は、最後に読めたフィードの運行情報を使い続ける。URLの場合はtimeoutまでに取得できなければ諦める
+    pub(crate) fn refresh(&self, timeout: Duration) -> Result<Arc<Alerts>, Box<dyn Error + Send + Sync + 'static>> {
+        let Some(source) = &self.source else {
+            return Err("realtime alerts source is not configured.".into());
+        };
+        let alerts = Alerts::decode(&fetch_feed(source, timeout)?, self.prefix.as_deref(), self.offset)?;
+        Ok(self.update(|sources| sources.feed = alerts.alerts().to_vec()).1)
+    }
+
+    /// 運行情報を登録する。同じalert_idのものがあれば置き換える
+    /// alert_idを指定しなければ、登録済みのものと重ならない`admin-<番号>`にする
+    pub(crate) fn add(&self, raw: RawAlert) -> Result<Alert, String> {
+        self.update(|sources| {
+            // `admin-<番号>`を指定して登録されたものは置き換えないように飛ばす
+            let mut next_id = sources.next_id + 1;
+            while sources.admin.contains_key(&format!("admin-{}", next_id)) {
+                next_id += 1;
+            }
+            let default_id = format!("admin-{}", next_id);
+            let alert = Alert::from_raw(raw, &default_id)?;
+            if alert.alert_id == default_id {
+                sources.next_id = next_id;
+            }
+            sources.admin.insert(alert.alert_id.clone(), alert.clone());
+            Ok(alert)
+        })
+        .0
+    }
+
+    /// 登録した運行情報を取り消す。フィードの運行情報は取り消せない
+    pub(crate) fn remove(&self, alert_id: &str) -> Option<Alert> {
+        self.update(|sources| sources.admin.remove(alert_id)).0
+    }
+
+    /// 同時に更新されたときに片方の更新が消えないように、sourcesをロックしたまま差し替える
+    fn update<T>(&self, f: impl FnOnce(&mut AlertSources) -> T) -> (T, Arc<Alerts>) {
+        let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+        let res = f(&mut sources);
+        let alerts = Alerts::new(sources.admin.values().chain(sources.feed.iter()).cloned().collect());
+        (res, self.current.replace(alerts))
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::AlertStore;
+    use calc::realtime::feed::{Alert, EntitySelector, FeedEntity, FeedMessage};
+    use chrono::FixedOffset;
+    use models::alert::{AlertEffect, AlertTarget, RawAlert};
+    use prost::Message;
+    use std::fs;
+    use std::time::Duration;
+
+    fn raw(alert_id: Option<&str>) -> RawAlert {
+        RawAlert {
+            alert_id: alert_id.map(|x| x.to_string()),
+            effect: AlertEffect::NoService,
+            targets: vec![AlertTarget { trip_id: Some("HAKODATEBUS_55G-D-0756".to_string()), ..Default::default() }],
+            from: None,
+            until: None,
+            header: "運休".to_string(),
+            description: None,
+        }
+    }
+
+    fn offset() -> FixedOffset {
+        FixedOffset::east_opt(9 * 60 * 60).unwrap()
+    }
+
+    #[test]
+    fn 登録した運行情報を置き換え_取り消せる() {
+        let store = AlertStore::new(None, None, offset());
+        assert_eq!("admin-1", store.add(raw(None)).unwrap().alert_id);
+        assert_eq!("admin-2", store.add(raw(None)).unwrap().alert_id);
+        assert_eq!("detour", store.add(raw(Some("detour"))).unwrap().alert_id);
+        assert_eq!("detour", store.add(raw(Some("detour"))).unwrap().alert_id);
+        assert_eq!(3, store.current().len());
+        assert!(store.add(RawAlert { targets: vec![], ..raw(None) }).is_err());
+
+        let in_flight = store.current();
+        assert!(store.remove("admin-1").is_some());
+        assert!(store.remove("admin-1").is_none());
+        assert_eq!(2, store.current().len());
+        // 取り消す前に受け取ったスナップショットは変わらない
+        assert_eq!(3, in_flight.len());
+    }
+
+    #[test]
+    fn 指定されたalert_idと重ならない番号を振る() {
+        let store = AlertStore::new(None, None, offset());
+        assert_eq!("admin-1", store.add(raw(Some("admin-1"))).unwrap().alert_id);
+        assert_eq!("admin-2", store.add(raw(None)).unwrap().alert_id);
+        assert_eq!(2, store.current().len());
+    }
+
+    #[test]
+    fn フィードを読み直しても登録した運行情報は残る() {
+        let path = std::env::temp_dir().join(format!("transfar-alerts-{}.pb", std::process::id()));
+        let store = AlertStore::new(Some(path.to_str().expect("utf-8 path").to_string()), Some("HAKODATEBUS".to_string()), offset());
+        store.add(raw(None)).unwrap();
+        let feed = FeedMessage {
+            header: None,
+            entity: vec![FeedEntity {
+                id: Some("feed-1".to_string()),
+                is_deleted: None,
+                trip_update: None,
+                alert: Some(Alert {
+                    informed_entity: vec![EntitySelector { stop_id: Some("050004".to_string()), ..Default::default() }],
+                    ..Default::default()
+                }),
+            }],
+        };
+        fs::write(&path, feed.encode_to_vec()).expect("write file");
+        let refreshed = store.refresh(Duration::from_secs(1));
+        fs::remove_file(&path).expect("remove file");
+
+        assert_eq!(2, refreshed.expect("feed should be read").len());
+        let alerts = store.current();
+        let ids: Vec<_> = alerts.alerts().iter().map(|x| x.alert_id.as_str()).collect();
+        assert_eq!(vec!["admin-1", "feed-1"], ids);
+        // フィードの運行情報は取り消せない
+        assert!(store.remove("feed-1").is_none());
+    }
+}
@@ -1,5 +1,54 @@
-use actix_web::Responder;
+pub(crate) mod query_datetime;
+pub(crate) mod snapshot;
+
+use actix_web::{web::{JsonConfig, QueryConfig}, Responder};
+use calc::dataset::Dataset;
+use chrono::NaiveDate;
+use models::{id::{ID, StationId}, station::Station};
+use crate::error::AppError;
 
 pub(crate) fn health() -> impl Responder {
-    format!("maybe healthy")
-}
\ No newline at end of file
+    "maybe healthy".to_string()
+}
+
+/// クエリパラメータが読めなかったときも、ほかのエラーと同じJSONを返す
+pub(crate) fn query_config() -> QueryConfig {
+    QueryConfig::default().error_handler(|e, _| AppError::InvalidQueryParameter(e.to_string()).into())
+}
+
+/// JSONのbodyが読めなかったときも、ほかのエラーと同じJSONを返す
+pub(crate) fn json_config() -> JsonConfig {
+    JsonConfig::default().error_handler(|e, _| AppError::InvalidRequestBody(e.to_string()).into())
+}
+
+pub(crate) fn find_station(dataset: &Dataset, raw_station_id: &str) -> Result<Station, AppError> {
+    let station_id = StationId::new(raw_station_id.to_string(), dataset.data_root_path());
+    dataset
+        .station(&station_id)
+        .cloned()
+        .ok_or(AppError::UnknownStation(raw_station_id.to_string()))
+}
+
+/// 駅に時刻表があり、dateに有効なカレンダーがあることを確かめる
+pub(crate) fn check_timetable(dataset: &Dataset, station: &Station, date: NaiveDate) -> Result<(), AppError> {
+    check_service_dates(dataset, station, date, &[date])
+}
+
+/// 駅に時刻表があり、dateの前日、当日、翌日の運行日のどれかに有効なカレンダーがあることを確かめる
+/// 日付をまたいで走る前日の便や翌日の便も返す場合に、当日のカレンダーが切れていてもエラーにしない
+pub(crate) fn check_timetable_around(dataset: &Dataset, station: &Station, date: NaiveDate) -> Result<(), AppError> {
+    let service_dates: Vec<NaiveDate> = [date.pred_opt(), Some(date), date.succ_opt()].into_iter().flatten().collect();
+    check_service_dates(dataset, station, date, &service_dates)
+}
+
+fn check_service_dates(dataset: &Dataset, station: &Station, date: NaiveDate, service_dates: &[NaiveDate]) -> Result<(), AppError> {
+    let raw_station_id = station.station_id.get_raw_id();
+    let timetable = dataset
+        .timetable(&station.station_id)
+        .ok_or(AppError::NoTimetable(raw_station_id.clone()))?;
+    if service_dates.iter().any(|x| timetable.get_valid_calendar(*x).is_some()) {
+        Ok(())
+    } else {
+        Err(AppError::NoValidCalendar { station_id: raw_station_id, date })
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+
+/// 運行事業者のタイムゾーン (Asia/Tokyo)。日本は夏時間がないので固定オフセットで扱う。
+pub(crate) const OPERATOR_UTC_OFFSET_SECONDS: i32 = 9 * 60 * 60;
+
+/// 検索時刻を指定するためのクエリパラメータ
+/// `at`が指定されていればそれを優先し、なければ`date`と`time`を組み合わせる。
+/// 指定がない部分は現在時刻で補う。
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct DateTimeQuery {
+    pub at: Option<String>,
+    pub date: Option<String>,
+    pub time: Option<String>,
+}
+
+/// 日付だけを指定するためのクエリパラメータ。指定がなければ今日 (運行事業者のタイムゾーン) にする
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct DateQuery {
+    pub date: Option<String>,
+}
+
+impl DateQuery {
+    pub(crate) fn resolve(&self, now: DateTime<FixedOffset>) -> Result<NaiveDate, String> {
+        match &self.date {
+            Some(date) => parse_date(date),
+            None => Ok(now.date_naive()),
+        }
+    }
+}
+
+fn parse_date(raw: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| format!("`date` must be formatted as YYYY-MM-DD but got `{}`.", raw))
+}
+
+pub(crate) fn operator_offset() -> FixedOffset {
+    FixedOffset::east_opt(OPERATOR_UTC_OFFSET_SECONDS).expect("valid offset")
+}
+
+pub(crate) fn operator_now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&operator_offset())
+}
+
+impl DateTimeQuery {
+    pub(crate) fn resolve(&self, now: DateTime<FixedOffset>) -> Result<DateTime<FixedOffset>, String> {
+        if let Some(at) = &self.at {
+            if self.date.is_some() || self.time.is_some() {
+                return Err("`at` cannot be combined with `date` or `time`.".to_string());
+            }
+            return DateTime::parse_from_rfc3339(at)
+                .map(|x| x.with_timezone(&operator_offset()))
+                .map_err(|_| format!("`at` must be an RFC3339 datetime but got `{}`.", at));
+        }
+
+        let date = DateQuery { date: self.date.clone() }.resolve(now)?;
+        let time = match &self.time {
+            Some(time) => NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|_| format!("`time` must be formatted as HH:MM but got `{}`.", time))?,
+            None => now.time(),
+        };
+
+        date.and_time(time)
+            .and_local_timezone(operator_offset())
+            .single()
+            .ok_or_else(|| format!("`{} {}` is not a valid datetime.", date, time))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+    use super::{DateQuery, DateTimeQuery};
+
+    fn now() -> DateTime<chrono::FixedOffset> {
+        DateTime::parse_from_rfc3339("2025-04-25T08:00:00+09:00").expect("valid datetime")
+    }
+
+    #[test]
+    fn 何も指定しないと現在時刻になる() {
+        let actual = DateTimeQuery::default().resolve(now()).unwrap();
+        assert_eq!(now(), actual);
+    }
+
+    #[test]
+    fn atは運行事業者のタイムゾーンに変換される() {
+        let query = DateTimeQuery {
+            at: Some("2025-04-26T00:30:00Z".to_string()),
+            ..Default::default()
+        };
+        let actual = query.resolve(now()).unwrap();
+        assert_eq!("2025-04-26T09:30:00+09:00", actual.to_rfc3339());
+    }
+
+    #[test]
+    fn dateとtimeを組み合わせられる() {
+        let only_time = DateTimeQuery {
+            time: Some("13:05".to_string()),
+            ..Default::default()
+        };
+        assert_eq!("2025-04-25T13:05:00+09:00", only_time.resolve(now()).unwrap().to_rfc3339());
+
+        let both = DateTimeQuery {
+            date: Some("2025-05-01".to_string()),
+            time: Some("07:10".to_string()),
+            ..Default::default()
+        };
+        assert_eq!("2025-05-01T07:10:00+09:00", both.resolve(now()).unwrap().to_rfc3339());
+    }
+
+    #[test]
+    fn 不正な値はエラーになる() {
+        let invalid_at = DateTimeQuery {
+            at: Some("2025/04/25 08:00".to_string()),
+            ..Default::default()
+        };
+        let invalid_date = DateTimeQuery {
+            date: Some("2025-02-30".to_string()),
+            ..Default::default()
+        };
+        let invalid_time = DateTimeQuery {
+            time: Some("8時".to_string()),
+            ..Default::default()
+        };
+        let mixed = DateTimeQuery {
+            at: Some("2025-04-25T08:00:00+09:00".to_string()),
+            time: Some("09:00".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid_at.resolve(now()).is_err());
+        assert!(invalid_date.resolve(now()).is_err());
+        assert!(invalid_time.resolve(now()).is_err());
+        assert!(mixed.resolve(now()).is_err());
+    }
+
+    #[test]
+    fn 日付だけの指定は省略すると今日になる() {
+        assert_eq!("2025-04-25", DateQuery::default().resolve(now()).unwrap().to_string());
+        let date = DateQuery { date: Some("2025-05-01".to_string()) };
+        assert_eq!("2025-05-01", date.resolve(now()).unwrap().to_string());
+        let invalid = DateQuery { date: Some("2025/05/01".to_string()) };
+        assert!(invalid.resolve(now()).is_err());
+    }
+}
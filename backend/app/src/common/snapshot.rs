@@ -0,0 +1,26 @@
+use std::sync::{Arc, RwLock};
+
+/// 差し替えられる値を保持し、読むたびにその時点のスナップショットを渡す
+/// 差し替えても、それまでに渡したスナップショットは変わらない
+#[derive(Debug)]
+pub(crate) struct Snapshot<T> {
+    current: RwLock<Arc<T>>,
+}
+
+impl<T> Snapshot<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self { current: RwLock::new(Arc::new(value)) }
+    }
+
+    pub(crate) fn current(&self) -> Arc<T> {
+        // 中身はArcを差し替えるだけなので、poisonされていてもそのまま使える
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// valueに差し替え、そのスナップショットを返す
+    pub(crate) fn replace(&self, value: T) -> Arc<T> {
+        let value = Arc::new(value);
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = value.clone();
+        value
+    }
+}
@@ -0,0 +1,178 @@
+use std::{env, error::Error, fs::File, io::BufReader, path::Path};
+use serde::Deserialize;
+
+const CONFIG_PATH_ENV: &str = "TRANSFAR_NAVI_CONFIG";
+const DATA_ROOT_ENV: &str = "TRANSFAR_NAVI_DATA_ROOT";
+const ADMIN_TOKEN_ENV: &str = "TRANSFAR_NAVI_ADMIN_TOKEN";
+const DEFAULT_DATA_ROOT: &str = "data";
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_REALTIME_INTERVAL_SECONDS: u64 = 30;
+
+/// サーバー起動時に決まる設定
+/// 優先順位は CLIフラグ > 環境変数 > 設定ファイル > デフォルト値
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AppConfig {
+    pub data_root_path: String,
+    pub host: String,
+    pub port: u16,
+    /// 管理用エンドポイントのBearerトークン。未設定なら管理用エンドポイントは使えない
+    pub admin_token: Option<String>,
+    /// GTFS-RTのTripUpdatesのURLかファイルのパス。未設定なら管理用エンドポイントで受け取ったものだけを使う
+    pub realtime_trip_updates: Option<String>,
+    /// GTFS-RTのAlertsのURLかファイルのパス。未設定なら管理用エンドポイントで登録したものだけを使う
+    pub realtime_alerts: Option<String>,
+    /// フィードのIDをデータセットのIDにそろえるためのprefix (gtfsのimportと同じもの)
+    pub realtime_id_prefix: Option<String>,
+    /// realtime_trip_updatesとrealtime_alertsを取得し直す間隔 (秒)
+    pub realtime_interval_seconds: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+struct RawAppConfig {
+    data_root: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    admin_token: Option<String>,
+    realtime_trip_updates: Option<String>,
+    realtime_alerts: Option<String>,
+    realtime_id_prefix: Option<String>,
+    realtime_interval_seconds: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct CliArgs {
+    config_path: Option<String>,
+    data_root: Option<String>,
+}
+
+impl AppConfig {
+    pub(crate) fn load() -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let args = parse_args(env::args().skip(1))?;
+        let config_path = args.config_path.clone().or_else(|| env::var(CONFIG_PATH_ENV).ok());
+        let raw = match &config_path {
+            Some(path) => {
+                let f = File::open(path).map_err(|e| format!("failed to open config file {}: {}", path, e))?;
+                serde_json::from_reader(BufReader::new(f))?
+            }
+            None => RawAppConfig::default(),
+        };
+
+        let mut config = Self::resolve(args, env::var(DATA_ROOT_ENV).ok(), raw);
+        if let Ok(admin_token) = env::var(ADMIN_TOKEN_ENV) && !admin_token.is_empty() {
+            config.admin_token = Some(admin_token);
+        }
+        if !Path::new(&config.data_root_path).is_dir() {
+            return Err(format!("data root {} is not a directory.", config.data_root_path).into());
+        }
+        Ok(config)
+    }
+
+    fn resolve(args: CliArgs, data_root_env: Option<String>, raw: RawAppConfig) -> Self {
+        let data_root = args.data_root
+            .or(data_root_env)
+            .or(raw.data_root)
+            .unwrap_or(DEFAULT_DATA_ROOT.to_string());
+        // ID::build_pathは末尾に"/"を足すので、ここで取り除いておく
+        let data_root_path = match data_root.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+
+        Self {
+            data_root_path,
+            host: raw.host.unwrap_or(DEFAULT_HOST.to_string()),
+            port: raw.port.unwrap_or(DEFAULT_PORT),
+            admin_token: raw.admin_token.filter(|x| !x.is_empty()),
+            realtime_trip_updates: raw.realtime_trip_updates.filter(|x| !x.is_empty()),
+            realtime_alerts: raw.realtime_alerts.filter(|x| !x.is_empty()),
+            realtime_id_prefix: raw.realtime_id_prefix.filter(|x| !x.is_empty()),
+            realtime_interval_seconds: raw.realtime_interval_seconds.filter(|x| *x > 0).unwrap_or(DEFAULT_REALTIME_INTERVAL_SECONDS),
+        }
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<CliArgs, String> {
+    let mut res = CliArgs::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let (key, inline_value) = match arg.split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let target = match key.as_str() {
+            "--config" => &mut res.config_path,
+            "--data-root" => &mut res.data_root,
+            _ => return Err(format!("unknown argument: {}", arg)),
+        };
+        let value = inline_value
+            .or_else(|| args.next())
+            .ok_or(format!("{} requires a value", key))?;
+        *target = Some(value);
+    }
+    Ok(res)
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{parse_args, AppConfig, CliArgs, RawAppConfig};
+
+    fn args(raw: &[&str]) -> impl Iterator<Item = String> {
+        raw.iter().map(|x| x.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn CLI引数をパースできる() {
+        let actual = parse_args(args(&["--data-root", "/srv/data", "--config=app.json"])).unwrap();
+        let expected = CliArgs {
+            config_path: Some("app.json".to_string()),
+            data_root: Some("/srv/data".to_string()),
+        };
+        assert_eq!(expected, actual);
+
+        assert!(parse_args(args(&["--data-root"])).is_err());
+        assert!(parse_args(args(&["--unknown", "x"])).is_err());
+    }
+
+    #[test]
+    fn CLIフラグ_環境変数_設定ファイルの順に優先される() {
+        let raw = || RawAppConfig {
+            data_root: Some("/from/file".to_string()),
+            host: Some("0.0.0.0".to_string()),
+            port: Some(3000),
+            admin_token: Some("secret".to_string()),
+            realtime_trip_updates: Some("https://example.com/trip_updates.pb".to_string()),
+            realtime_alerts: Some("".to_string()),
+            realtime_id_prefix: Some("HAKODATEBUS".to_string()),
+            realtime_interval_seconds: Some(0),
+        };
+        let cli = || CliArgs {
+            config_path: None,
+            data_root: Some("/from/cli/".to_string()),
+        };
+
+        let from_cli = AppConfig::resolve(cli(), Some("/from/env".to_string()), raw());
+        assert_eq!("/from/cli", from_cli.data_root_path);
+        assert_eq!("0.0.0.0", from_cli.host);
+        assert_eq!(3000, from_cli.port);
+        assert_eq!(Some("secret".to_string()), from_cli.admin_token);
+        assert_eq!(Some("https://example.com/trip_updates.pb".to_string()), from_cli.realtime_trip_updates);
+        assert_eq!(None, from_cli.realtime_alerts);
+        assert_eq!(Some("HAKODATEBUS".to_string()), from_cli.realtime_id_prefix);
+        // 0秒ではフィードを取得し続けてしまうのでデフォルト値を使う
+        assert_eq!(30, from_cli.realtime_interval_seconds);
+
+        let from_env = AppConfig::resolve(CliArgs::default(), Some("/from/env".to_string()), raw());
+        assert_eq!("/from/env", from_env.data_root_path);
+
+        let from_file = AppConfig::resolve(CliArgs::default(), None, raw());
+        assert_eq!("/from/file", from_file.data_root_path);
+
+        let default = AppConfig::resolve(CliArgs::default(), None, RawAppConfig::default());
+        assert_eq!("data", default.data_root_path);
+        assert_eq!(8080, default.port);
+        assert_eq!(None, default.admin_token);
+        assert_eq!(None, default.realtime_trip_updates);
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::{Arc, Mutex};
+use calc::dataset::{Dataset, DatasetError};
+use crate::common::snapshot::Snapshot;
+
+/// 現在配信しているDatasetを保持し、リクエストごとにそのスナップショットを渡す
+/// 再読み込みしても、処理中のリクエストは受け取ったスナップショットを使い続ける
+#[derive(Debug)]
+pub(crate) struct DatasetStore {
+    data_root_path: String,
+    current: Snapshot<Dataset>,
+    /// 同時に再読み込みされたときに、古い読み込み結果で上書きしないように直列化する
+    reloading: Mutex<()>,
+}
+
+impl DatasetStore {
+    pub(crate) fn new(data_root_path: &str, dataset: Dataset) -> Self {
+        Self {
+            data_root_path: data_root_path.to_string(),
+            current: Snapshot::new(dataset),
+            reloading: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn current(&self) -> Arc<Dataset> {
+        self.current.current()
+    }
+
+    /// データのルートを読み直し、問題がなければ差し替える
+    /// 読み込みに失敗した場合は今のデータを配信し続ける
+    /// ファイルをすべて読むのでブロックする
+    pub(crate) fn reload(&self) -> Result<Arc<Dataset>, DatasetError> {
+        let _guard = self.reloading.lock().unwrap_or_else(|e| e.into_inner());
+        let dataset = Dataset::load(&self.data_root_path)?;
+        Ok(self.current.replace(dataset))
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::DatasetStore;
+    use calc::dataset::Dataset;
+    use models::id::{StationId, ID};
+    use std::fs;
+
+    const STATION: &str = r#"{"station_id": "A_01", "name": "A", "join": [], "pronounce": "えー"}"#;
+
+    #[test]
+    fn 再読み込みに成功したら差し替え_失敗したら今のデータを使い続ける() {
+        let root = std::env::temp_dir().join(format!("transfar-store-{}", std::process::id()));
+        for dir in ["station/A", "timetable", "calendar", "departure-pattern", "ride"] {
+            fs::create_dir_all(root.join(dir)).expect("create dir");
+        }
+        let root_path = root.to_str().expect("utf-8 path");
+        let station_id = StationId::new("A_01".to_string(), root_path);
+
+        let store = DatasetStore::new(root_path, Dataset::load(root_path).expect("empty dataset is valid"));
+        let in_flight = store.current();
+
+        fs::write(root.join("station/A/01.json"), STATION).expect("write file");
+        let reloaded = store.reload();
+        assert!(reloaded.is_ok());
+        assert!(store.current().station(&station_id).is_some());
+        // 再読み込み前に受け取ったスナップショットは変わらない
+        assert!(in_flight.station(&station_id).is_none());
+
+        fs::write(root.join("ride/B.json"), "{").expect("write file");
+        let failed = store.reload();
+        fs::remove_dir_all(&root).expect("remove dir");
+
+        assert_eq!(1, failed.expect_err("broken file should be reported").errors.len());
+        assert!(store.current().station(&station_id).is_some());
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt::Display;
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use chrono::NaiveDate;
+use models::response::error::ResError;
+use serde_json::{json, Value};
+
+/// APIが返すエラー。どれも`{code, message, details}`のJSONとして返す
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AppError {
+    /// 存在しない駅のIDが指定された
+    UnknownStation(String),
+    /// 駅はあるが時刻表がない
+    NoTimetable(String),
+    /// 時刻表はあるが、指定した日に有効なカレンダーがない
+    NoValidCalendar { station_id: String, date: NaiveDate },
+    /// 指定した日に運行する便にそのtrip_idがない
+    UnknownTrip { trip_id: String, date: NaiveDate },
+    /// 登録した運行情報にそのalert_idがない
+    UnknownAlert(String),
+    /// データの参照が壊れているなどで計算できなかった。原因はログに出す
+    MalformedData(String),
+    /// クエリパラメータの値が不正
+    InvalidQueryParameter(String),
+    /// リクエストのbodyが読めない
+    InvalidRequestBody(String),
+    /// 管理用のトークンが違う
+    Unauthorized,
+    /// 管理用のトークンが設定されていない
+    AdminDisabled,
+    /// 読み直したデータセットに問題があった
+    InvalidDataset(Vec<String>),
+}
+
+impl AppError {
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownStation(_) => "UNKNOWN_STATION",
+            Self::NoTimetable(_) => "NO_TIMETABLE",
+            Self::NoValidCalendar { .. } => "NO_VALID_CALENDAR",
+            Self::UnknownTrip { .. } => "UNKNOWN_TRIP",
+            Self::UnknownAlert(_) => "UNKNOWN_ALERT",
+            Self::MalformedData(_) => "MALFORMED_DATA",
+            Self::InvalidQueryParameter(_) => "INVALID_QUERY_PARAMETER",
+            Self::InvalidRequestBody(_) => "INVALID_REQUEST_BODY",
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::AdminDisabled => "ADMIN_DISABLED",
+            Self::InvalidDataset(_) => "INVALID_DATASET",
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            Self::UnknownStation(station_id) | Self::NoTimetable(station_id) => Some(json!({ "station_id": station_id })),
+            Self::NoValidCalendar { station_id, date } => Some(json!({
+                "station_id": station_id,
+                "date": date.format("%Y-%m-%d").to_string(),
+            })),
+            Self::UnknownTrip { trip_id, date } => Some(json!({
+                "trip_id": trip_id,
+                "date": date.format("%Y-%m-%d").to_string(),
+            })),
+            Self::UnknownAlert(alert_id) => Some(json!({ "alert_id": alert_id })),
+            Self::InvalidDataset(errors) => Some(json!({ "errors": errors })),
+            Self::MalformedData(_) | Self::InvalidQueryParameter(_) | Self::InvalidRequestBody(_) | Self::Unauthorized | Self::AdminDisabled => None,
+        }
+    }
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownStation(station_id) => write!(f, "station {} not found.", station_id),
+            Self::NoTimetable(station_id) => write!(f, "station {} has no timetable.", station_id),
+            Self::NoValidCalendar { station_id, date } => write!(f, "station {} has no valid calendar on {}.", station_id, date),
+            Self::UnknownTrip { trip_id, date } => write!(f, "trip {} does not run on {}.", trip_id, date),
+            Self::UnknownAlert(alert_id) => write!(f, "alert {} not found.", alert_id),
+            // 原因はデータの中身なので、レスポンスには出さない
+            Self::MalformedData(_) => write!(f, "failed to calculate the response from the dataset."),
+            Self::InvalidQueryParameter(message) | Self::InvalidRequestBody(message) => write!(f, "{}", message),
+            Self::Unauthorized => write!(f, "invalid admin token."),
+            Self::AdminDisabled => write!(f, "admin endpoints are disabled."),
+            Self::InvalidDataset(_) => write!(f, "failed to reload dataset. the previous dataset is still being served."),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UnknownStation(_) | Self::NoTimetable(_) | Self::UnknownTrip { .. } | Self::UnknownAlert(_) => StatusCode::NOT_FOUND,
+            Self::InvalidQueryParameter(_) | Self::InvalidRequestBody(_) => StatusCode::BAD_REQUEST,
+            Self::NoValidCalendar { .. } | Self::InvalidDataset(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::AdminDisabled => StatusCode::FORBIDDEN,
+            Self::MalformedData(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Self::MalformedData(cause) = self {
+            log::error!("{}", cause);
+        }
+        HttpResponse::build(self.status_code()).json(ResError {
+            code: self.code().to_string(),
+            message: self.to_string(),
+            details: self.details(),
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use actix_web::{body::to_bytes, http::StatusCode, ResponseError};
+    use chrono::NaiveDate;
+    use serde_json::{json, Value};
+    use super::AppError;
+
+    async fn body(error: &AppError) -> Value {
+        let bytes = to_bytes(error.error_response().into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn 時刻表がない駅は404でcodeとdetailsを返す() {
+        let error = AppError::NoTimetable("HAKODATEBUS_030023".to_string());
+        assert_eq!(StatusCode::NOT_FOUND, error.status_code());
+        assert_eq!(
+            json!({
+                "code": "NO_TIMETABLE",
+                "message": "station HAKODATEBUS_030023 has no timetable.",
+                "details": { "station_id": "HAKODATEBUS_030023" },
+            }),
+            body(&error).await
+        );
+    }
+
+    #[actix_web::test]
+    async fn 有効なカレンダーがない日は422で日付を返す() {
+        let error = AppError::NoValidCalendar {
+            station_id: "HAKODATEBUS_050004".to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 10, 2).unwrap(),
+        };
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, error.status_code());
+        assert_eq!(json!({ "station_id": "HAKODATEBUS_050004", "date": "2025-10-02" }), body(&error).await["details"]);
+    }
+
+    #[actix_web::test]
+    async fn データの不備は500で原因を返さない() {
+        let error = AppError::MalformedData("Ride HAKODATEBUS_X not found.".to_string());
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, error.status_code());
+        assert_eq!(
+            json!({
+                "code": "MALFORMED_DATA",
+                "message": "failed to calculate the response from the dataset.",
+                "details": null,
+            }),
+            body(&error).await
+        );
+    }
+}
@@ -1,10 +1,29 @@
+mod alert_store;
 mod common;
+mod config;
+mod dataset_store;
+mod error;
+mod realtime_store;
 mod v1;
 
-use actix_web::{App, HttpServer, middleware::Logger};
+use std::{thread, time::Duration};
+use actix_web::{App, HttpServer, middleware::Logger, web};
+use calc::dataset::Dataset;
 use v1::departures::{get_departures_by_station_id};
+use crate::alert_store::AlertStore;
+use crate::common::{json_config, query_config, query_datetime::operator_offset};
+use crate::config::AppConfig;
+use crate::dataset_store::DatasetStore;
+use crate::realtime_store::RealtimeStore;
+use crate::v1::admin::{add_alert, push_trip_updates, reload_dataset, remove_alert};
 use crate::v1::available_stations::available_stations;
 use crate::v1::health::v1_health;
+use crate::v1::journeys::get_journeys_between_stations;
+use crate::v1::nearby_stations::get_nearby_stations_by_coordinate;
+use crate::v1::station_search::search_stations_by_name;
+use crate::v1::station_timetable::get_station_timetable_by_id;
+use crate::v1::revisions::get_revisions_by_station_id;
+use crate::v1::trips::get_trip_by_id;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -15,17 +34,65 @@ async fn main() -> std::io::Result<()> {
     }
     env_logger::init();
 
-    HttpServer::new(|| {
+    let config = AppConfig::load().map_err(std::io::Error::other)?;
+    log::info!("serving dataset at {}", config.data_root_path);
+    // データは起動時に読み込み、すべてのworkerで共有する。以降は/v1/admin/reloadで読み直す
+    let dataset = Dataset::load(&config.data_root_path).map_err(std::io::Error::other)?;
+    let store = web::Data::new(DatasetStore::new(&config.data_root_path, dataset));
+    let realtime = web::Data::new(RealtimeStore::new(config.realtime_trip_updates.clone(), config.realtime_id_prefix.clone()));
+    let alerts = web::Data::new(AlertStore::new(config.realtime_alerts.clone(), config.realtime_id_prefix.clone(), operator_offset()));
+    if realtime.source().is_some() || alerts.source().is_some() {
+        // 取得できなくても時刻表どおりに配信できるので、起動は止めない
+        log::info!(
+            "polling realtime feeds (trip updates: {:?}, alerts: {:?}) every {}s",
+            realtime.source(),
+            alerts.source(),
+            config.realtime_interval_seconds
+        );
+        let (realtime, alerts) = (realtime.clone(), alerts.clone());
+        let interval = Duration::from_secs(config.realtime_interval_seconds);
+        // 1つのスレッドで取得するので、応答が止まったフィードは次の取得の時刻までに打ち切る
+        let timeout = interval;
+        thread::spawn(move || loop {
+            if realtime.source().is_some() && let Err(e) = realtime.refresh(timeout) {
+                log::warn!("failed to refresh realtime trip updates: {}", e);
+            }
+            if alerts.source().is_some() && let Err(e) = alerts.refresh(timeout) {
+                log::warn!("failed to refresh realtime alerts: {}", e);
+            }
+            thread::sleep(interval);
+        });
+    }
+    let bind_address = (config.host.clone(), config.port);
+    let config = web::Data::new(config);
+
+    HttpServer::new(move || {
         let logger = Logger::default();
 
         App::new()
             .wrap(logger)
+            .app_data(config.clone())
+            .app_data(store.clone())
+            .app_data(realtime.clone())
+            .app_data(alerts.clone())
+            .app_data(query_config())
+            .app_data(json_config())
             .service(v1_health)
             .service(available_stations)
             .service(get_departures_by_station_id)
+            .service(get_journeys_between_stations)
+            .service(search_stations_by_name)
+            .service(get_nearby_stations_by_coordinate)
+            .service(get_station_timetable_by_id)
+            .service(get_revisions_by_station_id)
+            .service(get_trip_by_id)
+            .service(reload_dataset)
+            .service(push_trip_updates)
+            .service(add_alert)
+            .service(remove_alert)
             // .service(deb)
     })
-    .bind(("127.0.0.1", 8080))?
+    .bind(bind_address)?
     .run()
     .await
 }
@@ -0,0 +1,120 @@
+use std::{error::Error, fs, io::Read, sync::Arc, time::Duration};
+use calc::realtime::trip_delays::TripDelays;
+use crate::common::snapshot::Snapshot;
+
+/// GTFS-RTのTripUpdatesから読み取った遅れを保持し、リクエストごとにそのスナップショットを渡す
+/// フィードの取得に失敗した場合は、最後に読めた遅れを使い続ける
+#[derive(Debug)]
+pub(crate) struct RealtimeStore {
+    /// フィードのURL (`http://`か`https://`で始まる) かファイルのパス。Noneなら管理用エンドポイントで受け取ったものだけを使う
+    source: Option<String>,
+    /// フィードのIDにつけるprefix (gtfsのimportと同じもの)
+    prefix: Option<String>,
+    current: Snapshot<TripDelays>,
+}
+
+impl RealtimeStore {
+    pub(crate) fn new(source: Option<String>, prefix: Option<String>) -> Self {
+        Self {
+            source,
+            prefix,
+            current: Snapshot::new(TripDelays::default()),
+        }
+    }
+
+    pub(crate) fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub(crate) fn current(&self) -> Arc<TripDelays> {
+        self.current.current()
+    }
+
+    /// sourceからフィードを取得し直して差し替える。URLの場合はtimeoutまでに取得できなければ諦める
+    pub(crate) fn refresh(&self, timeout: Duration) -> Result<Arc<TripDelays>, Box<dyn Error + Send + Sync + 'static>> {
+        let Some(source) = &self.source else {
+            return Err("realtime trip updates source is not configured.".into());
+        };
+        self.replace(&fetch_feed(source, timeout)?)
+    }
+
+    /// 受け取ったフィード (protobuf) で差し替える。読めなければ今の遅れを使い続ける
+    pub(crate) fn replace(&self, bytes: &[u8]) -> Result<Arc<TripDelays>, Box<dyn Error + Send + Sync + 'static>> {
+        let delays = TripDelays::decode(bytes, self.prefix.as_deref())?;
+        Ok(self.current.replace(delays))
+    }
+}
+
+/// GTFS-RTのフィードをURL (`http://`か`https://`で始まる) かファイルのパスから取得する
+/// URLの場合はHTTPで取得するのでブロックする。応答が止まっても取得し直せるように、timeoutで打ち切る
+pub(crate) fn fetch_feed(source: &str, timeout: Duration) -> Result<Vec<u8>, Box<dyn Error + Send + Sync + 'static>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let mut res = vec![];
+        agent.get(source).call()?.into_reader().read_to_end(&mut res)?;
+        Ok(res)
+    } else {
+        Ok(fs::read(source).map_err(|e| format!("failed to read {}: {}", source, e))?)
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::RealtimeStore;
+    use calc::realtime::feed::{FeedEntity, FeedMessage, TripDescriptor, TripUpdate};
+    use prost::Message;
+    use std::fs;
+    use std::time::Duration;
+
+    fn timeout() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn feed(trip_id: &str) -> Vec<u8> {
+        FeedMessage {
+            header: None,
+            entity: vec![FeedEntity {
+                id: Some("1".to_string()),
+                is_deleted: None,
+                alert: None,
+                trip_update: Some(TripUpdate {
+                    trip: Some(TripDescriptor { trip_id: Some(trip_id.to_string()), start_time: None, start_date: None, route_id: None }),
+                    stop_time_update: vec![],
+                    timestamp: None,
+                    delay: Some(300),
+                }),
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn ファイルのフィードを読み直し_読めなければ今の遅れを使い続ける() {
+        let path = std::env::temp_dir().join(format!("transfar-trip-updates-{}.pb", std::process::id()));
+        let store = RealtimeStore::new(Some(path.to_str().expect("utf-8 path").to_string()), Some("HAKODATEBUS".to_string()));
+        assert!(store.current().is_empty());
+        // ファイルがまだない
+        assert!(store.refresh(timeout()).is_err());
+
+        fs::write(&path, feed("55G-D-0756")).expect("write file");
+        let refreshed = store.refresh(timeout());
+        assert_eq!(1, refreshed.expect("feed should be read").len());
+        assert_eq!(1, store.current().len());
+
+        fs::write(&path, [0xff, 0xff]).expect("write file");
+        let failed = store.refresh(timeout());
+        fs::remove_file(&path).expect("remove file");
+
+        assert!(failed.is_err());
+        assert_eq!(1, store.current().len());
+    }
+
+    #[test]
+    fn 取得元がなければ受け取ったフィードだけを使う() {
+        let store = RealtimeStore::new(None, None);
+        assert!(store.refresh(timeout()).is_err());
+        assert_eq!(1, store.replace(&feed("55G-D-0756")).expect("feed should be read").len());
+        assert_eq!(1, store.current().len());
+    }
+}
@@ -0,0 +1,147 @@
+use actix_web::{delete, http::header::AUTHORIZATION, post, web::{self, Bytes, Json}, HttpRequest, HttpResponse};
+use chrono::SecondsFormat;
+use subtle::ConstantTimeEq;
+use models::{alert::RawAlert, response::{realtime::ResRealtime, reload::ResReload}};
+use crate::{alert_store::AlertStore, config::AppConfig, dataset_store::DatasetStore, error::AppError, realtime_store::RealtimeStore};
+
+/// 管理用エンドポイントの認証。設定されたトークンと`Authorization: Bearer <token>`が一致すれば通す
+/// 何文字目まで一致したかを応答時間から推測されないように、トークンは定数時間で比べる
+pub(crate) fn authorize_admin(req: &HttpRequest, config: &AppConfig) -> Result<(), AppError> {
+    let Some(admin_token) = &config.admin_token else {
+        return Err(AppError::AdminDisabled);
+    };
+    let given = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "));
+    if given.is_some_and(|x| bool::from(x.as_bytes().ct_eq(admin_token.as_bytes()))) {
+        Ok(())
+    } else {
+        Err(AppError::Unauthorized)
+    }
+}
+
+#[post("/v1/admin/reload")]
+pub(crate) async fn reload_dataset(
+    req: HttpRequest,
+    config: web::Data<AppConfig>,
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResReload>, actix_web::error::Error> {
+    authorize_admin(&req, &config)?;
+
+    // 読み込みには時間がかかるので、workerをブロックしないように別スレッドで行う
+    let reloaded = {
+        let store = store.clone();
+        web::block(move || store.reload()).await?
+    };
+    match reloaded {
+        Ok(dataset) => {
+            log::info!("reloaded dataset at {}", dataset.data_root_path());
+            Ok(Json(ResReload {
+                stations: dataset.stations().count(),
+                rides: dataset.rides().count(),
+            }))
+        }
+        Err(e) => {
+            log::warn!("{}", e);
+            Err(AppError::InvalidDataset(e.errors).into())
+        }
+    }
+}
+
+/// GTFS-RTのTripUpdatesのフィード (protobuf) を受け取り、遅れを差し替える
+#[post("/v1/admin/realtime/trip-updates")]
+pub(crate) async fn push_trip_updates(
+    req: HttpRequest,
+    body: Bytes,
+    config: web::Data<AppConfig>,
+    realtime: web::Data<RealtimeStore>,
+) -> Result<Json<ResRealtime>, AppError> {
+    authorize_admin(&req, &config)?;
+
+    match realtime.replace(&body) {
+        Ok(delays) => {
+            log::info!("received trip updates of {} trips", delays.len());
+            Ok(Json(ResRealtime {
+                trips: delays.len(),
+                timestamp: delays.timestamp.map(|x| x.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            }))
+        }
+        Err(e) => Err(AppError::InvalidRequestBody(format!("failed to decode trip updates: {}", e))),
+    }
+}
+
+/// 運休や駅の閉鎖、お知らせを登録する。同じalert_idのものがあれば置き換える
+#[post("/v1/admin/alerts")]
+pub(crate) async fn add_alert(
+    req: HttpRequest,
+    body: Json<RawAlert>,
+    config: web::Data<AppConfig>,
+    alerts: web::Data<AlertStore>,
+) -> Result<Json<RawAlert>, AppError> {
+    authorize_admin(&req, &config)?;
+
+    let alert = alerts.add(body.into_inner()).map_err(AppError::InvalidRequestBody)?;
+    log::info!("added alert {}", alert.alert_id);
+    Ok(Json(alert.to_raw()))
+}
+
+/// 登録した運行情報を取り消す
+#[delete("/v1/admin/alerts/{alert_id}")]
+pub(crate) async fn remove_alert(
+    req: HttpRequest,
+    alert_id: web::Path<String>,
+    config: web::Data<AppConfig>,
+    alerts: web::Data<AlertStore>,
+) -> Result<HttpResponse, AppError> {
+    authorize_admin(&req, &config)?;
+
+    match alerts.remove(&alert_id) {
+        Some(_) => {
+            log::info!("removed alert {}", alert_id);
+            Ok(HttpResponse::NoContent().finish())
+        }
+        None => Err(AppError::UnknownAlert(alert_id.into_inner())),
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::authorize_admin;
+    use crate::{config::AppConfig, error::AppError};
+    use actix_web::{http::header::AUTHORIZATION, test::TestRequest};
+
+    fn config(admin_token: Option<&str>) -> AppConfig {
+        AppConfig {
+            data_root_path: "../data".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            admin_token: admin_token.map(|x| x.to_string()),
+            realtime_trip_updates: None,
+            realtime_alerts: None,
+            realtime_id_prefix: None,
+            realtime_interval_seconds: 60,
+        }
+    }
+
+    fn authorize(header: Option<&str>, admin_token: Option<&str>) -> Result<(), AppError> {
+        let req = match header {
+            Some(header) => TestRequest::default().insert_header((AUTHORIZATION, header)),
+            None => TestRequest::default(),
+        };
+        authorize_admin(&req.to_http_request(), &config(admin_token))
+    }
+
+    #[test]
+    fn トークンが一致するときだけ通す() {
+        assert_eq!(Ok(()), authorize(Some("Bearer secret"), Some("secret")));
+        assert_eq!(Err(AppError::Unauthorized), authorize(Some("Bearer secreT"), Some("secret")));
+        assert_eq!(Err(AppError::Unauthorized), authorize(Some("Bearer secre"), Some("secret")));
+        assert_eq!(Err(AppError::Unauthorized), authorize(Some("Bearer secrets"), Some("secret")));
+        assert_eq!(Err(AppError::Unauthorized), authorize(Some("secret"), Some("secret")));
+        assert_eq!(Err(AppError::Unauthorized), authorize(None, Some("secret")));
+        assert_eq!(Err(AppError::AdminDisabled), authorize(Some("Bearer secret"), None));
+    }
+}
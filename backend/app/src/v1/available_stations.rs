@@ -1,16 +1,17 @@
-use std::path::Path;
-use actix_web::{error, get, web::Json};
+use actix_web::{get, web::{self, Json}};
 use calc::valid_station::get_valid_stations;
-use models::{id::ROOT_PATH, response::{station::ResStation, stations::ResStations}};
+use models::response::{station::ResStation, stations::ResStations};
+use crate::dataset_store::DatasetStore;
+use crate::error::AppError;
 
 #[get("/v1/available_stations")]
-pub(crate) async fn available_stations() -> Result<Json<ResStations>, actix_web::error::Error> {
-    let valid_stations = get_valid_stations(&Path::new(ROOT_PATH).to_path_buf());
-    if valid_stations.is_err() {
-        return Err(error::ErrorInternalServerError("failed to calculate available station."));
-    }
+pub(crate) async fn available_stations(
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResStations>, AppError> {
+    let dataset = store.current();
+    let valid_stations = get_valid_stations(&dataset);
 
-    let available = valid_stations.unwrap()
+    let available = valid_stations
         .iter()
         .map(|x| ResStation::from(x.clone()))
         .collect::<Vec<ResStation>>();
@@ -18,4 +19,4 @@ pub(crate) async fn available_stations() -> Result<Json<ResStations>, actix_web:
         stations: available
     };
     Ok(Json(res))
-}
\ No newline at end of file
+}
@@ -1,22 +1,67 @@
-use actix_web::{error, get, web::{self, Json}};
-use calc::departures::get_departures;
-use chrono::{DateTime, Local};
-use models::response::departures::ResDepartures;
+use actix_web::{get, web::{self, Json}};
+use calc::departures::{get_departures, DepartureCursor, DepartureOptions, DEFAULT_DEPARTURE_LIMIT, MAX_DEPARTURE_LIMIT, MAX_WINDOW_MINUTES};
+use models::{response::departures::ResDepartures, ride::CareerType};
+use serde::Deserialize;
+use crate::common::{check_timetable_around, find_station, query_datetime::{operator_now, DateTimeQuery}};
+use crate::alert_store::AlertStore;
+use crate::dataset_store::DatasetStore;
+use crate::realtime_store::RealtimeStore;
+use crate::error::AppError;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct DepartureQuery {
+    limit: Option<usize>,
+    window_minutes: Option<i64>,
+    ride_type: Option<String>,
+    career_type: Option<CareerType>,
+    /// この駅の方面へ行く便だけを返す
+    direction: Option<String>,
+    /// 前のレスポンスのnext_cursor
+    cursor: Option<String>,
+    #[serde(flatten)]
+    datetime: DateTimeQuery,
+}
 
 #[get("/v1/departures/{station_id}")]
 pub(crate) async fn get_departures_by_station_id(
     station_id: web::Path<String>,
-) -> Result<Json<ResDepartures>, actix_web::error::Error> {
-    // let local_datetime = Local::now();
-    // let res = get_departures(station_id.as_str(), local_datetime.into());
-    
-    let datetime = DateTime::parse_from_rfc3339("2025-04-25T08:00:00+09:00").expect("valid datetime");
-    let res = get_departures(station_id.as_str(), datetime);
-    
-    if res.is_err() {
-        println!{"{:?}", res.err().unwrap()};
-        Err(error::ErrorInternalServerError("failed to calculate timetable."))
-    } else {
-        Ok(Json(res.unwrap()))
+    query: web::Query<DepartureQuery>,
+    store: web::Data<DatasetStore>,
+    realtime: web::Data<RealtimeStore>,
+    alerts: web::Data<AlertStore>,
+) -> Result<Json<ResDepartures>, AppError> {
+    let dataset = store.current();
+    let datetime = query.datetime.resolve(operator_now()).map_err(AppError::InvalidQueryParameter)?;
+    let limit = query.limit.unwrap_or(DEFAULT_DEPARTURE_LIMIT);
+    if limit == 0 {
+        return Err(AppError::InvalidQueryParameter("`limit` must be greater than 0.".to_string()));
+    }
+    if limit > MAX_DEPARTURE_LIMIT {
+        return Err(AppError::InvalidQueryParameter(format!("`limit` must be {} or less.", MAX_DEPARTURE_LIMIT)));
     }
+    if query.window_minutes.is_some_and(|x| x <= 0) {
+        return Err(AppError::InvalidQueryParameter("`window_minutes` must be greater than 0.".to_string()));
+    }
+    if query.window_minutes.is_some_and(|x| x > MAX_WINDOW_MINUTES) {
+        return Err(AppError::InvalidQueryParameter(format!("`window_minutes` must be {} or less.", MAX_WINDOW_MINUTES)));
+    }
+    let cursor = query.cursor.as_deref().map(DepartureCursor::parse).transpose().map_err(AppError::InvalidQueryParameter)?;
+    let direction = match &query.direction {
+        Some(direction) => Some(find_station(&dataset, direction)?.station_id),
+        None => None,
+    };
+    let station = find_station(&dataset, &station_id)?;
+    check_timetable_around(&dataset, &station, datetime.date_naive())?;
+
+    let options = DepartureOptions {
+        limit,
+        window_minutes: query.window_minutes,
+        ride_type: query.ride_type.clone(),
+        career_type: query.career_type,
+        direction,
+        cursor,
+    };
+    get_departures(&dataset, &realtime.current(), &alerts.current(), station_id.as_str(), datetime, &options)
+        .map(Json)
+        .map_err(|e| AppError::MalformedData(format!("failed to calculate departures of {}: {}", station_id, e)))
 }
@@ -0,0 +1,42 @@
+use actix_web::{get, web::{self, Json}};
+use calc::{journey::{get_journeys, JourneyOptions, DEFAULT_JOURNEY_LIMIT}};
+use models::response::journeys::ResJourneys;
+use serde::Deserialize;
+use crate::common::{find_station, query_datetime::{operator_now, DateTimeQuery}};
+use crate::alert_store::AlertStore;
+use crate::dataset_store::DatasetStore;
+use crate::realtime_store::RealtimeStore;
+use crate::error::AppError;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct JourneyQuery {
+    from: String,
+    to: String,
+    limit: Option<usize>,
+    /// trueの場合は指定した時刻までに到着する経路を探す
+    arrive_by: Option<bool>,
+    #[serde(flatten)]
+    datetime: DateTimeQuery,
+}
+
+#[get("/v1/journeys")]
+pub(crate) async fn get_journeys_between_stations(
+    query: web::Query<JourneyQuery>,
+    store: web::Data<DatasetStore>,
+    realtime: web::Data<RealtimeStore>,
+    alerts: web::Data<AlertStore>,
+) -> Result<Json<ResJourneys>, AppError> {
+    let dataset = store.current();
+    let datetime = query.datetime.resolve(operator_now()).map_err(AppError::InvalidQueryParameter)?;
+    let limit = query.limit.unwrap_or(DEFAULT_JOURNEY_LIMIT);
+    if limit == 0 {
+        return Err(AppError::InvalidQueryParameter("`limit` must be greater than 0.".to_string()));
+    }
+    find_station(&dataset, &query.from)?;
+    find_station(&dataset, &query.to)?;
+
+    let options = JourneyOptions { limit, arrive_by: query.arrive_by.unwrap_or(false) };
+    get_journeys(&dataset, &realtime.current(), &alerts.current(), &query.from, &query.to, datetime, &options)
+        .map(Json)
+        .map_err(|e| AppError::MalformedData(format!("failed to calculate journeys from {} to {}: {}", query.from, query.to, e)))
+}
@@ -1,3 +1,10 @@
 pub(crate) mod health;
 pub(crate) mod available_stations;
-pub(crate) mod departures;
\ No newline at end of file
+pub(crate) mod departures;
+pub(crate) mod journeys;
+pub(crate) mod admin;
+pub(crate) mod station_timetable;
+pub(crate) mod trips;
+pub(crate) mod station_search;
+pub(crate) mod nearby_stations;
+pub(crate) mod revisions;
\ No newline at end of file
@@ -0,0 +1,34 @@
+use actix_web::{get, web::{self, Json}};
+use calc::nearby_station::get_nearby_stations;
+use models::response::nearby_stations::ResNearbyStations;
+use serde::Deserialize;
+use crate::dataset_store::DatasetStore;
+use crate::error::AppError;
+
+const DEFAULT_RADIUS_METERS: f64 = 500.0;
+const MAX_RADIUS_METERS: f64 = 5_000.0;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct NearbyQuery {
+    lat: f64,
+    lon: f64,
+    /// メートル
+    radius: Option<f64>,
+}
+
+#[get("/v1/stations/nearby")]
+pub(crate) async fn get_nearby_stations_by_coordinate(
+    query: web::Query<NearbyQuery>,
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResNearbyStations>, AppError> {
+    let dataset = store.current();
+    if !(-90.0..=90.0).contains(&query.lat) || !(-180.0..=180.0).contains(&query.lon) {
+        return Err(AppError::InvalidQueryParameter("`lat` must be in -90..=90 and `lon` in -180..=180.".to_string()));
+    }
+    let radius = query.radius.unwrap_or(DEFAULT_RADIUS_METERS);
+    if !(radius > 0.0 && radius <= MAX_RADIUS_METERS) {
+        return Err(AppError::InvalidQueryParameter(format!("`radius` must be greater than 0 and at most {}.", MAX_RADIUS_METERS)));
+    }
+
+    Ok(Json(get_nearby_stations(&dataset, (query.lat, query.lon), radius)))
+}
@@ -0,0 +1,24 @@
+use actix_web::{get, web::{self, Json}};
+use calc::revisions::get_revisions;
+use models::{id::ID, response::revisions::ResRevisions};
+use crate::common::{find_station, query_datetime::{operator_now, DateQuery}};
+use crate::dataset_store::DatasetStore;
+use crate::error::AppError;
+
+#[get("/v1/stations/{station_id}/revisions")]
+pub(crate) async fn get_revisions_by_station_id(
+    station_id: web::Path<String>,
+    query: web::Query<DateQuery>,
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResRevisions>, AppError> {
+    let dataset = store.current();
+    let date = query.resolve(operator_now()).map_err(AppError::InvalidQueryParameter)?;
+    let station = find_station(&dataset, &station_id)?;
+    if dataset.timetable(&station.station_id).is_none() {
+        return Err(AppError::NoTimetable(station.station_id.get_raw_id()));
+    }
+
+    get_revisions(&dataset, station_id.as_str(), date)
+        .map(Json)
+        .map_err(|e| AppError::MalformedData(format!("failed to get revisions of {}: {}", station_id, e)))
+}
@@ -0,0 +1,33 @@
+use actix_web::{get, web::{self, Json}};
+use calc::station_search::{search_stations, DEFAULT_SEARCH_LIMIT};
+use models::response::{station::ResStation, stations::ResStations};
+use serde::Deserialize;
+use crate::dataset_store::DatasetStore;
+use crate::error::AppError;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct StationSearchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[get("/v1/stations/search")]
+pub(crate) async fn search_stations_by_name(
+    query: web::Query<StationSearchQuery>,
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResStations>, AppError> {
+    let dataset = store.current();
+    if query.q.trim().is_empty() {
+        return Err(AppError::InvalidQueryParameter("`q` must not be empty.".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if limit == 0 {
+        return Err(AppError::InvalidQueryParameter("`limit` must be greater than 0.".to_string()));
+    }
+
+    let stations = search_stations(&dataset, &query.q, limit)
+        .into_iter()
+        .map(ResStation::from)
+        .collect::<Vec<ResStation>>();
+    Ok(Json(ResStations { stations }))
+}
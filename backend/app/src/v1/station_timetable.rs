@@ -0,0 +1,22 @@
+use actix_web::{get, web::{self, Json}};
+use calc::station_timetable::get_station_timetable;
+use models::response::station_timetable::ResStationTimetable;
+use crate::common::{check_timetable, find_station, query_datetime::{operator_now, DateQuery}};
+use crate::dataset_store::DatasetStore;
+use crate::error::AppError;
+
+#[get("/v1/stations/{station_id}/timetable")]
+pub(crate) async fn get_station_timetable_by_id(
+    station_id: web::Path<String>,
+    query: web::Query<DateQuery>,
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResStationTimetable>, AppError> {
+    let dataset = store.current();
+    let date = query.resolve(operator_now()).map_err(AppError::InvalidQueryParameter)?;
+    let station = find_station(&dataset, &station_id)?;
+    check_timetable(&dataset, &station, date)?;
+
+    get_station_timetable(&dataset, station_id.as_str(), date)
+        .map(Json)
+        .map_err(|e| AppError::MalformedData(format!("failed to calculate timetable of {}: {}", station_id, e)))
+}
@@ -0,0 +1,23 @@
+use actix_web::{get, web::{self, Json}};
+use calc::trip::get_trips;
+use models::response::trips::ResTrips;
+use crate::common::query_datetime::{operator_now, DateQuery};
+use crate::dataset_store::DatasetStore;
+use crate::error::AppError;
+
+#[get("/v1/trips/{trip_id}")]
+pub(crate) async fn get_trip_by_id(
+    trip_id: web::Path<String>,
+    query: web::Query<DateQuery>,
+    store: web::Data<DatasetStore>,
+) -> Result<Json<ResTrips>, AppError> {
+    let dataset = store.current();
+    let date = query.resolve(operator_now()).map_err(AppError::InvalidQueryParameter)?;
+
+    let trips = get_trips(&dataset, trip_id.as_str(), date)
+        .map_err(|e| AppError::MalformedData(format!("failed to reconstruct trip {}: {}", trip_id, e)))?;
+    if trips.trips.is_empty() {
+        return Err(AppError::UnknownTrip { trip_id: trip_id.to_string(), date });
+    }
+    Ok(Json(trips))
+}
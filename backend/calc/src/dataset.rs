@@ -0,0 +1,494 @@
+use chrono::NaiveDate;
+use models::{
+    calendar::{Calendar, PatternSource},
+    departure::DeparturePattern,
+    id::{CalendarId, DeparturePatternId, RideId, StationId, ID},
+    ride::Ride,
+    station::{RawStation, Station},
+    timetable::TimeTable,
+    transfar_rule::{TransfarRules, TRANSFAR_RULES_FILE},
+};
+use repositories::{
+    impls::station_repository::StationRepositoryImpl, traits::station_repository::StationRepository,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::nearby_station::StationIndex;
+use crate::raptor::{cache::NetworkCache, network::Network};
+use crate::realtime::{alerts::Alerts, trip_delays::TripDelays};
+
+/// データセットの読み込みに失敗したファイルとその理由の一覧
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetError {
+    pub errors: Vec<String>,
+}
+
+impl Display for DatasetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to load dataset:\n{}", self.errors.join("\n"))
+    }
+}
+
+impl Error for DatasetError {}
+
+/// ある駅のある日の発車パターンと、それを決めたカレンダー
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedPattern<'a> {
+    pub calendar: &'a Calendar,
+    pub pattern: &'a DeparturePattern,
+    pub source: PatternSource,
+}
+
+/// データセットのルート以下にあるファイルをすべて読み込み、IDごとに保持したもの
+/// 起動時に一度だけ読み込んでworker間で共有するので、リクエストの処理中にディスクを読むことはない
+#[derive(Debug, Default)]
+pub struct Dataset {
+    data_root_path: String,
+    stations: HashMap<String, Station>,
+    timetables: HashMap<String, TimeTable>,
+    calendars: HashMap<String, Calendar>,
+    patterns: HashMap<String, DeparturePattern>,
+    rides: HashMap<String, Ride>,
+    transfar_rules: TransfarRules,
+    /// 緯度経度から近くの駅を探すための索引
+    station_index: StationIndex,
+    /// 経路探索用に運行日ごとに構築したNetwork
+    networks: NetworkCache,
+}
+
+impl Dataset {
+    /// 読めなかったファイルがあれば、すべてまとめてエラーとして返す
+    pub fn load(data_root_path: &str) -> Result<Self, DatasetError> {
+        let mut dataset = Self {
+            data_root_path: data_root_path.to_string(),
+            ..Default::default()
+        };
+        let mut errors: Vec<String> = Vec::new();
+        let station_repository = StationRepositoryImpl::new(data_root_path);
+
+        for raw_id in list_ids(data_root_path, "station", &mut errors) {
+            match station_repository.find_by_id(StationId::new(raw_id.clone(), data_root_path)) {
+                Ok(station) => {
+                    dataset.stations.insert(raw_id, station);
+                }
+                Err(e) => errors.push(format!("station {}: {}", raw_id, e)),
+            }
+        }
+        for raw_id in list_ids(data_root_path, "timetable", &mut errors) {
+            match TimeTable::from_station_id(StationId::new(raw_id.clone(), data_root_path)) {
+                Ok(timetable) => {
+                    dataset.timetables.insert(raw_id, timetable);
+                }
+                Err(e) => errors.push(format!("timetable {}: {}", raw_id, e)),
+            }
+        }
+        for raw_id in list_ids(data_root_path, "calendar", &mut errors) {
+            match Calendar::from_id(CalendarId::new(raw_id.clone(), data_root_path)) {
+                Ok(calendar) => {
+                    dataset.calendars.insert(raw_id, calendar);
+                }
+                Err(e) => errors.push(format!("calendar {}: {}", raw_id, e)),
+            }
+        }
+        for raw_id in list_ids(data_root_path, "departure-pattern", &mut errors) {
+            match DeparturePattern::from_id(DeparturePatternId::new(raw_id.clone(), data_root_path)) {
+                Ok(pattern) => {
+                    dataset.patterns.insert(raw_id, pattern);
+                }
+                Err(e) => errors.push(format!("departure-pattern {}: {}", raw_id, e)),
+            }
+        }
+        for raw_id in list_ids(data_root_path, "ride", &mut errors) {
+            match Ride::from_id(RideId::new(raw_id.clone(), data_root_path)) {
+                Ok(ride) => {
+                    dataset.rides.insert(raw_id, ride);
+                }
+                Err(e) => errors.push(format!("ride {}: {}", raw_id, e)),
+            }
+        }
+        match TransfarRules::from_data_root(data_root_path) {
+            Ok(transfar_rules) => dataset.transfar_rules = transfar_rules,
+            Err(e) => errors.push(format!("{}: {}", TRANSFAR_RULES_FILE, e)),
+        }
+
+        errors.extend(dataset.validate());
+        dataset.station_index = StationIndex::new(dataset.stations.values());
+        if errors.is_empty() {
+            Ok(dataset)
+        } else {
+            Err(DatasetError { errors })
+        }
+    }
+
+    /// ファイル間の参照がすべて解決できるか確認し、解決できなかったものを返す
+    fn validate(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+        let mut check_station = |owner: String, id: &StationId| {
+            if !self.stations.contains_key(&id.get_raw_id()) {
+                errors.push(format!("{}: station {} not found", owner, id.get_raw_id()));
+            }
+        };
+        for (raw_id, station) in sorted(&self.stations) {
+            for joined in station.join.iter() {
+                check_station(format!("station {}", raw_id), joined);
+            }
+        }
+        for (raw_id, ride) in sorted(&self.rides) {
+            for stop in ride.route.iter().chain(std::iter::once(&ride.to)) {
+                check_station(format!("ride {}", raw_id), stop);
+            }
+        }
+        for (raw_id, _) in sorted(&self.timetables) {
+            if !self.stations.contains_key(raw_id) {
+                errors.push(format!("timetable {}: station {} not found", raw_id, raw_id));
+            }
+        }
+
+        for (raw_id, timetable) in sorted(&self.timetables) {
+            for version in timetable.versions().iter() {
+                if self.calendar(&version.calendar_id).is_none() {
+                    errors.push(format!("timetable {}: calendar {} not found", raw_id, version.calendar_id.get_raw_id()));
+                }
+            }
+        }
+        for (raw_id, calendar) in sorted(&self.calendars) {
+            for pattern_id in calendar.pattern_ids() {
+                if self.pattern(pattern_id).is_none() {
+                    errors.push(format!("calendar {}: departure-pattern {} not found", raw_id, pattern_id.get_raw_id()));
+                }
+            }
+        }
+        let transfar_rules = self.transfar_rules.raw();
+        let rule_stations = transfar_rules
+            .stations
+            .iter()
+            .map(|x| &x.station_id)
+            .chain(transfar_rules.station_pairs.iter().flat_map(|x| [&x.from_station_id, &x.to_station_id]));
+        for raw_id in rule_stations {
+            if !self.stations.contains_key(raw_id) {
+                errors.push(format!("{}: station {} not found", TRANSFAR_RULES_FILE, raw_id));
+            }
+        }
+        for (raw_id, pattern) in sorted(&self.patterns) {
+            for departure in pattern.departures.iter() {
+                if self.ride(&departure.ride_id).is_none() {
+                    errors.push(format!("departure-pattern {}: ride {} not found", raw_id, departure.ride_id.get_raw_id()));
+                }
+            }
+        }
+        errors.dedup();
+        errors
+    }
+
+    pub fn data_root_path(&self) -> &str {
+        &self.data_root_path
+    }
+
+    pub fn station(&self, id: &StationId) -> Option<&Station> {
+        self.stations.get(&id.get_raw_id())
+    }
+
+    pub fn stations(&self) -> impl Iterator<Item = &Station> {
+        self.stations.values()
+    }
+
+    pub fn timetable(&self, id: &StationId) -> Option<&TimeTable> {
+        self.timetables.get(&id.get_raw_id())
+    }
+
+    pub fn calendar(&self, id: &CalendarId) -> Option<&Calendar> {
+        self.calendars.get(&id.get_raw_id())
+    }
+
+    pub fn pattern(&self, id: &DeparturePatternId) -> Option<&DeparturePattern> {
+        self.patterns.get(&id.get_raw_id())
+    }
+
+    pub fn ride(&self, id: &RideId) -> Option<&Ride> {
+        self.rides.get(&id.get_raw_id())
+    }
+
+    pub fn rides(&self) -> impl Iterator<Item = (&String, &Ride)> {
+        self.rides.iter()
+    }
+
+    pub fn transfar_rules(&self) -> &TransfarRules {
+        &self.transfar_rules
+    }
+
+    /// coordinateからradius_meters以内にある、緯度経度がわかる駅を距離 (メートル) とともに近い順に返す
+    pub fn nearby_stations(&self, coordinate: (f64, f64), radius_meters: f64) -> Vec<(&Station, f64)> {
+        self.station_index
+            .within(coordinate, radius_meters)
+            .into_iter()
+            .filter_map(|(raw_id, distance)| self.stations.get(raw_id).map(|x| (x, distance)))
+            .collect()
+    }
+
+    /// 時刻表がある駅をすべて返す
+    pub fn valid_stations(&self) -> Vec<&Station> {
+        let mut res: Vec<&Station> = self
+            .stations
+            .values()
+            .filter(|x| self.timetables.contains_key(&x.station_id.get_raw_id()))
+            .collect();
+        res.sort_by_key(|x| x.station_id.build_path());
+        res
+    }
+
+    /// 駅のdateに有効な発車パターンを返す
+    pub fn pattern_on(
+        &self,
+        id: &StationId,
+        date: NaiveDate,
+    ) -> Result<&DeparturePattern, Box<dyn Error + Send + Sync + 'static>> {
+        self.resolve_pattern(id, date).map(|x| x.pattern)
+    }
+
+    /// 駅のdateに有効な発車パターンを、どのカレンダーのどの規則で決まったかと一緒に返す
+    pub fn resolve_pattern(
+        &self,
+        id: &StationId,
+        date: NaiveDate,
+    ) -> Result<ResolvedPattern<'_>, Box<dyn Error + Send + Sync + 'static>> {
+        let raw_id = id.get_raw_id();
+        let timetable = self.timetable(id).ok_or(format!("Timetable for {} not found.", raw_id))?;
+        let version = timetable
+            .get_valid_calendar(date)
+            .ok_or(format!("No valid calendar found for {}", raw_id))?;
+        let calendar = self
+            .calendar(&version.calendar_id)
+            .ok_or(format!("Calendar {} not found.", version.calendar_id.get_raw_id()))?;
+        let (pattern_id, source) = calendar.resolve_pattern(date);
+        let pattern = self
+            .pattern(&pattern_id)
+            .ok_or(format!("Departure pattern {} not found.", pattern_id.get_raw_id()))?;
+        Ok(ResolvedPattern { calendar, pattern, source })
+    }
+
+    /// 同じ駅か、otherのjoinにidが含まれていればtrue
+    pub fn is_same_station(&self, id: &StationId, other: &StationId) -> bool {
+        let raw_id = id.get_raw_id();
+        if raw_id == other.get_raw_id() {
+            return true;
+        }
+        match self.station(other) {
+            Some(other_station) => other_station.join.iter().any(|x| x.get_raw_id() == raw_id),
+            None => false,
+        }
+    }
+
+    /// dateの運行情報から構築したNetworkを返す。構築済みならそれを使い回す
+    pub fn network(&self, date: NaiveDate) -> Result<Arc<Network>, Box<dyn Error + Send + Sync + 'static>> {
+        self.networks.get_or_build(self, date)
+    }
+
+    /// dateのNetworkにリアルタイムの遅れと運休を反映したものを返す。同じフィードと運行情報を反映したものは使い回す
+    pub fn realtime_network(
+        &self,
+        date: NaiveDate,
+        delays: &TripDelays,
+        alerts: &Alerts,
+    ) -> Result<Arc<Network>, Box<dyn Error + Send + Sync + 'static>> {
+        self.networks.get_or_build_realtime(self, date, delays, alerts)
+    }
+}
+
+impl StationRepository for Dataset {
+    fn find_by_id(&self, id: StationId) -> Result<Station, Box<dyn Error + Send + Sync + 'static>> {
+        self.station(&id)
+            .cloned()
+            .ok_or(format!("Station {} not found.", id.get_raw_id()).into())
+    }
+
+    fn build_from_raw(&self, raw: RawStation) -> Station {
+        StationRepositoryImpl::new(&self.data_root_path).build_from_raw(raw)
+    }
+
+    fn check_is_valid(&self, station: Station) -> bool {
+        self.timetable(&station.station_id).is_some()
+    }
+}
+
+/// エラーの出力順を安定させるためにIDの順に並べる
+fn sorted<T>(map: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut res: Vec<(&String, &T)> = map.iter().collect();
+    res.sort_by_key(|(raw_id, _)| *raw_id);
+    res
+}
+
+/// データの種類ごとのディレクトリ以下にあるjsonファイルのIDを列挙する
+/// IDはディレクトリからの相対パスの"/"を"_"に置き換えたもの (ID::build_pathの逆)
+pub fn list_ids(data_root_path: &str, data_type_path: &str, errors: &mut Vec<String>) -> Vec<String> {
+    let type_root = Path::new(data_root_path).join(data_type_path);
+    let mut res: Vec<String> = Vec::new();
+    let mut stack = vec![type_root.clone()];
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(format!("{}: {}", dir.display(), e));
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if path.extension().is_some_and(|x| x == "json") {
+                let raw_id = path
+                    .strip_prefix(&type_root)
+                    .ok()
+                    .and_then(|x| x.with_extension("").to_str().map(|x| x.replace("/", "_")));
+                match raw_id {
+                    Some(raw_id) => res.push(raw_id),
+                    None => errors.push(format!("{}: invalid file name", path.display())),
+                }
+            }
+        }
+    }
+    res.sort();
+    res
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::Dataset;
+    use crate::{departures::{get_departures, DepartureOptions}, journey::{get_journeys, JourneyOptions}, realtime::{alerts::Alerts, trip_delays::TripDelays}};
+    use chrono::{DateTime, NaiveDate};
+    use crate::test_fixture::{calendar_json, write_dataset};
+    use models::id::{StationId, ID};
+
+    #[test]
+    fn リポジトリのデータをエラーなく読み込める() {
+        let dataset = Dataset::load("../data").expect("dataset should be valid");
+        let station_id = StationId::new("HAKODATEBUS_050004".to_string(), "../data");
+        let date = NaiveDate::from_ymd_opt(2025, 4, 25).expect("valid date");
+
+        assert!(dataset.station(&station_id).is_some());
+        assert!(!dataset.pattern_on(&station_id, date).expect("pattern should exist").departures.is_empty());
+        assert!(dataset.valid_stations().iter().all(|x| dataset.timetable(&x.station_id).is_some()));
+    }
+
+    #[test]
+    fn 読めないファイルはすべてエラーとして報告する() {
+        let actual = write_dataset("dataset", &[("station/A/01.json", "{"), ("ride/B.json", "[]")]);
+
+        let errors = actual.expect_err("broken files should be reported").errors;
+        assert_eq!(2, errors.len());
+        assert!(errors[0].starts_with("station A_01"));
+        assert!(errors[1].starts_with("ride B"));
+    }
+
+    #[test]
+    fn 存在しないファイルを参照していればエラーとして報告する() {
+        let actual = write_dataset(
+            "dataset-ref",
+            &[
+                ("station/A/01.json", r#"{"station_id": "A_01", "name": "A", "join": ["A_02"], "pronounce": "えー"}"#),
+                ("timetable/A/01.json", r#"{"station_id": "A_01", "versions": [{"calendar_id": "A_01_0001", "valid_until": "2025/10/01"}]}"#),
+            ],
+        );
+
+        let expected = vec![
+            "station A_01: station A_02 not found".to_string(),
+            "timetable A_01: calendar A_01_0001 not found".to_string(),
+        ];
+        assert_eq!(expected, actual.expect_err("missing references should be reported").errors);
+    }
+
+    #[test]
+    fn 未知のcareer_typeとdeparture_typeは読み込み時にエラーになる() {
+        let actual = write_dataset(
+            "dataset-enum",
+            &[
+                (
+                    "ride/A/R.json",
+                    r##"{"ride_id": "A_R", "type": "R", "aka_type": "", "type_foreground": "#000000", "type_background": "#FFFFFF",
+                        "type_pronounce": "", "to": "A_01", "career_type": "SHIP", "route": ["A_01"]}"##,
+                ),
+                (
+                    "departure-pattern/A/0001.json",
+                    r#"{"pattern_id": "A_0001", "departures": [{"ride_id": "A_R", "trip_id": "1", "time": "08:00", "departure_type": "Both"}]}"#,
+                ),
+            ],
+        );
+
+        let errors = actual.expect_err("unknown values should be reported").errors;
+        assert_eq!(2, errors.len());
+        assert!(errors[0].starts_with("departure-pattern A_0001: unknown variant `Both`, expected one of `both`, `incoming`, `outgoing`"));
+        assert!(errors[1].starts_with("ride A_R: unknown variant `SHIP`, expected one of `BUS`"));
+    }
+
+    /// A_01を23:50に出て、日付をまたいでA_03に24:30に着く便だけがある。どの駅の時刻表もvalid_untilまで
+    fn overnight_dataset(name: &str, valid_until: &str) -> Dataset {
+        let mut files: Vec<(String, String)> = vec![(
+            "ride/A/R.json".to_string(),
+            r##"{"ride_id": "A_R", "type": "R", "aka_type": "", "type_foreground": "#000000", "type_background": "#FFFFFF",
+                "type_pronounce": "", "to": "A_03", "career_type": "BUS", "route": ["A_01", "A_02", "A_03"]}"##.to_string(),
+        )];
+        for (station, time) in [("01", "23:50"), ("02", "24:10"), ("03", "24:30")] {
+            files.push((
+                format!("station/A/{}.json", station),
+                format!(r#"{{"station_id": "A_{}", "name": "{}", "join": [], "pronounce": ""}}"#, station, station),
+            ));
+            files.push((
+                format!("timetable/A/{}.json", station),
+                format!(r#"{{"station_id": "A_{0}", "versions": [{{"calendar_id": "A_{0}", "valid_until": "{1}"}}]}}"#, station, valid_until),
+            ));
+            files.push((format!("calendar/A/{}.json", station), calendar_json(&format!("A_{}", station))));
+            files.push((
+                format!("departure-pattern/A/{}.json", station),
+                format!(r#"{{"pattern_id": "A_{}", "departures": [{{"ride_id": "A_R", "trip_id": "R-2350", "time": "{}"}}]}}"#, station, time),
+            ));
+        }
+        write_dataset(name, &files).expect("dataset should be valid")
+    }
+
+    #[test]
+    fn 日付をまたぐ便は翌日の0時以降の経路と発車に含まれる() {
+        let dataset = overnight_dataset("overnight", "2025/10/01");
+
+        // 運行日の23時から探すと、翌日の0時30分に着く
+        let evening = DateTime::parse_from_rfc3339("2025-04-25T23:00:00+09:00").expect("valid datetime");
+        let journeys = get_journeys(&dataset, &TripDelays::default(), &Alerts::default(), "A_01", "A_03", evening, &JourneyOptions::default()).expect("journeys").journeys;
+        assert_eq!(1, journeys.len());
+        assert_eq!(("23:50", "2025-04-25"), (journeys[0].depart_at.as_str(), journeys[0].depart_date.as_str()));
+        assert_eq!(Some("00:30".to_string()), journeys[0].arrive_at);
+        assert_eq!(Some("2025-04-26".to_string()), journeys[0].arrive_date);
+
+        // 翌日の0時5分から探しても、前日の運行日の便に乗れる
+        let midnight = DateTime::parse_from_rfc3339("2025-04-26T00:05:00+09:00").expect("valid datetime");
+        let journeys = get_journeys(&dataset, &TripDelays::default(), &Alerts::default(), "A_02", "A_03", midnight, &JourneyOptions { limit: 1, ..Default::default() }).expect("journeys").journeys;
+        assert_eq!(1, journeys.len());
+        assert_eq!(("00:10", "2025-04-26"), (journeys[0].depart_at.as_str(), journeys[0].depart_date.as_str()));
+        assert_eq!(Some("2025-04-26".to_string()), journeys[0].arrive_date);
+
+        // 発車は前日の運行日の便、その日の運行日の便 (翌日の0時10分) の順に並ぶ
+        let options = DepartureOptions { limit: 2, ..Default::default() };
+        let departures = get_departures(&dataset, &TripDelays::default(), &Alerts::default(), "A_02", midnight, &options).expect("departures").departures;
+        let actual: Vec<(&str, &str)> = departures.iter().map(|x| (x.depart_at.as_str(), x.service_date.as_str())).collect();
+        assert_eq!(vec![("00:10", "2025-04-25"), ("00:10", "2025-04-26")], actual);
+    }
+    #[test]
+    fn 時刻表が切れた翌日の0時以降も前日の運行日の便を返す() {
+        let dataset = overnight_dataset("expired", "2025/04/25");
+
+        let midnight = DateTime::parse_from_rfc3339("2025-04-26T00:05:00+09:00").expect("valid datetime");
+        let departures = get_departures(&dataset, &TripDelays::default(), &Alerts::default(), "A_02", midnight, &DepartureOptions::default())
+            .expect("departures")
+            .departures;
+        let actual: Vec<(&str, &str)> = departures.iter().map(|x| (x.depart_at.as_str(), x.service_date.as_str())).collect();
+        assert_eq!(vec![("00:10", "2025-04-25")], actual);
+
+        // 前日の運行日にも有効なカレンダーがなければエラーにする
+        let next_night = DateTime::parse_from_rfc3339("2025-04-27T00:05:00+09:00").expect("valid datetime");
+        assert!(get_departures(&dataset, &TripDelays::default(), &Alerts::default(), "A_02", next_night, &DepartureOptions::default()).is_err());
+    }
+}
@@ -1,95 +1,361 @@
-use chrono::{DateTime, FixedOffset, NaiveDate, TimeDelta};
-use repositories::{impls::station_repository::StationRepositoryImpl, traits::station_repository::StationRepository};
-use std::{collections::HashMap, error::Error};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeDelta};
+use repositories::traits::station_repository::StationRepository;
+use std::error::Error;
+use std::fmt::Display;
 use models::{
-    calendar::Calendar,
-    departure::DeparturePattern,
+    departure::Departure,
     id::{StationId, ID},
-    response::{departure::ResDeparture, departures::ResDepartures, station::ResStation},
-    ride::Ride,
-    timetable::TimeTable
+    response::{departure::ResDeparture, departures::ResDepartures},
+    ride::{CareerType, Ride},
+    service_time::ServiceTime,
 };
-use crate::transfar::calc_transfars;
+use crate::dataset::Dataset;
+use crate::raptor::network::route_position;
+use crate::realtime::alerts::Alerts;
+use crate::realtime::trip_delays::{RealtimeStatus, TripDelays};
+use crate::transfar::{calc_transfars, is_cancelled, notices, realtime_status};
 
-pub fn get_departures(raw_station_id: &str, datetime: DateTime<FixedOffset>) -> Result<ResDepartures, Box<dyn Error + Send + Sync + 'static>> {
-    let station_id = StationId::new(raw_station_id.to_string());
-    let station_repository = StationRepositoryImpl;
-    let root_station = station_repository.from_id(station_id.clone())?;
-    if station_repository.check_is_valid(root_station.clone()) == false {
+pub const DEFAULT_DEPARTURE_LIMIT: usize = 6;
+pub const MAX_DEPARTURE_LIMIT: usize = 100;
+/// window_minutesの上限 (1日)
+pub const MAX_WINDOW_MINUTES: i64 = 24 * 60;
+
+/// 発車一覧の件数と絞り込みの条件
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepartureOptions {
+    pub limit: usize,
+    /// 検索時刻から何分後までの発車を返すか。Noneなら翌日の運行日の終わりまで
+    pub window_minutes: Option<i64>,
+    pub ride_type: Option<String>,
+    pub career_type: Option<CareerType>,
+    /// この駅 (joinでつながった駅を含む) に乗った駅より先で停車する便だけを返す
+    pub direction: Option<StationId>,
+    /// 前のページの最後の発車。これより後の発車から返す
+    pub cursor: Option<DepartureCursor>,
+}
+
+impl Default for DepartureOptions {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_DEPARTURE_LIMIT,
+            window_minutes: None,
+            ride_type: None,
+            career_type: None,
+            direction: None,
+            cursor: None,
+        }
+    }
+}
+
+/// 発車一覧の続きを取得するための位置。`20250425T1102_1_<trip_id>`の形で受け渡す
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DepartureCursor {
+    depart_at: NaiveDateTime,
+    loop_count: i32,
+    trip_id: String,
+}
+
+impl DepartureCursor {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let invalid = || format!("`cursor` is invalid: `{}`.", raw);
+        let mut parts = raw.splitn(3, '_');
+        let (Some(depart_at), Some(loop_count), Some(trip_id)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let depart_at = NaiveDateTime::parse_from_str(depart_at, "%Y%m%dT%H%M").map_err(|_| invalid())?;
+        let loop_count = loop_count.parse::<i32>().map_err(|_| invalid())?;
+        Ok(Self { depart_at, loop_count, trip_id: trip_id.to_string() })
+    }
+
+    /// timeはリアルタイムの遅れを反映した出発時刻
+    fn of(service_date: NaiveDate, departure: &Departure, time: ServiceTime) -> Self {
+        Self {
+            depart_at: time.datetime_on(service_date),
+            loop_count: departure.loop_count,
+            trip_id: departure.trip_id.clone(),
+        }
+    }
+}
+
+impl Display for DepartureCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}_{}", self.depart_at.format("%Y%m%dT%H%M"), self.loop_count, self.trip_id)
+    }
+}
+
+impl DepartureOptions {
+    /// 種別、乗り物、方面の条件に当てはまる便ならtrue
+    fn matches(&self, dataset: &Dataset, ride: &Ride, station_id: &StationId, departure: &Departure) -> bool {
+        if self.ride_type.as_ref().is_some_and(|x| *x != ride.ride_type) {
+            return false;
+        }
+        if self.career_type.is_some_and(|x| x != ride.career_type) {
+            return false;
+        }
+        let Some(direction) = &self.direction else {
+            return true;
+        };
+        let Some(position) = route_position(ride, station_id, departure.loop_count) else {
+            return false;
+        };
+        ride.route[position + 1..].iter().any(|x| dataset.is_same_station(x, direction) || dataset.is_same_station(direction, x))
+    }
+}
+
+/// datetime以降に駅を出発する便を、出発が早い順にoptions.limitまで返す
+/// 前日の運行日の便が日付をまたいで出る場合と、翌日の運行日の便も含める
+/// リアルタイムの遅れがわかる便は、遅れを反映した時刻で絞り込んで並べる。運休や駅の閉鎖で取りやめになった発車は返さない
+pub fn get_departures(
+    dataset: &Dataset,
+    delays: &TripDelays,
+    alerts: &Alerts,
+    raw_station_id: &str,
+    datetime: DateTime<FixedOffset>,
+    options: &DepartureOptions,
+) -> Result<ResDepartures, Box<dyn Error + Send + Sync + 'static>> {
+    let station_id = StationId::new(raw_station_id.to_string(), dataset.data_root_path());
+    let root_station = dataset.find_by_id(station_id.clone())?;
+    if !dataset.check_is_valid(root_station.clone()) {
         return Err(format!{"Timetable for {} not found.", raw_station_id}.into())
     }
 
-    let root_timetable = TimeTable::from_station_id(station_id)?;
     let date: NaiveDate = datetime.date_naive();
-    let Some(calendar) = root_timetable.get_valid_calendar(date) else {
-        return Err(format!{"No valid calendar found for {}", raw_station_id}.into())
+    let now = datetime.naive_local();
+    let until = options
+        .window_minutes
+        .map(|x| TimeDelta::try_minutes(x).and_then(|y| now.checked_add_signed(y)).ok_or(format!("window_minutes {} is out of range.", x)))
+        .transpose()?;
+    // (出発の位置, 運行日, 出発, リアルタイムの情報) の組
+    let mut departures: Vec<(DepartureCursor, NaiveDate, &Departure, RealtimeStatus)> = Vec::new();
+    // 有効なカレンダーがない運行日は運行がないものとして扱い、どの運行日にもなければエラーにする
+    // 当日のカレンダーが切れていても、前日の運行日の便が日付をまたいで出ることがある
+    let mut found = false;
+    let mut error = None;
+    for service_date in [date.pred_opt(), Some(date), date.succ_opt()].into_iter().flatten() {
+        let pattern = match dataset.pattern_on(&station_id, service_date) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                if service_date == date {
+                    error = Some(e);
+                }
+                continue;
+            }
+        };
+        found = true;
+        for departure in pattern.departures.iter().filter(|x| x.departure_type.can_board()) {
+            if is_cancelled(dataset, alerts, departure, &station_id, service_date) {
+                continue;
+            }
+            let realtime = realtime_status(dataset, delays, departure, &station_id, service_date);
+            let cursor = DepartureCursor::of(service_date, departure, realtime.apply(departure.time));
+            if cursor.depart_at < now
+                || until.is_some_and(|x| cursor.depart_at > x)
+                || options.cursor.as_ref().is_some_and(|x| cursor <= *x)
+            {
+                continue;
+            }
+            let ride = dataset
+                .ride(&departure.ride_id)
+                .ok_or(format!("Ride {} not found.", departure.ride_id.get_raw_id()))?;
+            if options.matches(dataset, ride, &station_id, departure) {
+                departures.push((cursor, service_date, departure, realtime));
+            }
+        }
+    }
+    if !found {
+        return Err(error.unwrap_or_else(|| format!("No valid calendar for {} around {}.", raw_station_id, date).into()));
+    }
+    departures.sort_by(|x, y| x.0.cmp(&y.0));
+
+    // 続きがあるかを知るために1つ多く取り出す
+    let next_cursor = if departures.len() > options.limit {
+        Some(departures[options.limit - 1].0.to_string())
+    } else {
+        None
     };
+    departures.truncate(options.limit);
 
-    let pattern_id = Calendar::from_id(calendar.calendar_id)?.get_pattern_id(date);
-    let mut departure_pattern = DeparturePattern::from_id(pattern_id)?;
-    departure_pattern.departures.sort_by(|x, y| x.time.cmp(&y.time));
-    
-    // 出発が近いうちから最大6つ取得
-    let rideable_departures = departure_pattern
-        .departures
-        .into_iter()
-        .filter(|x| x.time.signed_duration_since(datetime.time()) >= TimeDelta::zero())
-        .filter(|x| x.departure_type == "both" || x.departure_type == "outgoing")
-        .take(6)
-        .collect::<Vec<_>>();
-    
     let mut res_departures: Vec<ResDeparture> = Vec::new();
-    let mut ride_cache: HashMap<String, Ride> = HashMap::new();
-    for departure in rideable_departures {
-        let ride: Ride = if ride_cache.contains_key(&departure.ride_id.get_raw_id()) {
-            ride_cache.get(&departure.ride_id.get_raw_id()).unwrap().to_owned()
-        } else {
-            let key = departure.ride_id.get_raw_id();
-            let _ride = Ride::from_id(key.clone())?;
-            ride_cache.insert(key, _ride.clone());
-            _ride
-        };
+    for (_, service_date, departure, realtime) in departures {
+        let ride = dataset
+            .ride(&departure.ride_id)
+            .ok_or(format!("Ride {} not found.", departure.ride_id.get_raw_id()))?
+            .clone();
         
-        let bound_for = station_repository.from_id(ride.to.clone())?;
+        let bound_for = dataset.find_by_id(ride.to.clone())?;
         let transfars = calc_transfars(
-            &departure,
+            dataset,
+            delays,
+            alerts,
+            departure,
             &root_station,
-            datetime
-        );
+            service_date
+        )?;
         res_departures.push( ResDeparture {
             ride_type: ride.ride_type,
             aka_type: if ride.aka_type.is_empty() { None } else { Some(ride.aka_type) },
             type_foreground: ride.type_foreground,
             type_background: ride.type_background,
             type_pronounce: ride.type_pronounce,
-            to: ResStation {
-                id: bound_for.station_id.get_raw_id(),
-                name: bound_for.name,
-                pronounce: bound_for.pronounce
-            },
+            to: bound_for.into(),
             career_type: ride.career_type,
-            depart_at: departure.time.format("%H:%M").to_string(),
-            transfars: transfars,
+            depart_at: realtime.apply(departure.time).wall_clock().format("%H:%M").to_string(),
+            scheduled_depart_at: departure.time.wall_clock().format("%H:%M").to_string(),
+            delay_minutes: realtime.delay_minutes(),
+            realtime: realtime.realtime,
+            service_date: service_date.format("%Y-%m-%d").to_string(),
+            alerts: notices(dataset, alerts, departure, &station_id, service_date),
+            transfars,
         });
     }
     
     Ok(ResDepartures {
-        departures: res_departures
+        departures: res_departures,
+        next_cursor,
     })
 }
 
+#[allow(non_snake_case)]
 #[cfg(test)]
 mod test {
-    use chrono::DateTime;
+    use chrono::{DateTime, FixedOffset};
+    use models::alert::{Alert, AlertEffect, AlertTarget};
+    use models::id::{StationId, ID};
+    use models::ride::CareerType;
 
-    use super::get_departures;
+    use super::{get_departures, DepartureCursor, DepartureOptions};
+    use crate::dataset::Dataset;
+    use crate::realtime::alerts::Alerts;
+    use crate::realtime::trip_delays::TripDelays;
+    use crate::test_fixture::delayed;
+
+    fn datetime() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2025-04-25T08:00:00+09:00").expect("valid datetime")
+    }
+
+    /// (運行日, 出発時刻) の一覧とnext_cursor
+    fn departures(dataset: &Dataset, options: &DepartureOptions) -> (Vec<(String, String)>, Option<String>) {
+        let res = get_departures(dataset, &TripDelays::default(), &Alerts::default(), "HAKODATEBUS_050004", datetime(), options).unwrap();
+        let departures = res.departures.into_iter().map(|x| (x.service_date, x.depart_at)).collect();
+        (departures, res.next_cursor)
+    }
+
+    fn pair(date: &str, time: &str) -> (String, String) {
+        (date.to_string(), time.to_string())
+    }
+
+    #[test]
+    fn cursorで続きの発車を翌日の運行日まで取得できる() {
+        let dataset = Dataset::load("../data").unwrap();
+        let (first, cursor) = departures(&dataset, &DepartureOptions { limit: 2, ..Default::default() });
+        assert_eq!(vec![pair("2025-04-25", "11:02"), pair("2025-04-25", "13:11")], first);
+        assert_eq!(Some("20250425T1311_1_HAKODATEBUS_55G-D-1311".to_string()), cursor);
+
+        let cursor = DepartureCursor::parse(&cursor.unwrap()).unwrap();
+        let (second, _) = departures(&dataset, &DepartureOptions { limit: 3, cursor: Some(cursor), ..Default::default() });
+        assert_eq!(vec![pair("2025-04-25", "14:10"), pair("2025-04-25", "15:47"), pair("2025-04-26", "07:56")], second);
+    }
+
+    #[test]
+    fn window_minutesより後の発車は返さない() {
+        let dataset = Dataset::load("../data").unwrap();
+        let (actual, cursor) = departures(&dataset, &DepartureOptions { window_minutes: Some(240), ..Default::default() });
+        assert_eq!(vec![pair("2025-04-25", "11:02")], actual);
+        assert_eq!(None, cursor);
+    }
+
+    #[test]
+    fn 大きすぎるwindow_minutesはエラーを返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        let options = DepartureOptions { window_minutes: Some(i64::MAX), ..Default::default() };
+        assert!(get_departures(&dataset, &TripDelays::default(), &Alerts::default(), "HAKODATEBUS_050004", datetime(), &options).is_err());
+    }
+
+    #[test]
+    fn 種別と乗り物と方面で絞り込める() {
+        let dataset = Dataset::load("../data").unwrap();
+        let ride_type = |x: &str| DepartureOptions { ride_type: Some(x.to_string()), ..Default::default() };
+        assert_eq!(6, departures(&dataset, &ride_type("55G")).0.len());
+        assert!(departures(&dataset, &ride_type("7A")).0.is_empty());
+
+        let career_types = |x: CareerType| {
+            let options = DepartureOptions { career_type: Some(x), ..Default::default() };
+            let res = get_departures(&dataset, &TripDelays::default(), &Alerts::default(), "HAKODATEBUS_050004", datetime(), &options).unwrap();
+            res.departures.into_iter().map(|x| x.career_type).collect::<Vec<_>>()
+        };
+        assert!(career_types(CareerType::Airplane).is_empty());
+        let bus = career_types(CareerType::Bus);
+        assert_eq!(6, bus.len());
+        assert!(bus.iter().all(|x| *x == CareerType::Bus));
+
+        let direction = |raw_id: &str| Some(StationId::new(raw_id.to_string(), dataset.data_root_path()));
+        let (forward, _) = departures(&dataset, &DepartureOptions { direction: direction("HAKODATEBUS_050010"), ..Default::default() });
+        assert_eq!(6, forward.len());
+        let (backward, _) = departures(&dataset, &DepartureOptions { direction: direction("HAKODATEBUS_050003"), ..Default::default() });
+        assert!(backward.is_empty());
+    }
+
+    #[test]
+    fn 遅れている便は遅れを反映した時刻で返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        // 07:56の便が10分遅れているので、08:00の検索でも乗れる
+        let delays = delayed("HAKODATEBUS_55G-D-0756", "HAKODATEBUS_050004", 600);
+        let options = DepartureOptions { limit: 2, ..Default::default() };
+        let actual = get_departures(&dataset, &delays, &Alerts::default(), "HAKODATEBUS_050004", datetime(), &options).unwrap().departures;
+
+        assert_eq!(2, actual.len());
+        assert_eq!(("08:06", "07:56", Some(10), true), (actual[0].depart_at.as_str(), actual[0].scheduled_depart_at.as_str(), actual[0].delay_minutes, actual[0].realtime));
+        assert_eq!(("11:02", None, false), (actual[1].depart_at.as_str(), actual[1].delay_minutes, actual[1].realtime));
+    }
+
+    fn alert(alert_id: &str, effect: AlertEffect, target: AlertTarget) -> Alert {
+        Alert {
+            alert_id: alert_id.to_string(),
+            effect,
+            targets: vec![target],
+            from: None,
+            until: None,
+            header: alert_id.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn 運休の便と閉鎖された駅での乗り継ぎを除き_お知らせをつける() {
+        let dataset = Dataset::load("../data").unwrap();
+        let options = DepartureOptions { limit: 2, ..Default::default() };
+        // 13:11の便は050005で乗り継ぎ、さらに030001で乗り継げる
+        let closed = "HAKODATEBUS_030001";
+
+        let alerts = Alerts::new(vec![
+            alert("cancel", AlertEffect::NoService, AlertTarget { trip_id: Some("HAKODATEBUS_55G-D-1102".to_string()), ..Default::default() }),
+            alert("closed", AlertEffect::NoService, AlertTarget { station_id: Some(closed.to_string()), ..Default::default() }),
+            alert("notice", AlertEffect::Notice, AlertTarget { ride_id: Some("HAKODATEBUS_55G-D".to_string()), ..Default::default() }),
+        ]);
+        let actual = get_departures(&dataset, &TripDelays::default(), &alerts, "HAKODATEBUS_050004", datetime(), &options).unwrap().departures;
+
+        // 11:02の便は運休なので13:11の便から返す
+        assert_eq!(vec!["13:11", "14:10"], actual.iter().map(|x| x.depart_at.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec!["notice"], actual[0].alerts.iter().map(|x| x.alert_id.as_str()).collect::<Vec<_>>());
+        // 乗り継ぎの先頭は乗った便そのもの
+        let transfars: Vec<_> = actual[0].transfars.iter().flat_map(|x| x.iter().skip(1)).collect();
+        assert!(!transfars.is_empty());
+        assert!(transfars.iter().all(|x| x.at.id != closed));
+        assert!(actual[0].transfars.iter().all(|x| x[0].alerts.len() == 1));
+    }
+
+    #[test]
+    fn 読めないcursorはエラーになる() {
+        assert!(DepartureCursor::parse("20250425T1311").is_err());
+        assert!(DepartureCursor::parse("2025-04-25_1_A").is_err());
+    }
 
 
     #[test]
     #[ignore = "comment out this ignore when you want to debug get_departures()"]
     fn debug_get_departures() {
-        let datetime = DateTime::parse_from_rfc3339("2025-04-25T08:00:00+09:00").expect("valid datetime");
-        let res = get_departures("HAKODATEBUS_050004", datetime);
+        let dataset = Dataset::load("../data").unwrap();
+        let res = get_departures(&dataset, &TripDelays::default(), &Alerts::default(), "HAKODATEBUS_050004", datetime(), &DepartureOptions::default());
         println!{"{:?}", res};
 
         assert_eq!(1, 1)
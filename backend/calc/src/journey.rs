@@ -0,0 +1,729 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use models::{
+    id::{RideId, StationId, ID},
+    response::{alert::ResAlert, journey::{ResJourney, ResJourneyLeg, ResLegType}, journeys::ResJourneys, station::ResStation},
+    service_time::ServiceTime,
+    station::Station,
+};
+use repositories::traits::station_repository::StationRepository;
+use std::cmp::Reverse;
+use std::error::Error;
+
+use crate::dataset::Dataset;
+use crate::realtime::{alerts::Alerts, trip_delays::{RealtimeStatus, TripDelays}};
+use crate::raptor::{
+    network::Network,
+    search::{raptor, raptor_arrive_by},
+};
+
+/// 1回のrange RAPTORで扱う出発時刻 (arrive_byでは到着時刻) の幅
+/// 幅ごとに経路を探し、limitより多く見つかるまで次の幅に進む
+const SEARCH_WINDOW_SECONDS: u32 = 2 * 60 * 60;
+pub const DEFAULT_JOURNEY_LIMIT: usize = 3;
+/// 歩く区間のride_type
+const WALK_RIDE_TYPE: &str = "徒歩";
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LegKind {
+    /// realtimeは乗る駅を出るときのリアルタイムの情報。previous_dayは前日の運行日の便ならtrue
+    Ride { trip_id: String, ride_id: RideId, realtime: RealtimeStatus, previous_day: bool },
+    /// 緯度経度から歩いて移れる駅へ歩く
+    Walk,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct JourneyLeg {
+    pub kind: LegKind,
+    /// 歩く区間では歩き始める駅
+    pub board_at: StationId,
+    /// Networkの日の運行日での時刻。前日から日付をまたいできた便も同じ日の時刻に直してある
+    pub depart_at: ServiceTime,
+    pub alight_at: StationId,
+    /// 降車駅に時刻表がない場合はNone
+    pub arrive_at: Option<ServiceTime>,
+    pub transfar_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Journey {
+    pub legs: Vec<JourneyLeg>,
+}
+
+impl Journey {
+    fn depart_at(&self) -> ServiceTime {
+        self.legs[0].depart_at
+    }
+
+    fn arrive_at(&self) -> Option<ServiceTime> {
+        self.legs.last().and_then(|x| x.arrive_at)
+    }
+
+    /// 到着時刻がわからない場合は最後に乗る便の出発時刻で代用する
+    fn sort_key(&self) -> ServiceTime {
+        let last = self.legs.last().expect("journey has at least one leg");
+        last.arrive_at.unwrap_or(last.depart_at)
+    }
+
+    /// 歩く区間は乗り換えに数えない
+    fn transfar_count(&self) -> usize {
+        self.legs.iter().filter(|x| x.kind != LegKind::Walk).count().saturating_sub(1)
+    }
+
+    /// 自分のほうが遅く出て早く着き、乗り換えも少ない (どれも同じではない) ならtrue
+    fn dominates(&self, other: &Journey) -> bool {
+        let not_worse = self.depart_at() >= other.depart_at()
+            && self.sort_key() <= other.sort_key()
+            && self.transfar_count() <= other.transfar_count();
+        let better = self.depart_at() > other.depart_at()
+            || self.sort_key() < other.sort_key()
+            || self.transfar_count() < other.transfar_count();
+        not_worse && better
+    }
+}
+
+/// 出発時刻の幅ごとにrange RAPTORで経路を探し、ほかの経路に劣らないものを到着が早い順に返す
+pub(crate) fn search_journeys(
+    network: &Network,
+    origin: &Station,
+    destination: &Station,
+    after: ServiceTime,
+    limit: usize,
+) -> Vec<Journey> {
+    let origins = network.expand(origin);
+    let destinations = network.expand(destination);
+    if origins.is_empty() || destinations.is_empty() || !origins.is_disjoint(&destinations) {
+        return vec![];
+    }
+    let access = network.walkable(&origins);
+
+    // 出発地から便が出る時刻ごとに探す
+    // 最も早く着くラベルだけを残すと、乗り換えの待ち時間の上限を超えて見つからないことがあるので、
+    // range RAPTORで遅く出る時刻から順に探す
+    let departures = network.departure_times(&access, after.seconds());
+    let mut res: Vec<Journey> = Vec::new();
+    let mut start = 0;
+    while start < departures.len() && res.len() <= limit {
+        let end = start + departures[start..].partition_point(|x| *x < departures[start] + SEARCH_WINDOW_SECONDS);
+        let found = raptor(network, &origins, &destinations, &departures[start..end]);
+        merge_journeys(&mut res, found);
+        start = end;
+    }
+
+    res.sort_by_key(|x| (x.sort_key(), x.transfar_count(), Reverse(x.depart_at())));
+    res.truncate(limit);
+    res
+}
+
+/// 到着時刻の幅ごとに逆向きのrange RAPTORで経路を探し、ほかの経路に劣らないものを出発が遅い順に返す
+/// 目的地に時刻表がない場合は、最後に乗る便がbefore以前に出るものを返す
+pub(crate) fn search_journeys_arrive_by(
+    network: &Network,
+    origin: &Station,
+    destination: &Station,
+    before: ServiceTime,
+    limit: usize,
+) -> Vec<Journey> {
+    let origins = network.expand(origin);
+    let destinations = network.expand(destination);
+    if origins.is_empty() || destinations.is_empty() || !origins.is_disjoint(&destinations) {
+        return vec![];
+    }
+    let egress = network.walkable(&destinations);
+
+    // 目的地に便が着く時刻ごとに、早い時刻から順に探す (理由はsearch_journeysと同じ)
+    let arrivals = network.arrival_times(&egress, before.seconds());
+    let mut res: Vec<Journey> = Vec::new();
+    let mut end = arrivals.len();
+    while end > 0 && res.len() <= limit {
+        let bound = arrivals[end - 1].saturating_sub(SEARCH_WINDOW_SECONDS);
+        let start = arrivals[..end].partition_point(|x| *x <= bound);
+        let found = raptor_arrive_by(network, &origins, &destinations, &arrivals[start..end]);
+        merge_journeys(&mut res, found);
+        end = start;
+    }
+
+    res.sort_by_key(|x| (Reverse(x.depart_at()), x.transfar_count(), x.sort_key()));
+    res.truncate(limit);
+    res
+}
+
+/// foundのうち、resのどの経路にも劣らないものをresに加え、foundの経路に劣るものをresから除く
+fn merge_journeys(res: &mut Vec<Journey>, found: Vec<Journey>) {
+    for journey in found {
+        res.retain(|x| !journey.dominates(x));
+        if !res.iter().any(|x| x.dominates(&journey) || *x == journey) {
+            res.push(journey);
+        }
+    }
+}
+
+fn format_time(time: ServiceTime) -> String {
+    time.wall_clock().format("%H:%M").to_string()
+}
+
+/// timeがdateの何日の時刻か。24時以降なら翌日になる
+fn format_date(date: NaiveDate, time: ServiceTime) -> String {
+    time.date_on(date).format("%Y-%m-%d").to_string()
+}
+
+/// 便に乗る駅と降りる駅に当てはまるお知らせ。同じお知らせは1つにまとめる
+fn leg_notices(alerts: &Alerts, leg: &JourneyLeg, ride_id: &RideId, trip_id: &str, service_date: NaiveDate) -> Vec<ResAlert> {
+    let mut res: Vec<ResAlert> = Vec::new();
+    for station_id in [&leg.board_at, &leg.alight_at] {
+        for alert in alerts.notices(&station_id.get_raw_id(), &ride_id.get_raw_id(), trip_id, service_date) {
+            if !res.iter().any(|x| x.alert_id == alert.alert_id) {
+                res.push(alert.into());
+            }
+        }
+    }
+    res
+}
+
+fn to_res_journey(
+    journey: &Journey,
+    network: &Network,
+    alerts: &Alerts,
+    station_repository: &dyn StationRepository,
+) -> Result<ResJourney, Box<dyn Error + Send + Sync + 'static>> {
+    let mut legs: Vec<ResJourneyLeg> = Vec::new();
+    for leg in journey.legs.iter() {
+        let at: ResStation = station_repository.find_by_id(leg.board_at.clone())?.into();
+        let to: ResStation = station_repository.find_by_id(leg.alight_at.clone())?.into();
+        let duration = leg.arrive_at.map(|x| x.minutes_since(leg.depart_at));
+        let res_leg = match &leg.kind {
+            LegKind::Ride { trip_id, ride_id, realtime, previous_day } => {
+                let ride = network.rides
+                    .get(&ride_id.get_raw_id())
+                    .ok_or(format!("Ride {} not found.", ride_id.get_raw_id()))?;
+                let service_date = if *previous_day { network.date.pred_opt() } else { Some(network.date) };
+                let notices = service_date
+                    .map(|x| leg_notices(alerts, leg, ride_id, trip_id, x))
+                    .unwrap_or_default();
+                ResJourneyLeg {
+                    leg_type: ResLegType::Ride,
+                    ride_type: ride.ride_type.clone(),
+                    type_foreground: ride.type_foreground.clone(),
+                    type_background: ride.type_background.clone(),
+                    at,
+                    to,
+                    career_type: Some(ride.career_type),
+                    depart_at: format_time(leg.depart_at),
+                    arrive_at: leg.arrive_at.map(format_time),
+                    duration,
+                    transfar_time: leg.transfar_time,
+                    delay_minutes: realtime.delay_minutes(),
+                    realtime: realtime.realtime,
+                    alerts: notices,
+                }
+            }
+            LegKind::Walk => ResJourneyLeg {
+                leg_type: ResLegType::Walk,
+                ride_type: WALK_RIDE_TYPE.to_string(),
+                type_foreground: "".to_string(),
+                type_background: "".to_string(),
+                at,
+                to,
+                career_type: None,
+                depart_at: format_time(leg.depart_at),
+                arrive_at: leg.arrive_at.map(format_time),
+                duration,
+                transfar_time: leg.transfar_time,
+                delay_minutes: None,
+                realtime: false,
+                alerts: vec![],
+            },
+        };
+        legs.push(res_leg);
+    }
+
+    Ok(ResJourney {
+        depart_at: format_time(journey.depart_at()),
+        depart_date: format_date(network.date, journey.depart_at()),
+        arrive_at: journey.arrive_at().map(format_time),
+        arrive_date: journey.arrive_at().map(|x| format_date(network.date, x)),
+        transfar_count: journey.transfar_count(),
+        legs,
+    })
+}
+
+/// 経路の件数と探し方
+#[derive(Debug, Clone, PartialEq)]
+pub struct JourneyOptions {
+    pub limit: usize,
+    /// trueの場合は検索時刻までに到着する経路を探す
+    pub arrive_by: bool,
+}
+
+impl Default for JourneyOptions {
+    fn default() -> Self {
+        Self { limit: DEFAULT_JOURNEY_LIMIT, arrive_by: false }
+    }
+}
+
+/// fromからtoへ、datetime以降に出発する経路を探す
+/// options.arrive_byがtrueの場合は、datetimeまでに到着する経路を探す
+/// 運行日のNetworkはcacheから取り出し、なければ構築する。リアルタイムの遅れや運休があれば反映したものを探す (これもcacheする)
+/// 運休の便と閉鎖された駅は使わず、乗る区間には乗る駅と降りる駅のお知らせをつける
+pub fn get_journeys(
+    dataset: &Dataset,
+    delays: &TripDelays,
+    alerts: &Alerts,
+    raw_from: &str,
+    raw_to: &str,
+    datetime: DateTime<FixedOffset>,
+    options: &JourneyOptions,
+) -> Result<ResJourneys, Box<dyn Error + Send + Sync + 'static>> {
+    let data_root_path = dataset.data_root_path();
+    let origin = dataset.find_by_id(StationId::new(raw_from.to_string(), data_root_path))?;
+    let destination = dataset.find_by_id(StationId::new(raw_to.to_string(), data_root_path))?;
+
+    let network = dataset.realtime_network(datetime.date_naive(), delays, alerts)?;
+    let journeys = if options.arrive_by {
+        search_journeys_arrive_by(&network, &origin, &destination, datetime.time().into(), options.limit)
+    } else {
+        search_journeys(&network, &origin, &destination, datetime.time().into(), options.limit)
+    };
+    let journeys = journeys
+        .iter()
+        .map(|x| to_res_journey(x, &network, alerts, dataset))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ResJourneys { journeys })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{search_journeys, search_journeys_arrive_by, LegKind};
+    use crate::realtime::{alerts::Alerts, trip_delays::{RealtimeStatus, TripDelays}};
+    use crate::raptor::network::{Network, Trip};
+    use crate::test_fixture::{self, delayed, ride, time, trip, trip_id};
+    use models::alert::{Alert, AlertEffect, AlertTarget};
+    use models::id::{ID, StationId};
+    use models::ride::{CareerType, Ride};
+    use models::station::Station;
+    use models::transfar_rule::TransfarRules;
+
+    fn station(raw_id: &str, join: &[&str]) -> Station {
+        Station {
+            station_id: StationId::new(raw_id.to_string(), ""),
+            name: raw_id.to_string(),
+            pronounce: "".to_string(),
+            join: join.iter().map(|x| StationId::new(x.to_string(), "")).collect(),
+            lat: None,
+            lon: None,
+        }
+    }
+
+    fn located(raw_id: &str, lat: f64, lon: f64) -> Station {
+        Station { lat: Some(lat), lon: Some(lon), ..station(raw_id, &[]) }
+    }
+
+    /// data/transfar-rules.jsonの乗り物ごとのルールと同じもの
+    fn transfar_rules(extra: &str) -> TransfarRules {
+        let raw = format!(
+            r#"{{"modes": [
+                {{"from_mode": "BUS", "to_mode": "BUS", "min": 0, "max": 150}},
+                {{"from_mode": "BUS", "to_mode": "AIRPLANE", "min": 30, "max": 240}}
+            ]{}}}"#,
+            extra
+        );
+        TransfarRules::from_raw(serde_json::from_str(&raw).expect("valid rules"))
+    }
+
+    fn network(trips: Vec<Trip>, rides: Vec<(&str, Ride)>, stations: &[Station]) -> Network {
+        network_with_rules(trips, rides, stations, transfar_rules(""))
+    }
+
+    fn network_with_rules(trips: Vec<Trip>, rides: Vec<(&str, Ride)>, stations: &[Station], rules: TransfarRules) -> Network {
+        test_fixture::network(trips, rides, stations, rules)
+    }
+
+    #[test]
+    fn 乗り換えを含む最速の経路を見つけられる() {
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10"), ("03", "08:20")]),
+                trip("B-0815", "B", &[("02", "08:15"), ("04", "08:30")]),
+                trip("C-0900", "C", &[("01", "09:00"), ("04", "09:10")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02", "03"])),
+                ("B", ride(CareerType::Bus, &["02", "04"])),
+                ("C", ride(CareerType::Bus, &["01", "04"])),
+            ],
+            &[],
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("04", &[]), time("07:50"), 3);
+        assert_eq!(2, actual.len());
+        assert_eq!(vec!["A-0800", "B-0815"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!(5, actual[0].legs[1].transfar_time);
+        assert_eq!(Some(time("08:30")), actual[0].arrive_at());
+        assert_eq!(vec!["C-0900"], actual[1].legs.iter().map(trip_id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn 最低乗り換え時間を満たさない乗り換えは使わない() {
+        let network = network(
+            vec![
+                trip("BUS-0800", "BUS", &[("01", "08:00"), ("AP", "08:40")]),
+                trip("AIR-0900", "AIR", &[("AP", "09:00")]),
+                trip("AIR-1000", "AIR", &[("AP", "10:00")]),
+            ],
+            vec![
+                ("BUS", ride(CareerType::Bus, &["01", "AP"])),
+                ("AIR", ride(CareerType::Airplane, &["AP", "FAR"])),
+            ],
+            &[],
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("FAR", &[]), time("07:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("AIR-1000", trip_id(&actual[0].legs[1]));
+        // 到着地に時刻表がないので到着時刻はわからない
+        assert_eq!(None, actual[0].arrive_at());
+    }
+
+    #[test]
+    fn joinでつながった駅で乗り換えられる() {
+        let stations = vec![station("AP", &["BUSSTOP"])];
+        let network = network(
+            vec![
+                trip("BUS-0800", "BUS", &[("01", "08:00"), ("BUSSTOP", "08:30")]),
+                trip("AIR-0915", "AIR", &[("AP", "09:15"), ("FAR", "10:30")]),
+            ],
+            vec![
+                ("BUS", ride(CareerType::Bus, &["01", "BUSSTOP"])),
+                ("AIR", ride(CareerType::Airplane, &["AP", "FAR"])),
+            ],
+            &stations,
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("FAR", &[]), time("07:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("AP", actual[0].legs[1].board_at.get_raw_id());
+        assert_eq!(Some(time("10:30")), actual[0].arrive_at());
+    }
+
+    #[test]
+    fn 駅ごとのルールは乗り物ごとのルールより優先される() {
+        // 空港でANAに乗るときだけ40分必要
+        let rules = transfar_rules(r#", "stations": [{"station_id": "AP", "to_agency": "ANA", "min": 40}]"#);
+        let network = network_with_rules(
+            vec![
+                trip("BUS-0800", "HB_BUS", &[("01", "08:00"), ("AP", "08:40")]),
+                trip("ANA-0915", "ANA_1", &[("AP", "09:15"), ("FAR", "10:30")]),
+                trip("ANA-0930", "ANA_1", &[("AP", "09:30"), ("FAR", "10:45")]),
+                trip("JAL-0915", "JAL_1", &[("AP", "09:15"), ("FAR", "10:35")]),
+            ],
+            vec![
+                ("HB_BUS", ride(CareerType::Bus, &["01", "AP"])),
+                ("ANA_1", ride(CareerType::Airplane, &["AP", "FAR"])),
+                ("JAL_1", ride(CareerType::Airplane, &["AP", "FAR"])),
+            ],
+            &[],
+            rules,
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("FAR", &[]), time("07:00"), 3);
+        assert_eq!(1, actual.len());
+        // ANA-0915は35分しかないので乗れない
+        assert_eq!("JAL-0915", trip_id(&actual[0].legs[1]));
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("FAR", &[]), time("10:40"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("JAL-0915", trip_id(&actual[0].legs[1]));
+    }
+
+    #[test]
+    fn 駅の組のルールはjoinでつながった駅の乗り換えに使われる() {
+        let stations = vec![station("TRAIN", &["BUSSTOP"])];
+        let rules = transfar_rules(r#", "station_pairs": [{"from_station_id": "BUSSTOP", "to_station_id": "TRAIN", "min": 20}]"#);
+        let network = network_with_rules(
+            vec![
+                trip("BUS-0800", "BUS", &[("01", "08:00"), ("BUSSTOP", "08:30")]),
+                trip("TRAIN-0840", "TRAIN", &[("TRAIN", "08:40"), ("FAR", "09:00")]),
+                trip("TRAIN-0900", "TRAIN", &[("TRAIN", "09:00"), ("FAR", "09:20")]),
+            ],
+            vec![
+                ("BUS", ride(CareerType::Bus, &["01", "BUSSTOP"])),
+                ("TRAIN", ride(CareerType::LtdExpTrain, &["TRAIN", "FAR"])),
+            ],
+            &stations,
+            rules,
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("FAR", &[]), time("07:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("TRAIN-0900", trip_id(&actual[0].legs[1]));
+        assert_eq!(Some(time("09:20")), actual[0].arrive_at());
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("FAR", &[]), time("09:30"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("TRAIN-0900", trip_id(&actual[0].legs[1]));
+    }
+
+    #[test]
+    fn 同じ時刻に着くならより遅く出発する経路だけを返す() {
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10")]),
+                trip("B-0830", "B", &[("02", "08:30"), ("03", "09:00")]),
+                trip("A-0815", "A", &[("01", "08:15"), ("02", "08:25")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02"])),
+                ("B", ride(CareerType::Bus, &["02", "03"])),
+            ],
+            &[],
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("03", &[]), time("07:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("A-0815", trip_id(&actual[0].legs[0]));
+    }
+
+    #[test]
+    fn arrive_byでは到着時刻までに着く最も遅い出発の経路を返す() {
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10")]),
+                trip("A-0830", "A", &[("01", "08:30"), ("02", "08:40")]),
+                trip("A-0900", "A", &[("01", "09:00"), ("02", "09:10")]),
+                trip("B-0850", "B", &[("02", "08:50"), ("03", "09:20")]),
+                trip("B-0950", "B", &[("02", "09:50"), ("03", "10:20")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02"])),
+                ("B", ride(CareerType::Bus, &["02", "03"])),
+            ],
+            &[],
+        );
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("03", &[]), time("10:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["A-0830", "B-0850"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!(10, actual[0].legs[1].transfar_time);
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("03", &[]), time("11:00"), 3);
+        assert_eq!(2, actual.len());
+        assert_eq!("A-0900", trip_id(&actual[0].legs[0]));
+        assert_eq!("A-0830", trip_id(&actual[1].legs[0]));
+    }
+
+    #[test]
+    fn arrive_byでも最低乗り換え時間を満たす便を選ぶ() {
+        let network = network(
+            vec![
+                trip("BUS-0800", "BUS", &[("01", "08:00"), ("AP", "08:40")]),
+                trip("BUS-0830", "BUS", &[("01", "08:30"), ("AP", "09:10")]),
+                trip("AIR-0930", "AIR", &[("AP", "09:30"), ("FAR", "10:45")]),
+            ],
+            vec![
+                ("BUS", ride(CareerType::Bus, &["01", "AP"])),
+                ("AIR", ride(CareerType::Airplane, &["AP", "FAR"])),
+            ],
+            &[],
+        );
+
+        // BUS-0830では飛行機まで20分しかないので乗り継げない
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("FAR", &[]), time("11:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["BUS-0800", "AIR-0930"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!(50, actual[0].legs[1].transfar_time);
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("FAR", &[]), time("10:30"), 3);
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn arrive_byで時刻表のない目的地は最後の便の出発時刻で判定する() {
+        let network = network(
+            vec![
+                trip("BUS-0800", "BUS", &[("01", "08:00"), ("AP", "08:40")]),
+                trip("AIR-0930", "AIR", &[("AP", "09:30")]),
+                trip("AIR-1200", "AIR", &[("AP", "12:00")]),
+            ],
+            vec![
+                ("BUS", ride(CareerType::Bus, &["01", "AP"])),
+                ("AIR", ride(CareerType::Airplane, &["AP", "FAR"])),
+            ],
+            &[],
+        );
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("FAR", &[]), time("11:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("AIR-0930", trip_id(&actual[0].legs[1]));
+        assert_eq!(None, actual[0].arrive_at());
+    }
+
+    #[test]
+    fn 乗り換えの待ち時間が上限を超える場合はあとの便から探し直す() {
+        let network = network(
+            vec![
+                trip("A-0500", "A", &[("01", "05:00"), ("02", "05:10")]),
+                trip("A-0700", "A", &[("01", "07:00"), ("02", "07:10")]),
+                trip("B-0800", "B", &[("02", "08:00"), ("03", "08:30")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02"])),
+                ("B", ride(CareerType::Bus, &["02", "03"])),
+            ],
+            &[],
+        );
+
+        // A-0500で着くとB-0800まで170分待つことになり、BUS同士の上限 (150分) を超える
+        let actual = search_journeys(&network, &station("01", &[]), &station("03", &[]), time("04:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("A-0700", trip_id(&actual[0].legs[0]));
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("03", &[]), time("09:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!("A-0700", trip_id(&actual[0].legs[0]));
+    }
+
+    #[test]
+    fn 遅れている便は遅れを反映した時刻で乗り換えを探す() {
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10")]),
+                trip("B-0815", "B", &[("02", "08:15"), ("03", "08:30")]),
+                trip("B-0845", "B", &[("02", "08:45"), ("03", "09:00")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02"])),
+                ("B", ride(CareerType::Bus, &["02", "03"])),
+            ],
+            &[],
+        );
+        let actual = search_journeys(&network, &station("01", &[]), &station("03", &[]), time("07:50"), 1);
+        assert_eq!(vec!["A-0800", "B-0815"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+
+        // A-0800が10分遅れて02に08:20に着くので、B-0815には乗り継げない
+        let delayed_network = network.with_realtime(&delayed("A-0800", "01", 600), &Alerts::default());
+        let actual = search_journeys(&delayed_network, &station("01", &[]), &station("03", &[]), time("07:50"), 1);
+        assert_eq!(vec!["A-0800", "B-0845"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!(Some(time("08:20")), actual[0].legs[0].arrive_at);
+        let LegKind::Ride { realtime, .. } = &actual[0].legs[0].kind else {
+            panic!("first leg should be a ride");
+        };
+        assert_eq!(RealtimeStatus { realtime: true, delay: Some(600) }, *realtime);
+    }
+
+    fn no_service(station_id: Option<&str>, trip_id: Option<&str>) -> Alerts {
+        Alerts::new(vec![Alert {
+            alert_id: "1".to_string(),
+            effect: AlertEffect::NoService,
+            targets: vec![AlertTarget {
+                station_id: station_id.map(|x| x.to_string()),
+                ride_id: None,
+                trip_id: trip_id.map(|x| x.to_string()),
+            }],
+            from: None,
+            until: None,
+            header: "運休".to_string(),
+            description: None,
+        }])
+    }
+
+    #[test]
+    fn 運休の便は経路に使わない() {
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10")]),
+                trip("A-0830", "A", &[("01", "08:30"), ("02", "08:40")]),
+            ],
+            vec![("A", ride(CareerType::Bus, &["01", "02"]))],
+            &[],
+        );
+
+        let cancelled = network.with_realtime(&TripDelays::default(), &no_service(None, Some("A-0800")));
+        let actual = search_journeys(&cancelled, &station("01", &[]), &station("02", &[]), time("07:50"), 3);
+        assert_eq!(vec!["A-0830"], actual.iter().map(|x| trip_id(&x.legs[0])).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn 閉鎖された駅では乗り換えない() {
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10"), ("03", "08:20")]),
+                trip("B-0815", "B", &[("02", "08:15"), ("04", "08:30")]),
+                trip("C-0825", "C", &[("03", "08:25"), ("04", "08:40")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02", "03"])),
+                ("B", ride(CareerType::Bus, &["02", "04"])),
+                ("C", ride(CareerType::Bus, &["03", "04"])),
+            ],
+            &[],
+        );
+
+        let closed = network.with_realtime(&TripDelays::default(), &no_service(Some("02"), None));
+        let actual = search_journeys(&closed, &station("01", &[]), &station("04", &[]), time("07:50"), 1);
+        assert_eq!(vec!["A-0800", "C-0825"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn 近くの駅へ歩いて乗り換えられる() {
+        // 02と05は約111m離れているので、80m/分で歩くと2分かかる
+        let stations = vec![located("02", 41.770, 140.720), located("05", 41.771, 140.720)];
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:10")]),
+                trip("B-0811", "B", &[("05", "08:11"), ("03", "08:25")]),
+                trip("B-0815", "B", &[("05", "08:15"), ("03", "08:30")]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02"])),
+                ("B", ride(CareerType::Bus, &["05", "03"])),
+            ],
+            &stations,
+        );
+
+        let actual = search_journeys(&network, &station("01", &[]), &station("03", &[]), time("07:50"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["A-0800", "WALK", "B-0815"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!((time("08:10"), Some(time("08:12"))), (actual[0].legs[1].depart_at, actual[0].legs[1].arrive_at));
+        assert_eq!(3, actual[0].legs[2].transfar_time);
+        assert_eq!(1, actual[0].transfar_count());
+
+        let actual = search_journeys_arrive_by(&network, &station("01", &[]), &station("03", &[]), time("09:00"), 3);
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["A-0800", "WALK", "B-0815"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!((time("08:10"), Some(time("08:12"))), (actual[0].legs[1].depart_at, actual[0].legs[1].arrive_at));
+    }
+
+    #[test]
+    fn 出発地と目的地から歩いて移れる駅の便を使える() {
+        let stations = vec![
+            located("HOME", 41.770, 140.720),
+            located("01", 41.771, 140.720),
+            located("02", 41.800, 140.720),
+            located("OFFICE", 41.801, 140.720),
+        ];
+        let network = network(
+            vec![
+                trip("A-0800", "A", &[("01", "08:00"), ("02", "08:20")]),
+                trip("A-0900", "A", &[("01", "09:00"), ("02", "09:20")]),
+            ],
+            vec![("A", ride(CareerType::Bus, &["01", "02"]))],
+            &stations,
+        );
+
+        // 07:59に出るとA-0800に間に合わない
+        let actual = search_journeys(&network, &stations[0], &stations[3], time("07:59"), 1);
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["WALK", "A-0900", "WALK"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        // 最初の便にちょうど間に合うように歩き始める
+        assert_eq!(time("08:58"), actual[0].depart_at());
+        assert_eq!(Some(time("09:22")), actual[0].arrive_at());
+        assert_eq!(0, actual[0].transfar_count());
+
+        let actual = search_journeys_arrive_by(&network, &stations[0], &stations[3], time("08:30"), 1);
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["WALK", "A-0800", "WALK"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!(time("07:58"), actual[0].depart_at());
+        assert_eq!(Some(time("08:22")), actual[0].arrive_at());
+    }
+}
@@ -0,0 +1,125 @@
+/// 拗音など2文字で1音になるかな。1文字のものより先に調べる
+const DIGRAPHS: &[(&str, &str)] = &[
+    ("きゃ", "kya"), ("きゅ", "kyu"), ("きょ", "kyo"),
+    ("ぎゃ", "gya"), ("ぎゅ", "gyu"), ("ぎょ", "gyo"),
+    ("しゃ", "sha"), ("しゅ", "shu"), ("しぇ", "she"), ("しょ", "sho"),
+    ("じゃ", "ja"), ("じゅ", "ju"), ("じぇ", "je"), ("じょ", "jo"),
+    ("ちゃ", "cha"), ("ちゅ", "chu"), ("ちぇ", "che"), ("ちょ", "cho"),
+    ("ぢゃ", "ja"), ("ぢゅ", "ju"), ("ぢょ", "jo"),
+    ("にゃ", "nya"), ("にゅ", "nyu"), ("にょ", "nyo"),
+    ("ひゃ", "hya"), ("ひゅ", "hyu"), ("ひょ", "hyo"),
+    ("びゃ", "bya"), ("びゅ", "byu"), ("びょ", "byo"),
+    ("ぴゃ", "pya"), ("ぴゅ", "pyu"), ("ぴょ", "pyo"),
+    ("みゃ", "mya"), ("みゅ", "myu"), ("みょ", "myo"),
+    ("りゃ", "rya"), ("りゅ", "ryu"), ("りょ", "ryo"),
+    ("てぃ", "ti"), ("でぃ", "di"), ("とぅ", "tu"), ("どぅ", "du"),
+    ("ふぁ", "fa"), ("ふぃ", "fi"), ("ふぇ", "fe"), ("ふぉ", "fo"),
+    ("うぃ", "wi"), ("うぇ", "we"), ("うぉ", "wo"),
+    ("ゔぁ", "va"), ("ゔぃ", "vi"), ("ゔぇ", "ve"), ("ゔぉ", "vo"),
+];
+
+const MONOGRAPHS: &[(char, &str)] = &[
+    ('あ', "a"), ('い', "i"), ('う', "u"), ('え', "e"), ('お', "o"),
+    ('か', "ka"), ('き', "ki"), ('く', "ku"), ('け', "ke"), ('こ', "ko"),
+    ('が', "ga"), ('ぎ', "gi"), ('ぐ', "gu"), ('げ', "ge"), ('ご', "go"),
+    ('さ', "sa"), ('し', "shi"), ('す', "su"), ('せ', "se"), ('そ', "so"),
+    ('ざ', "za"), ('じ', "ji"), ('ず', "zu"), ('ぜ', "ze"), ('ぞ', "zo"),
+    ('た', "ta"), ('ち', "chi"), ('つ', "tsu"), ('て', "te"), ('と', "to"),
+    ('だ', "da"), ('ぢ', "ji"), ('づ', "zu"), ('で', "de"), ('ど', "do"),
+    ('な', "na"), ('に', "ni"), ('ぬ', "nu"), ('ね', "ne"), ('の', "no"),
+    ('は', "ha"), ('ひ', "hi"), ('ふ', "fu"), ('へ', "he"), ('ほ', "ho"),
+    ('ば', "ba"), ('び', "bi"), ('ぶ', "bu"), ('べ', "be"), ('ぼ', "bo"),
+    ('ぱ', "pa"), ('ぴ', "pi"), ('ぷ', "pu"), ('ぺ', "pe"), ('ぽ', "po"),
+    ('ま', "ma"), ('み', "mi"), ('む', "mu"), ('め', "me"), ('も', "mo"),
+    ('や', "ya"), ('ゆ', "yu"), ('よ', "yo"),
+    ('ら', "ra"), ('り', "ri"), ('る', "ru"), ('れ', "re"), ('ろ', "ro"),
+    ('わ', "wa"), ('ゐ', "i"), ('ゑ', "e"), ('を', "o"), ('ん', "n"), ('ゔ', "vu"),
+    ('ぁ', "a"), ('ぃ', "i"), ('ぅ', "u"), ('ぇ', "e"), ('ぉ', "o"),
+    ('ゃ', "ya"), ('ゅ', "yu"), ('ょ', "yo"), ('ゎ', "wa"),
+];
+
+/// カタカナをひらがなにする。それ以外の文字はそのまま
+pub fn to_hiragana(raw: &str) -> String {
+    raw.chars()
+        .map(|x| match x {
+            'ァ'..='ヶ' => char::from_u32(x as u32 - 0x60).unwrap_or(x),
+            _ => x,
+        })
+        .collect()
+}
+
+/// ひらがな (カタカナも可) をヘボン式のローマ字にする。長音は母音を重ねて書く (`ちゅうおう` → `chuuou`)
+/// 長音符は前の母音を重ね、かな以外の文字は英数字だけ小文字にして残す
+pub fn to_romaji(raw: &str) -> String {
+    let chars: Vec<char> = to_hiragana(raw).chars().collect();
+    let mut res = String::new();
+    // 直前が促音 (っ) なら次の子音を重ねる
+    let mut geminate = false;
+    let mut index = 0;
+    while index < chars.len() {
+        let current = chars[index];
+        let syllable = chars
+            .get(index + 1)
+            .and_then(|next| {
+                let pair: String = [current, *next].iter().collect();
+                DIGRAPHS.iter().find(|(kana, _)| *kana == pair).map(|(_, romaji)| (*romaji, 2))
+            })
+            .or_else(|| MONOGRAPHS.iter().find(|(kana, _)| *kana == current).map(|(_, romaji)| (*romaji, 1)));
+
+        match (current, syllable) {
+            ('っ', _) => {
+                geminate = true;
+                index += 1;
+                continue;
+            }
+            ('ー', _) => {
+                if let Some(vowel) = res.chars().last().filter(|x| "aiueo".contains(*x)) {
+                    res.push(vowel);
+                }
+                index += 1;
+            }
+            (_, Some((romaji, length))) => {
+                if geminate {
+                    // `っち`は`tchi`と書く
+                    if romaji.starts_with("ch") {
+                        res.push('t');
+                    } else {
+                        res.extend(romaji.chars().next().filter(|x| !"aiueon".contains(*x)));
+                    }
+                }
+                res.push_str(romaji);
+                index += length;
+            }
+            (_, None) => {
+                if current.is_ascii_alphanumeric() {
+                    res.push(current.to_ascii_lowercase());
+                }
+                index += 1;
+            }
+        }
+        geminate = false;
+    }
+    res
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{to_hiragana, to_romaji};
+
+    #[test]
+    fn ひらがなをヘボン式のローマ字にする() {
+        assert_eq!("hakodate", to_romaji("はこだて"));
+        assert_eq!("kamedachuugakkoumae", to_romaji("かめだちゅうがっこうまえ"));
+        assert_eq!("shouwataaminaru", to_romaji("しょうわたーみなる"));
+        assert_eq!("matcha", to_romaji("まっちゃ"));
+        assert_eq!("jeieemae", to_romaji("じぇいえーまえ"));
+        assert_eq!("hakodate", to_romaji("ハコダテ"));
+    }
+
+    #[test]
+    fn カタカナをひらがなにする() {
+        assert_eq!("はこだてばすせんたー", to_hiragana("ハコダテバスセンター"));
+        assert_eq!("函館ばすせんたー", to_hiragana("函館バスセンター"));
+    }
+}
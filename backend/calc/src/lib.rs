@@ -1,3 +1,15 @@
 pub mod valid_station;
 pub mod departures;
-pub mod transfar;
\ No newline at end of file
+pub mod transfar;
+pub mod journey;
+pub mod raptor;
+pub mod dataset;
+pub mod station_timetable;
+pub mod trip;
+pub mod kana;
+pub mod station_search;
+pub mod nearby_station;
+pub mod realtime;
+pub mod revisions;
+#[cfg(test)]
+mod test_fixture;
\ No newline at end of file
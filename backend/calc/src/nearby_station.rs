@@ -0,0 +1,149 @@
+use models::{
+    response::nearby_stations::{ResNearbyStation, ResNearbyStations},
+    id::ID,
+    station::{distance_meters, Station},
+};
+use repositories::traits::station_repository::StationRepository;
+use std::collections::HashMap;
+
+use crate::dataset::Dataset;
+
+/// グリッドの1マスの大きさ (度)。緯度方向に約1.1km
+const CELL_DEGREES: f64 = 0.01;
+/// 緯度1度あたりの距離 (メートル)。検索するマスの範囲を決めるのに使う
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// 駅のIDと (緯度, 経度)
+type LocatedStation = (String, (f64, f64));
+
+/// 緯度経度がわかる駅を、緯度経度のグリッドのマスごとにまとめたもの
+/// データセットの読み込み時に一度だけ作り、半径の検索では近くのマスの駅だけ距離を計算する
+#[derive(Debug, Default)]
+pub struct StationIndex {
+    cells: HashMap<(i64, i64), Vec<LocatedStation>>,
+}
+
+impl StationIndex {
+    pub fn new<'a>(stations: impl Iterator<Item = &'a Station>) -> Self {
+        let mut cells: HashMap<(i64, i64), Vec<LocatedStation>> = HashMap::new();
+        for station in stations {
+            if let Some(coordinate) = station.coordinate() {
+                cells.entry(cell_of(coordinate)).or_default().push((station.station_id.get_raw_id(), coordinate));
+            }
+        }
+        Self { cells }
+    }
+
+    /// coordinateからradius_meters以内にある駅のIDを、距離 (メートル) とともに近い順に返す
+    pub fn within(&self, coordinate: (f64, f64), radius_meters: f64) -> Vec<(&str, f64)> {
+        let lat_span = radius_meters / METERS_PER_DEGREE;
+        let lon_span = radius_meters / (METERS_PER_DEGREE * coordinate.0.to_radians().cos().max(f64::EPSILON));
+        let (min_lat, max_lat) = (coordinate.0 - lat_span, coordinate.0 + lat_span);
+        let (min_lon, max_lon) = (coordinate.1 - lon_span, coordinate.1 + lon_span);
+        let (min_cell, max_cell) = (cell_of((min_lat, min_lon)), cell_of((max_lat, max_lon)));
+        let cell_count = (max_cell.0 - min_cell.0 + 1).saturating_mul(max_cell.1 - min_cell.1 + 1);
+
+        // 極の近くや経度180度をまたぐ範囲、駅のあるマスより広い範囲はすべてのマスを調べる
+        let candidates: Vec<&LocatedStation> =
+            if min_lon < -180.0 || 180.0 < max_lon || (self.cells.len() as i64) < cell_count {
+                self.cells.values().flatten().collect()
+            } else {
+                (min_cell.0..=max_cell.0)
+                    .flat_map(|lat| (min_cell.1..=max_cell.1).map(move |lon| (lat, lon)))
+                    .filter_map(|x| self.cells.get(&x))
+                    .flatten()
+                    .collect()
+            };
+
+        let mut res: Vec<(&str, f64)> = candidates
+            .into_iter()
+            .map(|(raw_id, station_coordinate)| (raw_id.as_str(), distance_meters(coordinate, *station_coordinate)))
+            .filter(|(_, distance)| *distance <= radius_meters)
+            .collect();
+        res.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(b.0)));
+        res
+    }
+}
+
+fn cell_of(coordinate: (f64, f64)) -> (i64, i64) {
+    ((coordinate.0 / CELL_DEGREES).floor() as i64, (coordinate.1 / CELL_DEGREES).floor() as i64)
+}
+
+/// coordinateからradius_meters以内にある駅を近い順に、時刻表があるかどうかとともに返す
+/// 緯度経度がわからない駅は返さない
+pub fn get_nearby_stations(dataset: &Dataset, coordinate: (f64, f64), radius_meters: f64) -> ResNearbyStations {
+    let stations = dataset
+        .nearby_stations(coordinate, radius_meters)
+        .into_iter()
+        .map(|(station, distance)| ResNearbyStation {
+            station: station.clone().into(),
+            distance_meters: distance.round() as u32,
+            has_timetable: dataset.check_is_valid(station.clone()),
+        })
+        .collect();
+    ResNearbyStations { stations }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{get_nearby_stations, StationIndex};
+    use crate::test_fixture::{calendar_json, write_dataset};
+    use models::{id::{StationId, ID}, station::Station};
+
+    fn located(raw_id: &str, lat: f64, lon: f64) -> Station {
+        Station {
+            station_id: StationId::new(raw_id.to_string(), "../data"),
+            name: raw_id.to_string(),
+            pronounce: String::new(),
+            join: vec![],
+            lat: Some(lat),
+            lon: Some(lon),
+        }
+    }
+
+    #[test]
+    fn 半径以内の駅を近い順に返す() {
+        // 緯度0.001度は約111m。01と02はグリッドの別のマスにある
+        let stations = [
+            located("A_01", 41.7705, 140.7200),
+            located("A_02", 41.7695, 140.7200),
+            located("A_03", 41.7730, 140.7200),
+            located("A_04", 41.9000, 140.7200),
+        ];
+        let index = StationIndex::new(stations.iter());
+
+        let actual = index.within((41.7702, 140.7200), 500.0);
+        assert_eq!(vec!["A_01", "A_02", "A_03"], actual.iter().map(|x| x.0).collect::<Vec<_>>());
+        assert!(actual.windows(2).all(|x| x[0].1 <= x[1].1));
+        assert!((actual[0].1 - 33.4).abs() < 1.0);
+
+        assert_eq!(vec!["A_01"], index.within((41.7702, 140.7200), 50.0).iter().map(|x| x.0).collect::<Vec<_>>());
+        assert_eq!(4, index.within((41.7702, 140.7200), 20_000.0).len());
+        assert!(index.within((0.0, 0.0), 500.0).is_empty());
+    }
+
+    #[test]
+    fn 近くの駅を時刻表があるかどうかとともに返す() {
+        let mut files: Vec<(String, String)> = vec![
+            (
+                "timetable/A/01.json".to_string(),
+                r#"{"station_id": "A_01", "versions": [{"calendar_id": "A_01", "valid_until": "2025/10/01"}]}"#.to_string(),
+            ),
+            ("calendar/A/01.json".to_string(), calendar_json("A_01")),
+            ("departure-pattern/A/01.json".to_string(), r#"{"pattern_id": "A_01", "departures": []}"#.to_string()),
+        ];
+        for (station, coordinate) in [("01", r#", "lat": 41.7705, "lon": 140.72"#), ("02", r#", "lat": 41.771, "lon": 140.72"#), ("03", "")] {
+            files.push((
+                format!("station/A/{}.json", station),
+                format!(r#"{{"station_id": "A_{}", "name": "{}", "join": [], "pronounce": ""{}}}"#, station, station, coordinate),
+            ));
+        }
+        let dataset = write_dataset("nearby", &files).expect("dataset should be valid");
+
+        let actual = get_nearby_stations(&dataset, (41.7702, 140.72), 500.0).stations;
+        assert_eq!(2, actual.len());
+        assert_eq!(("A_01".to_string(), 33, true), (actual[0].station.id.clone(), actual[0].distance_meters, actual[0].has_timetable));
+        assert_eq!(("A_02".to_string(), false), (actual[1].station.id.clone(), actual[1].has_timetable));
+    }
+}
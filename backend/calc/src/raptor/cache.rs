@@ -0,0 +1,158 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+use crate::dataset::Dataset;
+use crate::realtime::alerts::Alerts;
+use crate::realtime::trip_delays::TripDelays;
+
+use super::network::Network;
+
+/// 保持しておく運行日の数 (前後の日付をまたいだ検索や、翌日の検索に使う程度)
+const MAX_CACHED_DAYS: usize = 4;
+
+/// リアルタイムの情報を反映したNetworkと、反映したもの
+#[derive(Debug)]
+struct RealtimeNetwork {
+    /// 反映したフィードの日時。遅れがない場合はNone
+    timestamp: Option<DateTime<Utc>>,
+    alerts: Alerts,
+    network: Arc<Network>,
+}
+
+/// 運行日ごとのNetworkを保持する
+/// 同じ日の検索では構築済みのNetworkを使い回す
+#[derive(Debug, Default)]
+pub struct NetworkCache {
+    networks: Mutex<HashMap<NaiveDate, Arc<Network>>>,
+    /// 運行日ごとに、最後に反映したフィードの遅れと運行情報を反映したNetwork
+    realtime: Mutex<HashMap<NaiveDate, RealtimeNetwork>>,
+}
+
+impl NetworkCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_build(
+        &self,
+        dataset: &Dataset,
+        date: NaiveDate,
+    ) -> Result<Arc<Network>, Box<dyn Error + Send + Sync + 'static>> {
+        if let Some(network) = self.networks.lock().map_err(|e| e.to_string())?.get(&date) {
+            return Ok(network.clone());
+        }
+
+        // 構築には時間がかかるので、ロックを外してから構築する
+        let network = Arc::new(Network::from_dataset(dataset, date));
+        let mut networks = self.networks.lock().map_err(|e| e.to_string())?;
+        if networks.len() >= MAX_CACHED_DAYS && !networks.contains_key(&date) {
+            // 一番古い日付のものから捨てる
+            if let Some(oldest) = networks.keys().min().cloned() {
+                networks.remove(&oldest);
+            }
+        }
+        Ok(networks.entry(date).or_insert(network).clone())
+    }
+
+    /// dateのNetworkにdelaysの遅れとalertsの運休を反映したものを返す。同じ日時のフィードと同じ運行情報を反映したものは使い回す
+    /// 遅れがあるのにフィードの日時がわからない場合は同じフィードか判断できないので、毎回反映し直す
+    pub fn get_or_build_realtime(
+        &self,
+        dataset: &Dataset,
+        date: NaiveDate,
+        delays: &TripDelays,
+        alerts: &Alerts,
+    ) -> Result<Arc<Network>, Box<dyn Error + Send + Sync + 'static>> {
+        let network = self.get_or_build(dataset, date)?;
+        if delays.is_empty() && alerts.is_empty() {
+            return Ok(network);
+        }
+        let timestamp = if delays.is_empty() { None } else { delays.timestamp };
+        if !delays.is_empty() && timestamp.is_none() {
+            return Ok(Arc::new(network.with_realtime(delays, alerts)));
+        }
+        if let Some(cached) = self.realtime.lock().map_err(|e| e.to_string())?.get(&date)
+            && cached.timestamp == timestamp
+            && cached.alerts == *alerts {
+            return Ok(cached.network.clone());
+        }
+
+        // 反映もNetworkを複製するので、ロックを外してから反映する
+        let realtime = Arc::new(network.with_realtime(delays, alerts));
+        let mut networks = self.realtime.lock().map_err(|e| e.to_string())?;
+        if networks.len() >= MAX_CACHED_DAYS && !networks.contains_key(&date) {
+            // 一番古い日付のものから捨てる
+            if let Some(oldest) = networks.keys().min().cloned() {
+                networks.remove(&oldest);
+            }
+        }
+        // 古いフィードや運行情報を反映したものは使わないので置き換える
+        networks.insert(date, RealtimeNetwork { timestamp, alerts: alerts.clone(), network: realtime.clone() });
+        Ok(realtime)
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::NetworkCache;
+    use crate::dataset::Dataset;
+    use crate::realtime::{alerts::Alerts, trip_delays::TripDelays};
+    use crate::test_fixture::delayed;
+    use chrono::{DateTime, NaiveDate};
+    use models::alert::{Alert, AlertEffect, AlertTarget};
+    use std::sync::Arc;
+
+    fn delays(timestamp: Option<i64>) -> TripDelays {
+        let mut res = delayed("A-0800", "01", 60);
+        res.timestamp = timestamp.and_then(|x| DateTime::from_timestamp(x, 0));
+        res
+    }
+
+    #[test]
+    fn 同じ日時のフィードの遅れを反映したNetworkは使い回す() {
+        let dataset = Dataset::default();
+        let cache = NetworkCache::new();
+        let date = NaiveDate::from_ymd_opt(2025, 4, 25).expect("valid date");
+        let none = Alerts::default();
+
+        let first = cache.get_or_build_realtime(&dataset, date, &delays(Some(1745535600)), &none).expect("network");
+        let same = cache.get_or_build_realtime(&dataset, date, &delays(Some(1745535600)), &none).expect("network");
+        let updated = cache.get_or_build_realtime(&dataset, date, &delays(Some(1745535660)), &none).expect("network");
+        let untimed = cache.get_or_build_realtime(&dataset, date, &delays(None), &none).expect("network");
+        let base = cache.get_or_build_realtime(&dataset, date, &TripDelays::default(), &none).expect("network");
+
+        assert!(Arc::ptr_eq(&first, &same));
+        assert!(!Arc::ptr_eq(&first, &updated));
+        assert!(!Arc::ptr_eq(&updated, &untimed));
+        assert!(Arc::ptr_eq(&base, &cache.get_or_build(&dataset, date).expect("network")));
+    }
+
+    #[test]
+    fn 運行情報が変わったらNetworkを作り直す() {
+        let dataset = Dataset::default();
+        let cache = NetworkCache::new();
+        let date = NaiveDate::from_ymd_opt(2025, 4, 25).expect("valid date");
+        let alerts = |header: &str| {
+            Alerts::new(vec![Alert {
+                alert_id: "1".to_string(),
+                effect: AlertEffect::NoService,
+                targets: vec![AlertTarget { station_id: None, ride_id: None, trip_id: Some("A-0800".to_string()) }],
+                from: None,
+                until: None,
+                header: header.to_string(),
+                description: None,
+            }])
+        };
+
+        let first = cache.get_or_build_realtime(&dataset, date, &TripDelays::default(), &alerts("運休")).expect("network");
+        let same = cache.get_or_build_realtime(&dataset, date, &TripDelays::default(), &alerts("運休")).expect("network");
+        let updated = cache.get_or_build_realtime(&dataset, date, &TripDelays::default(), &alerts("終日運休")).expect("network");
+
+        assert!(Arc::ptr_eq(&first, &same));
+        assert!(!Arc::ptr_eq(&first, &updated));
+        assert!(!Arc::ptr_eq(&first, &cache.get_or_build(&dataset, date).expect("network")));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod cache;
+pub mod network;
+pub(crate) mod search;
@@ -0,0 +1,576 @@
+use chrono::NaiveDate;
+use models::{
+    departure::DepartureType,
+    id::{RideId, StationId, ID},
+    ride::{CareerType, Ride},
+    service_time::ServiceTime,
+    station::{distance_meters, Station},
+    transfar_rule::TransfarRules,
+};
+use std::collections::{HashMap, HashSet};
+
+use crate::dataset::Dataset;
+use crate::realtime::alerts::Alerts;
+use crate::realtime::trip_delays::{RealtimeStatus, TripDelays};
+
+/// Networkの日の0時からの経過秒数 (ServiceTime::seconds)。日付をまたぐ便は86400以上になる
+pub(crate) type Seconds = u32;
+
+/// 便がある駅に停車する情報 (発車パターンの1行に相当する)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TripStop {
+    pub station_id: StationId,
+    pub time: ServiceTime,
+    pub loop_count: i32,
+    pub departure_type: DepartureType,
+}
+
+/// 1つの便 (trip_id) の停車情報。時刻表がない駅の情報は含まれない
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Trip {
+    pub trip_id: String,
+    pub ride_id: RideId,
+    pub stops: Vec<TripStop>,
+    /// 前日の運行日の便で、日付をまたいだあとの停車だけを含む
+    pub previous_day: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct StopTime {
+    pub time: Seconds,
+    pub can_board: bool,
+    pub can_alight: bool,
+}
+
+/// RAPTORのroute。同じRide (系統) の便をまとめたもので、停車駅はRide::routeそのもの
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Route {
+    pub ride_id: RideId,
+    /// Network::modesのindex
+    pub mode: usize,
+    /// Ride::routeの各駅に対応する停留所のindex
+    pub stops: Vec<usize>,
+    pub trip_ids: Vec<String>,
+    /// trip_idsの各便が前日の運行日の便か
+    pub previous_day: Vec<bool>,
+    /// trip_ids.len() * stops.len()の2次元配列。時刻表がない駅はNone
+    pub stop_times: Vec<Option<StopTime>>,
+    /// stop_timesと同じ形の、各駅を出るときのリアルタイムの情報
+    pub realtime: Vec<RealtimeStatus>,
+    /// 各駅で乗れる便の (発車時刻, 便のindex) を時刻順に並べたもの。乗る便は二分探索で探す
+    /// 追い越しや時刻表のない駅があっても駅ごとに並べるので、便の順番とは関係なく探せる
+    pub boardings: Vec<Vec<(Seconds, usize)>>,
+    /// 各駅で降りられる便の (到着時刻, 便のindex) を時刻順に並べたもの
+    pub alightings: Vec<Vec<(Seconds, usize)>>,
+}
+
+impl Route {
+    pub(crate) fn stop_time(&self, trip: usize, position: usize) -> Option<StopTime> {
+        self.stop_times[trip * self.stops.len() + position]
+    }
+
+    /// positionでafter以降に乗れる便を、発車時刻の早い順に返す
+    pub(crate) fn boardings_after(&self, position: usize, after: Seconds) -> &[(Seconds, usize)] {
+        let boardings = &self.boardings[position];
+        &boardings[boardings.partition_point(|(time, _)| *time < after)..]
+    }
+
+    /// positionでbefore以前に乗れる便を、発車時刻の早い順に返す
+    pub(crate) fn boardings_before(&self, position: usize, before: Seconds) -> &[(Seconds, usize)] {
+        let boardings = &self.boardings[position];
+        &boardings[..boardings.partition_point(|(time, _)| *time <= before)]
+    }
+
+    /// positionでbefore以前に降りられる便を、到着時刻の早い順に返す
+    pub(crate) fn alightings_before(&self, position: usize, before: Seconds) -> &[(Seconds, usize)] {
+        let alightings = &self.alightings[position];
+        &alightings[..alightings.partition_point(|(time, _)| *time <= before)]
+    }
+
+    /// stop_timesからboardingsとalightingsを作り直す
+    fn index_trips(&mut self) {
+        let width = self.stops.len();
+        self.boardings = vec![vec![]; width];
+        self.alightings = vec![vec![]; width];
+        for trip in 0..self.trip_ids.len() {
+            for position in 0..width {
+                let Some(stop_time) = self.stop_time(trip, position) else {
+                    continue;
+                };
+                if stop_time.can_board {
+                    self.boardings[position].push((stop_time.time, trip));
+                }
+                if stop_time.can_alight {
+                    self.alightings[position].push((stop_time.time, trip));
+                }
+            }
+        }
+        for index in self.boardings.iter_mut().chain(self.alightings.iter_mut()) {
+            index.sort();
+        }
+    }
+
+    pub(crate) fn realtime_at(&self, trip: usize, position: usize) -> RealtimeStatus {
+        self.realtime[trip * self.stops.len() + position]
+    }
+}
+
+/// 便がある駅に停車する情報。時刻表がない駅はtimeがNoneになる
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledStop {
+    /// Ride::routeの駅 (ループしている場合は`~n`つき)
+    pub station_id: StationId,
+    pub time: Option<ServiceTime>,
+    pub can_board: bool,
+    pub can_alight: bool,
+}
+
+/// ある日に運行する1つの便。停車駅はRide::routeの順に並ぶ
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledTrip {
+    pub trip_id: String,
+    pub ride_id: RideId,
+    pub stops: Vec<ScheduledStop>,
+}
+
+/// ある1日の運行情報を、RAPTORで探索しやすい配列に変換したもの
+/// その日の運行日の便に加えて、前日の運行日の便の24時以降の停車を含む
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub(crate) date: NaiveDate,
+    pub(crate) stations: Vec<StationId>,
+    station_index: HashMap<String, usize>,
+    /// career_type (BUSなど) の一覧
+    pub(crate) modes: Vec<CareerType>,
+    pub(crate) routes: Vec<Route>,
+    /// 停留所のindex -> (routeのindex, route内での位置)
+    pub(crate) routes_by_stop: Vec<Vec<(usize, usize)>>,
+    /// 停留所のindex -> joinでつながっている停留所のindex (自分自身は含まない)
+    pub(crate) joins: Vec<Vec<usize>>,
+    /// 停留所のindex -> 歩いて移れる停留所のindexと歩く秒数 (joinでつながっている停留所は含まない)
+    pub(crate) footpaths: Vec<Vec<(usize, Seconds)>>,
+    pub(crate) rides: HashMap<String, Ride>,
+    pub(crate) transfar_rules: TransfarRules,
+}
+
+impl Network {
+    /// データセットから、dateに運行する便をすべて取り出して構築する
+    /// 前日の運行日の便のうち24時以降の停車も、dateの0時からの時刻にして含める
+    pub fn from_dataset(dataset: &Dataset, date: NaiveDate) -> Self {
+        let stations: Vec<Station> = dataset.valid_stations().into_iter().cloned().collect();
+
+        let mut trips = service_day_trips(dataset, &stations, date);
+        if let Some(previous) = date.pred_opt() {
+            for trip in service_day_trips(dataset, &stations, previous) {
+                let stops: Vec<TripStop> = trip
+                    .stops
+                    .into_iter()
+                    .filter_map(|x| x.time.on_next_day().map(|time| TripStop { time, ..x }))
+                    .collect();
+                if !stops.is_empty() {
+                    trips.push(Trip { stops, previous_day: true, ..trip });
+                }
+            }
+        }
+
+        let mut rides: HashMap<String, Ride> = HashMap::new();
+        for trip in trips.iter() {
+            let key = trip.ride_id.get_raw_id();
+            if rides.contains_key(&key) {
+                continue;
+            }
+            // 系統の情報がない便は経路が分からないので使わない
+            if let Some(ride) = dataset.ride(&trip.ride_id) {
+                rides.insert(key, ride.clone());
+            }
+        }
+
+        Self::build(date, trips, rides, &stations, dataset.transfar_rules().clone())
+    }
+
+    pub(crate) fn build(
+        date: NaiveDate,
+        trips: Vec<Trip>,
+        rides: HashMap<String, Ride>,
+        stations: &[Station],
+        transfar_rules: TransfarRules,
+    ) -> Self {
+        let mut network = Self {
+            date,
+            stations: vec![],
+            station_index: HashMap::new(),
+            modes: vec![],
+            routes: vec![],
+            routes_by_stop: vec![],
+            joins: vec![],
+            footpaths: vec![],
+            rides: HashMap::new(),
+            transfar_rules,
+        };
+
+        let mut trips_by_ride: HashMap<String, Vec<Trip>> = HashMap::new();
+        for trip in trips {
+            trips_by_ride.entry(trip.ride_id.get_raw_id()).or_default().push(trip);
+        }
+        let mut ride_ids: Vec<String> = trips_by_ride.keys().cloned().collect();
+        ride_ids.sort();
+
+        for ride_id in ride_ids {
+            let Some(ride) = rides.get(&ride_id) else {
+                continue;
+            };
+            let ride_trips = trips_by_ride.remove(&ride_id).unwrap_or_default();
+            let route = network.build_route(ride, &ride_trips);
+            network.routes.push(route);
+        }
+
+        network.routes_by_stop = vec![vec![]; network.stations.len()];
+        for (route_index, route) in network.routes.iter().enumerate() {
+            for (position, stop) in route.stops.iter().enumerate() {
+                network.routes_by_stop[*stop].push((route_index, position));
+            }
+        }
+
+        network.build_joins(stations);
+        network.build_footpaths(stations);
+        network.rides = rides;
+        network
+    }
+
+    fn intern_station(&mut self, id: &StationId) -> usize {
+        let raw_id = id.get_raw_id();
+        if let Some(index) = self.station_index.get(&raw_id) {
+            return *index;
+        }
+        let index = self.stations.len();
+        self.stations.push(id.with_same_root(raw_id.clone()));
+        self.station_index.insert(raw_id, index);
+        index
+    }
+
+    fn intern_mode(&mut self, career_type: CareerType) -> usize {
+        match self.modes.iter().position(|x| *x == career_type) {
+            Some(index) => index,
+            None => {
+                self.modes.push(career_type);
+                self.modes.len() - 1
+            }
+        }
+    }
+
+    fn build_route(&mut self, ride: &Ride, trips: &[Trip]) -> Route {
+        let stops: Vec<usize> = ride.route.iter().map(|x| self.intern_station(x)).collect();
+        let mode = self.intern_mode(ride.career_type);
+
+        let mut rows: Vec<(String, bool, Vec<Option<StopTime>>)> = Vec::new();
+        for trip in trips.iter() {
+            let mut row: Vec<Option<StopTime>> = vec![None; stops.len()];
+            for stop in trip.stops.iter() {
+                let Some(position) = route_position(ride, &stop.station_id, stop.loop_count) else {
+                    continue;
+                };
+                if row[position].is_none() {
+                    row[position] = Some(StopTime {
+                        time: stop.time.seconds(),
+                        can_board: stop.departure_type.can_board(),
+                        can_alight: stop.departure_type.can_alight(),
+                    });
+                }
+            }
+            rows.push((trip.trip_id.clone(), trip.previous_day, row));
+        }
+        // 最初に時刻がわかる駅の時刻順に並べる
+        rows.sort_by_key(|(trip_id, _, row)| (row.iter().flatten().map(|x| x.time).next(), trip_id.clone()));
+
+        let mut route = Route {
+            ride_id: trips[0].ride_id.clone(),
+            mode,
+            trip_ids: rows.iter().map(|(trip_id, _, _)| trip_id.clone()).collect(),
+            previous_day: rows.iter().map(|(_, previous_day, _)| *previous_day).collect(),
+            realtime: vec![RealtimeStatus::default(); rows.len() * stops.len()],
+            stops,
+            stop_times: rows.into_iter().flat_map(|(_, _, row)| row).collect(),
+            boardings: vec![],
+            alightings: vec![],
+        };
+        route.index_trips();
+        route
+    }
+
+    /// リアルタイムの遅れがわかる便の時刻を遅れを反映した時刻にし、運休の便と閉鎖された駅では乗り降りできないようにしたNetworkを返す
+    /// 取りやめになった停車も時刻は残すので、時刻表のない駅と区別できる
+    pub fn with_realtime(&self, delays: &TripDelays, alerts: &Alerts) -> Network {
+        let mut res = self.clone();
+        for route in res.routes.iter_mut() {
+            let Some(ride) = self.ride(&route.ride_id) else {
+                continue;
+            };
+            let raw_ride_id = route.ride_id.get_raw_id();
+            let width = route.stops.len();
+            for trip in 0..route.trip_ids.len() {
+                let service_date = if route.previous_day[trip] { self.date.pred_opt() } else { Some(self.date) };
+                let Some(service_date) = service_date else {
+                    continue;
+                };
+                if !alerts.is_empty() {
+                    for position in 0..width {
+                        let station_id = self.stations[route.stops[position]].get_raw_id();
+                        if !alerts.is_cancelled(&station_id, &raw_ride_id, &route.trip_ids[trip], service_date) {
+                            continue;
+                        }
+                        if let Some(stop_time) = route.stop_times[trip * width + position].as_mut() {
+                            stop_time.can_board = false;
+                            stop_time.can_alight = false;
+                        }
+                    }
+                }
+                let Some(trip_delays) = delays.delays_along(&route.trip_ids[trip], service_date, ride) else {
+                    continue;
+                };
+                for (position, delay) in trip_delays.into_iter().enumerate() {
+                    let realtime = RealtimeStatus { realtime: true, delay };
+                    route.realtime[trip * width + position] = realtime;
+                    if let Some(stop_time) = route.stop_times[trip * width + position].as_mut() {
+                        stop_time.time = realtime.apply(ServiceTime::from_seconds(stop_time.time)).seconds();
+                    }
+                }
+            }
+            route.index_trips();
+        }
+        res
+    }
+
+    /// joinは片方の駅にしか書かれていないことがあるので、両方向につないだうえで連結成分ごとにまとめる
+    fn build_joins(&mut self, stations: &[Station]) {
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for station in stations.iter() {
+            let from = self.intern_station(&station.station_id);
+            for joined in station.join.iter() {
+                let to = self.intern_station(joined);
+                edges.push((from, to));
+            }
+        }
+        self.routes_by_stop.resize(self.stations.len(), vec![]);
+
+        let mut adjacency: Vec<Vec<usize>> = vec![vec![]; self.stations.len()];
+        for (from, to) in edges {
+            adjacency[from].push(to);
+            adjacency[to].push(from);
+        }
+        self.joins = vec![vec![]; self.stations.len()];
+        for start in 0..self.stations.len() {
+            if adjacency[start].is_empty() || !self.joins[start].is_empty() {
+                continue;
+            }
+            let mut group: Vec<usize> = vec![start];
+            let mut visited: HashSet<usize> = HashSet::from([start]);
+            let mut index = 0;
+            while index < group.len() {
+                for next in adjacency[group[index]].iter() {
+                    if visited.insert(*next) {
+                        group.push(*next);
+                    }
+                }
+                index += 1;
+            }
+            for member in group.iter() {
+                self.joins[*member] = group.iter().filter(|x| *x != member).cloned().collect();
+            }
+        }
+    }
+
+    /// 緯度経度がわかる駅どうしで、transfar_rulesの半径以内にあるものを歩いて移れるようにする
+    /// 駅の数は多くないので、すべての組の距離を計算する
+    fn build_footpaths(&mut self, stations: &[Station]) {
+        let walking = self.transfar_rules.walking();
+        let located: Vec<(usize, (f64, f64))> = stations
+            .iter()
+            .filter_map(|x| x.coordinate().map(|coordinate| (self.intern_station(&x.station_id), coordinate)))
+            .collect();
+        self.footpaths = vec![vec![]; self.stations.len()];
+        for (from, from_coordinate) in located.iter() {
+            for (to, to_coordinate) in located.iter() {
+                if from == to || self.joins[*from].contains(to) {
+                    continue;
+                }
+                let distance = distance_meters(*from_coordinate, *to_coordinate);
+                if distance <= walking.radius_meters {
+                    let seconds = (walking.minutes(distance) * 60) as Seconds;
+                    self.footpaths[*from].push((*to, seconds));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn stop_of(&self, id: &StationId) -> Option<usize> {
+        self.station_index.get(&id.get_raw_id()).cloned()
+    }
+
+    /// 駅とjoinでつながっている停留所をすべて返す
+    /// 時刻表のない駅 (出発地や目的地) に書かれたjoinもここで考慮する
+    pub(crate) fn expand(&self, station: &Station) -> HashSet<usize> {
+        let mut res: HashSet<usize> = HashSet::new();
+        for id in std::iter::once(&station.station_id).chain(station.join.iter()) {
+            if let Some(stop) = self.stop_of(id) {
+                res.insert(stop);
+                res.extend(self.joins[stop].iter().cloned());
+            }
+        }
+        res
+    }
+
+    /// stopsと、そこから歩いて移れる停留所を、歩く秒数 (stops自身は0) とともに返す
+    pub(crate) fn walkable(&self, stops: &HashSet<usize>) -> HashMap<usize, Seconds> {
+        let mut res: HashMap<usize, Seconds> = stops.iter().map(|x| (*x, 0)).collect();
+        for stop in stops.iter() {
+            for (other, seconds) in self.footpaths[*stop].iter() {
+                let entry = res.entry(*other).or_insert(*seconds);
+                *entry = (*entry).min(*seconds);
+            }
+        }
+        res
+    }
+
+    /// stopsから乗れる便について、after以降に出発地を出て間に合う時刻を早い順に重複なく返す
+    /// stopsの値は出発地からその停留所まで歩く秒数
+    pub(crate) fn departure_times(&self, stops: &HashMap<usize, Seconds>, after: Seconds) -> Vec<Seconds> {
+        let mut res: Vec<Seconds> = stops
+            .iter()
+            .flat_map(|(stop, walk)| self.routes_by_stop[*stop].iter().map(move |x| (x, *walk)))
+            .flat_map(|((route, position), walk)| {
+                self.routes[*route].boardings_after(*position, after + walk).iter().map(move |(time, _)| time - walk)
+            })
+            .collect();
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    /// stopsで降りる便について、before以前に目的地に着く時刻を早い順に重複なく返す
+    /// stopsの値はその停留所から目的地まで歩く秒数
+    /// 到着時刻がわからない便は、その手前で最後にわかる時刻で代用する
+    pub(crate) fn arrival_times(&self, stops: &HashMap<usize, Seconds>, before: Seconds) -> Vec<Seconds> {
+        let mut res: Vec<Seconds> = Vec::new();
+        for (stop, walk) in stops.iter() {
+            for (route, position) in self.routes_by_stop[*stop].iter() {
+                let route = &self.routes[*route];
+                if let Some(before) = before.checked_sub(*walk) {
+                    res.extend(route.alightings_before(*position, before).iter().map(|(time, _)| time + walk));
+                }
+                // 時刻のわからない停留所から歩くことはない
+                if *walk != 0 {
+                    continue;
+                }
+                for trip in 0..route.trip_ids.len() {
+                    if route.stop_time(trip, *position).is_some() {
+                        continue;
+                    }
+                    let last = (0..*position).rev().find_map(|x| route.stop_time(trip, x)).filter(|x| x.can_board);
+                    res.extend(last.map(|x| x.time).filter(|x| *x <= before));
+                }
+            }
+        }
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn ride(&self, id: &RideId) -> Option<&Ride> {
+        self.rides.get(&id.get_raw_id())
+    }
+
+    /// この日の運行日の便をすべて返す (前日の運行日から日付をまたいできた便は含まない)
+    /// 同じtrip_idが1日に複数回使われている場合は、それぞれ別の便として返す
+    pub fn scheduled_trips(&self) -> Vec<ScheduledTrip> {
+        self.scheduled_trips_by(|_| true)
+    }
+
+    /// この日の運行日の便のうち、trip_idのものをすべて返す
+    pub fn scheduled_trips_of(&self, trip_id: &str) -> Vec<ScheduledTrip> {
+        self.scheduled_trips_by(|x| x == trip_id)
+    }
+
+    fn scheduled_trips_by(&self, filter: impl Fn(&str) -> bool) -> Vec<ScheduledTrip> {
+        let mut res: Vec<ScheduledTrip> = Vec::new();
+        for route in self.routes.iter() {
+            let Some(ride) = self.ride(&route.ride_id) else {
+                continue;
+            };
+            for (trip, trip_id) in route.trip_ids.iter().enumerate() {
+                if route.previous_day[trip] || !filter(trip_id) {
+                    continue;
+                }
+                let stops = ride
+                    .route
+                    .iter()
+                    .enumerate()
+                    .map(|(position, station_id)| {
+                        let stop_time = route.stop_time(trip, position);
+                        ScheduledStop {
+                            station_id: station_id.clone(),
+                            time: stop_time.map(|x| ServiceTime::from_seconds(x.time)),
+                            can_board: stop_time.is_none_or(|x| x.can_board),
+                            can_alight: stop_time.is_none_or(|x| x.can_alight),
+                        }
+                    })
+                    .collect();
+                res.push(ScheduledTrip {
+                    trip_id: trip_id.clone(),
+                    ride_id: route.ride_id.clone(),
+                    stops,
+                });
+            }
+        }
+        res
+    }
+}
+
+/// dateの運行日の便をすべて取り出す
+/// 同じtrip_idが1日に複数回使われていることがある (AIRDO_58など) ので、
+/// 同じ駅に同じ周回で2回目に停車したものは別の便として扱う
+fn service_day_trips(dataset: &Dataset, stations: &[Station], date: NaiveDate) -> Vec<Trip> {
+    let mut trips: HashMap<String, Vec<Trip>> = HashMap::new();
+    for station in stations.iter() {
+        // 有効なカレンダーがない駅はこの日は運行がないものとして扱う
+        let Ok(pattern) = dataset.pattern_on(&station.station_id, date) else {
+            continue;
+        };
+        let mut departures = pattern.departures.clone();
+        departures.sort_by_key(|x| x.time);
+        for departure in departures {
+            let instances = trips.entry(departure.trip_id.clone()).or_default();
+            let stop = TripStop {
+                station_id: station.station_id.clone(),
+                time: departure.time,
+                loop_count: departure.loop_count,
+                departure_type: departure.departure_type,
+            };
+            let instance = instances.iter_mut().find(|x| {
+                !x.stops.iter().any(|y| y.station_id.get_raw_id() == stop.station_id.get_raw_id() && y.loop_count == stop.loop_count)
+            });
+            match instance {
+                Some(trip) => trip.stops.push(stop),
+                None => instances.push(Trip {
+                    trip_id: departure.trip_id.clone(),
+                    ride_id: departure.ride_id.clone(),
+                    stops: vec![stop],
+                    previous_day: false,
+                }),
+            }
+        }
+    }
+    trips.into_values().flatten().collect()
+}
+
+/// 停車駅がRide::routeの何番目かを返す。ループしている場合はloop_countで区別する
+/// loop_countが合う停車がなければNone (ほかの周回の停車とみなすと遅れや乗車を別の周回に当ててしまう)
+pub(crate) fn route_position(ride: &Ride, station_id: &StationId, loop_count: i32) -> Option<usize> {
+    let raw_id = station_id.get_raw_id();
+    ride.route
+        .iter()
+        .position(|x| x.get_raw_id() == raw_id && x.get_loop_count() == loop_count)
+}
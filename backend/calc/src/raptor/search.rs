@@ -0,0 +1,846 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::journey::{Journey, JourneyLeg, LegKind};
+use models::{service_time::ServiceTime, transfar_rule::TransfarKind};
+
+use super::network::{Network, Seconds};
+
+/// 1つの経路で乗る便の最大数 (= RAPTORのラウンド数。乗り換えは3回まで)
+pub(crate) const MAX_ROUNDS: usize = 4;
+
+/// 停留所のindexと、その停留所に着いたときに乗っていたroute (Network::routesのindex) の組
+/// 乗り換え時間の条件が乗り物や事業者で変わるので、ラベルはこの単位で持つ
+type LabelKey = (usize, Option<usize>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Parent {
+    Origin,
+    Ride {
+        route: usize,
+        trip: usize,
+        board_position: usize,
+        alight_position: usize,
+        from: LabelKey,
+    },
+    /// joinでつながった停留所から移ってきた
+    Join { from: LabelKey },
+    /// fromの停留所からstopまでduration秒歩いてきた (arrive_byの探索ではstopからfromへ歩く)
+    Walk { from: LabelKey, stop: usize, duration: Seconds },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Label {
+    /// 順方向の探索では停留所に着く時刻、arrive_byの探索では停留所を出る時刻
+    time: Seconds,
+    parent: Parent,
+}
+
+/// 目的地への到着候補。到着時刻がわからない場合は最後に乗る便の出発時刻をkeyにする
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    key: Seconds,
+    label: Label,
+    arrive_at: Option<Seconds>,
+}
+
+/// 現在乗っている便
+#[derive(Debug, Clone, Copy)]
+struct Boarding {
+    trip: usize,
+    position: usize,
+    departure: Seconds,
+    from: LabelKey,
+}
+
+/// 出発時刻 (arrive_byの探索では到着時刻) を変えながら使い回すラベル
+/// range RAPTORでは、遅く出る探索で見つけたラベルは早く出る探索でもそのまま使える (待てば同じ便に乗れる) ので、
+/// 出発時刻の遅い順に探索すると、それより早く着くか乗り換えが少ない経路だけが新しく見つかる
+struct Labels {
+    /// ラウンドごとの停留所のラベル
+    rounds: Vec<HashMap<LabelKey, Label>>,
+    /// すべてのラウンドを通して最もよい時刻
+    best: HashMap<LabelKey, Seconds>,
+    /// ラウンドごとに、目的地に着く最も早い時刻 (arrive_byの探索では出発地を出る最も遅い時刻)
+    targets: Vec<Option<Seconds>>,
+    /// trueなら順方向の探索 (時刻が早いほどよい)
+    forward: bool,
+}
+
+impl Labels {
+    fn new(forward: bool) -> Self {
+        Self {
+            rounds: vec![HashMap::new(); MAX_ROUNDS + 1],
+            best: HashMap::new(),
+            targets: vec![None; MAX_ROUNDS + 1],
+            forward,
+        }
+    }
+
+    fn is_better(&self, time: Seconds, than: Seconds) -> bool {
+        if self.forward { time < than } else { time > than }
+    }
+
+    /// labelがこれまでのどのラウンドのラベルよりもよければ、roundのラベルにしてtrueを返す
+    fn improve(&mut self, round: usize, key: LabelKey, label: Label) -> bool {
+        if self.best.get(&key).is_some_and(|x| !self.is_better(label.time, *x)) {
+            return false;
+        }
+        self.best.insert(key, label.time);
+        self.rounds[round].insert(key, label);
+        true
+    }
+
+    /// roundまでのどのラウンドの経路よりもよく目的地に着ければ、roundの経路として記録してtrueを返す
+    fn improve_target(&mut self, round: usize, key: Seconds) -> bool {
+        if self.targets[..=round].iter().flatten().any(|x| !self.is_better(key, *x)) {
+            return false;
+        }
+        self.targets[round] = Some(key);
+        true
+    }
+}
+
+/// departuresの各時刻に出発地を出る経路のうち、(出発時刻, 到着時刻, 乗り換え回数) についてパレート最適なものを返す
+/// departuresは早い順に並べる。range RAPTORとして遅い時刻から順にラベルを使い回して探す
+pub(crate) fn raptor(
+    network: &Network,
+    origins: &HashSet<usize>,
+    destinations: &HashSet<usize>,
+    departures: &[Seconds],
+) -> Vec<Journey> {
+    let mut labels = Labels::new(true);
+    let mut res: Vec<Journey> = Vec::new();
+    for departure in departures.iter().rev() {
+        res.extend(raptor_from(network, &mut labels, origins, destinations, *departure));
+    }
+    res
+}
+
+/// departureに出発地を出る経路のうち、labelsのこれまでの経路より早く着くか乗り換えが少ないものを返す
+/// 返り値は乗る便が少ない順 (= 到着が遅い順) に並ぶ
+fn raptor_from(
+    network: &Network,
+    labels: &mut Labels,
+    origins: &HashSet<usize>,
+    destinations: &HashSet<usize>,
+    departure: Seconds,
+) -> Vec<Journey> {
+    let mut marked: HashSet<LabelKey> = HashSet::new();
+    for origin in origins.iter() {
+        if labels.improve(0, (*origin, None), Label { time: departure, parent: Parent::Origin }) {
+            marked.insert((*origin, None));
+        }
+    }
+    // 出発地から歩いて移れる停留所の便にも乗れる
+    let origin_keys = sorted(&marked);
+    for (key, label) in walk_labels(network, &labels.rounds[0], &origin_keys, true) {
+        if labels.improve(0, key, label) {
+            marked.insert(key);
+        }
+    }
+
+    let mut res: Vec<Journey> = Vec::new();
+    for round in 1..=MAX_ROUNDS {
+        let marked_by_stop = keys_by_stop(&marked);
+        let mut candidates: Vec<Candidate> = Vec::new();
+
+        // 前のラウンドで更新された停留所を通るrouteを、最も手前の位置から走査する
+        let mut queue: HashMap<usize, usize> = HashMap::new();
+        for stop in marked_by_stop.keys() {
+            for (route, position) in network.routes_by_stop[*stop].iter() {
+                let entry = queue.entry(*route).or_insert(*position);
+                *entry = (*entry).min(*position);
+            }
+        }
+        let mut queue: Vec<(usize, usize)> = queue.into_iter().collect();
+        queue.sort();
+
+        let mut improved: HashSet<LabelKey> = HashSet::new();
+        for (route_index, start) in queue {
+            let route = &network.routes[route_index];
+            let mut boarding: Option<Boarding> = None;
+            for position in start..route.stops.len() {
+                let stop = route.stops[position];
+
+                if let Some(on) = boarding {
+                    let parent = Parent::Ride {
+                        route: route_index,
+                        trip: on.trip,
+                        board_position: on.position,
+                        alight_position: position,
+                        from: on.from,
+                    };
+                    match route.stop_time(on.trip, position) {
+                        Some(stop_time) if stop_time.can_alight && stop_time.time >= on.departure => {
+                            let key = (stop, Some(route_index));
+                            let label = Label { time: stop_time.time, parent };
+                            if labels.improve(round, key, label) {
+                                improved.insert(key);
+                            }
+                            if destinations.contains(&stop) {
+                                candidates.push(Candidate { key: stop_time.time, label, arrive_at: Some(stop_time.time) });
+                            }
+                        }
+                        None if destinations.contains(&stop) => {
+                            // 時刻表のない目的地は到着時刻不明として候補にする
+                            let label = Label { time: on.departure, parent };
+                            candidates.push(Candidate { key: on.departure, label, arrive_at: None });
+                        }
+                        _ => {}
+                    }
+                }
+
+                // 前のラウンドでこの停留所のラベルが更新されていれば、より早い便に乗れないか調べる
+                let Some(keys) = marked_by_stop.get(&stop) else {
+                    continue;
+                };
+                for from in keys.iter().copied() {
+                    let label = &labels.rounds[round - 1][&from];
+                    let Some((trip, departure)) = earliest_trip(network, route_index, position, label, from.1) else {
+                        continue;
+                    };
+                    let is_earlier = match boarding {
+                        Some(on) => match route.stop_time(on.trip, position) {
+                            Some(stop_time) => departure < stop_time.time,
+                            None => false,
+                        },
+                        None => true,
+                    };
+                    if is_earlier {
+                        boarding = Some(Boarding { trip, position, departure, from });
+                    }
+                }
+            }
+        }
+
+        // joinでつながった停留所へは同じ時刻で移れる
+        let mut joined: Vec<(LabelKey, Label)> = Vec::new();
+        for (stop, arrived_by) in sorted(&improved) {
+            let label = labels.rounds[round][&(stop, arrived_by)];
+            for other in network.joins[stop].iter() {
+                joined.push(((*other, arrived_by), Label { time: label.time, parent: Parent::Join { from: (stop, arrived_by) } }));
+            }
+        }
+        for (key, label) in joined {
+            if labels.improve(round, key, label) {
+                improved.insert(key);
+                if destinations.contains(&key.0) {
+                    candidates.push(Candidate { key: label.time, label, arrive_at: Some(label.time) });
+                }
+            }
+        }
+        // 歩いて移れる停留所へは歩く時間だけ遅れて着く
+        for (key, label) in walk_labels(network, &labels.rounds[round], &sorted(&improved), true) {
+            if labels.improve(round, key, label) {
+                improved.insert(key);
+                if destinations.contains(&key.0) {
+                    candidates.push(Candidate { key: label.time, label, arrive_at: Some(label.time) });
+                }
+            }
+        }
+
+        let best_candidate = candidates.into_iter().min_by_key(|x| x.key);
+        if let Some(candidate) = best_candidate
+            && labels.improve_target(round, candidate.key)
+            && let Some(journey) = reconstruct(network, &labels.rounds, round, candidate) {
+            res.push(journey);
+        }
+
+        if improved.is_empty() {
+            break;
+        }
+        marked = improved;
+    }
+    res
+}
+
+/// HashMapやHashSetの順に依存しないように、乗り換えなし、routeの順に並べる
+fn sorted(keys: &HashSet<LabelKey>) -> Vec<LabelKey> {
+    let mut res: Vec<LabelKey> = keys.iter().copied().collect();
+    res.sort();
+    res
+}
+
+/// 停留所ごとのlabelのkey。ラウンドの最初にまとめておく
+fn keys_by_stop(keys: &HashSet<LabelKey>) -> HashMap<usize, Vec<LabelKey>> {
+    let mut res: HashMap<usize, Vec<LabelKey>> = HashMap::new();
+    for key in sorted(keys) {
+        res.entry(key.0).or_default().push(key);
+    }
+    res
+}
+
+/// stopのlabelの便を実際に降りた (arrive_byの探索では乗った) 停留所。joinで移ったり歩いたりした場合は移る前の停留所
+fn transfar_stop(stop: usize, label: &Label) -> usize {
+    match label.parent {
+        Parent::Join { from } | Parent::Walk { from, .. } => from.0,
+        _ => stop,
+    }
+}
+
+/// labelが歩いてきたものなら歩いた秒数。乗り換え時間は歩く時間も含めて判定する
+fn walk_seconds(label: &Label) -> Seconds {
+    match label.parent {
+        Parent::Walk { duration, .. } => duration,
+        _ => 0,
+    }
+}
+
+/// labelsのうちkeysの停留所から歩いて移れる停留所のラベルを返す。歩いて着いた停留所からはさらに歩かない
+/// forwardがfalse (arrive_byの探索) なら、歩く秒数だけ早い時刻に出るラベルにする
+fn walk_labels(network: &Network, labels: &HashMap<LabelKey, Label>, keys: &[LabelKey], forward: bool) -> Vec<(LabelKey, Label)> {
+    let mut res: Vec<(LabelKey, Label)> = Vec::new();
+    for from in keys.iter().copied() {
+        let label = &labels[&from];
+        if matches!(label.parent, Parent::Walk { .. }) {
+            continue;
+        }
+        for (stop, duration) in network.footpaths[from.0].iter() {
+            let time = if forward {
+                label.time + duration
+            } else {
+                let Some(time) = label.time.checked_sub(*duration) else {
+                    continue;
+                };
+                time
+            };
+            let parent = Parent::Walk { from, stop: *stop, duration: *duration };
+            res.push(((*stop, from.1), Label { time, parent }));
+        }
+    }
+    res
+}
+
+/// 乗り換えの条件に合う待ち時間 (秒) の範囲。上限がなければNone
+/// 待ち時間は分に切り捨てて判定するので、上限はその分の59秒までを含む
+fn transfar_window(network: &Network, transfar: &TransfarKind) -> (i64, Option<i64>) {
+    let limit = network.transfar_rules.limit(transfar);
+    (limit.min.max(0) * 60, limit.max.map(|x| x * 60 + 59))
+}
+
+/// labelの時刻以降にrouteのpositionから乗れる最も早い便を返す
+/// 乗り換えの条件は待ち時間の範囲になるので、範囲の始まりを二分探索してから上限までの便を見る
+fn earliest_trip(
+    network: &Network,
+    route_index: usize,
+    position: usize,
+    label: &Label,
+    arrived_by: Option<usize>,
+) -> Option<(usize, Seconds)> {
+    let route = &network.routes[route_index];
+    let stop = route.stops[position];
+    let arrived_trip = match label.parent {
+        Parent::Ride { route: from_route, trip, .. } if from_route == route_index => Some(trip),
+        _ => None,
+    };
+    let (earliest, latest) = match arrived_by {
+        Some(arrived_route) => {
+            let transfar = TransfarKind {
+                from_station: &network.stations[transfar_stop(stop, label)],
+                to_station: &network.stations[stop],
+                from_mode: network.modes[network.routes[arrived_route].mode],
+                to_mode: network.modes[route.mode],
+                to_ride: &route.ride_id,
+            };
+            let (min, max) = transfar_window(network, &transfar);
+            let arrived_at = (label.time - walk_seconds(label)) as i64;
+            let earliest = (arrived_at + min).max(label.time as i64);
+            (Seconds::try_from(earliest).ok()?, max.map(|x| arrived_at + x))
+        }
+        None => (label.time, None),
+    };
+    route
+        .boardings_after(position, earliest)
+        .iter()
+        .take_while(|(time, _)| latest.is_none_or(|x| *time as i64 <= x))
+        .find(|(_, trip)| Some(*trip) != arrived_trip)
+        .map(|(time, trip)| (*trip, *time))
+}
+
+fn reconstruct(
+    network: &Network,
+    rounds: &[HashMap<LabelKey, Label>],
+    round: usize,
+    candidate: Candidate,
+) -> Option<Journey> {
+    let mut legs: Vec<JourneyLeg> = Vec::new();
+    let mut round = round;
+    let mut label = candidate.label;
+    let mut arrive_at = candidate.arrive_at;
+    loop {
+        match label.parent {
+            Parent::Origin => break,
+            Parent::Join { from } => {
+                label = *rounds[round].get(&from)?;
+            }
+            Parent::Walk { from, stop, duration } => {
+                legs.push(JourneyLeg {
+                    kind: LegKind::Walk,
+                    board_at: network.stations[from.0].clone(),
+                    depart_at: ServiceTime::from_seconds(label.time - duration),
+                    alight_at: network.stations[stop].clone(),
+                    arrive_at: Some(ServiceTime::from_seconds(label.time)),
+                    transfar_time: 0,
+                });
+                label = *rounds[round].get(&from)?;
+                arrive_at = Some(label.time);
+            }
+            Parent::Ride { route, trip, board_position, alight_position, from } => {
+                let route_data = &network.routes[route];
+                let departure = route_data.stop_time(trip, board_position)?.time;
+                let from_label = *rounds[round - 1].get(&from)?;
+                legs.push(JourneyLeg {
+                    kind: LegKind::Ride {
+                        trip_id: route_data.trip_ids[trip].clone(),
+                        ride_id: route_data.ride_id.clone(),
+                        realtime: route_data.realtime_at(trip, board_position),
+                        previous_day: route_data.previous_day[trip],
+                    },
+                    board_at: network.stations[route_data.stops[board_position]].clone(),
+                    depart_at: ServiceTime::from_seconds(departure),
+                    alight_at: network.stations[route_data.stops[alight_position]].clone(),
+                    arrive_at: arrive_at.map(ServiceTime::from_seconds),
+                    transfar_time: 0,
+                });
+                arrive_at = Some(from_label.time);
+                label = from_label;
+                round -= 1;
+            }
+        }
+    }
+    legs.reverse();
+    finish_legs(&mut legs);
+    Some(Journey { legs })
+}
+
+/// arrive_byの探索で乗っている便。降りる停留所から乗る停留所へ逆にたどる
+#[derive(Debug, Clone, Copy)]
+enum ReverseBoarding {
+    Trip {
+        trip: usize,
+        position: usize,
+        arrival: Seconds,
+        from: LabelKey,
+    },
+    /// 時刻表のない目的地で降りる便。乗る停留所の時刻がbound以前ならどの便でもよい
+    Untimed {
+        position: usize,
+        bound: Seconds,
+        from: LabelKey,
+    },
+}
+
+impl ReverseBoarding {
+    fn position(&self) -> usize {
+        match self {
+            Self::Trip { position, .. } | Self::Untimed { position, .. } => *position,
+        }
+    }
+
+    fn from(&self) -> LabelKey {
+        match self {
+            Self::Trip { from, .. } | Self::Untimed { from, .. } => *from,
+        }
+    }
+}
+
+/// arrivalsの各時刻までに目的地に着く経路のうち、(出発時刻, 到着時刻, 乗り換え回数) についてパレート最適なものを返す
+/// arrivalsは早い順に並べる。目的地から逆向きのrange RAPTORとして早い時刻から順にラベルを使い回して探す
+pub(crate) fn raptor_arrive_by(
+    network: &Network,
+    origins: &HashSet<usize>,
+    destinations: &HashSet<usize>,
+    arrivals: &[Seconds],
+) -> Vec<Journey> {
+    let mut labels = Labels::new(false);
+    let mut res: Vec<Journey> = Vec::new();
+    for arrival in arrivals.iter() {
+        res.extend(raptor_arrive_by_from(network, &mut labels, origins, destinations, *arrival));
+    }
+    res
+}
+
+/// arrival以前に目的地に着く経路のうち、labelsのこれまでの経路より遅く出るか乗り換えが少ないものを返す
+/// 返り値は乗る便が少ない順 (= 出発が早い順) に並ぶ
+fn raptor_arrive_by_from(
+    network: &Network,
+    labels: &mut Labels,
+    origins: &HashSet<usize>,
+    destinations: &HashSet<usize>,
+    arrival: Seconds,
+) -> Vec<Journey> {
+    let mut marked: HashSet<LabelKey> = HashSet::new();
+    for destination in destinations.iter() {
+        if labels.improve(0, (*destination, None), Label { time: arrival, parent: Parent::Origin }) {
+            marked.insert((*destination, None));
+        }
+    }
+    // 目的地まで歩いて移れる停留所で降りてもよい
+    let destination_keys = sorted(&marked);
+    for (key, label) in walk_labels(network, &labels.rounds[0], &destination_keys, false) {
+        if labels.improve(0, key, label) {
+            marked.insert(key);
+        }
+    }
+
+    let mut res: Vec<Journey> = Vec::new();
+    for round in 1..=MAX_ROUNDS {
+        let marked_by_stop = keys_by_stop(&marked);
+        let mut candidates: Vec<Candidate> = Vec::new();
+
+        // 前のラウンドで更新された停留所を通るrouteを、最も奥の位置から逆に走査する
+        let mut queue: HashMap<usize, usize> = HashMap::new();
+        for stop in marked_by_stop.keys() {
+            for (route, position) in network.routes_by_stop[*stop].iter() {
+                let entry = queue.entry(*route).or_insert(*position);
+                *entry = (*entry).max(*position);
+            }
+        }
+        let mut queue: Vec<(usize, usize)> = queue.into_iter().collect();
+        queue.sort();
+
+        let mut improved: HashSet<LabelKey> = HashSet::new();
+        for (route_index, end) in queue {
+            let route = &network.routes[route_index];
+            let mut boarding: Option<ReverseBoarding> = None;
+            for position in (0..=end).rev() {
+                let stop = route.stops[position];
+
+                if let Some(on) = boarding {
+                    let boarded = match on {
+                        ReverseBoarding::Trip { trip, arrival, .. } => route
+                            .stop_time(trip, position)
+                            .filter(|x| x.can_board && x.time <= arrival)
+                            .map(|x| (trip, x.time)),
+                        ReverseBoarding::Untimed { position: alight_position, bound, .. } => {
+                            latest_untimed_trip(network, route_index, position, alight_position, bound)
+                        }
+                    };
+                    if let Some((trip, departure)) = boarded {
+                        let key = (stop, Some(route_index));
+                        let label = Label {
+                            time: departure,
+                            parent: Parent::Ride {
+                                route: route_index,
+                                trip,
+                                board_position: position,
+                                alight_position: on.position(),
+                                from: on.from(),
+                            },
+                        };
+                        if labels.improve(round, key, label) {
+                            improved.insert(key);
+                        }
+                        if origins.contains(&stop) {
+                            candidates.push(Candidate { key: departure, label, arrive_at: None });
+                        }
+                    }
+                }
+
+                // 前のラウンドでこの停留所のラベルが更新されていれば、より遅い便で着けないか調べる
+                let Some(keys) = marked_by_stop.get(&stop) else {
+                    continue;
+                };
+                for from in keys.iter().copied() {
+                    let label = &labels.rounds[round - 1][&from];
+                    let Some((trip, arrival)) = latest_trip(network, route_index, position, label, from.1) else {
+                        // 時刻表のない目的地は、その手前の停留所で時刻を確かめる
+                        if label.parent == Parent::Origin && boarding.is_none() {
+                            boarding = Some(ReverseBoarding::Untimed { position, bound: label.time, from });
+                        }
+                        continue;
+                    };
+                    let is_later = match boarding {
+                        Some(ReverseBoarding::Trip { trip, .. }) => match route.stop_time(trip, position) {
+                            Some(stop_time) => arrival > stop_time.time,
+                            None => false,
+                        },
+                        Some(ReverseBoarding::Untimed { .. }) => false,
+                        None => true,
+                    };
+                    if is_later {
+                        boarding = Some(ReverseBoarding::Trip { trip, position, arrival, from });
+                    }
+                }
+            }
+        }
+
+        // joinでつながった停留所へは同じ時刻で移れる
+        let mut joined: Vec<(LabelKey, Label)> = Vec::new();
+        for (stop, arrived_by) in sorted(&improved) {
+            let label = labels.rounds[round][&(stop, arrived_by)];
+            for other in network.joins[stop].iter() {
+                joined.push(((*other, arrived_by), Label { time: label.time, parent: Parent::Join { from: (stop, arrived_by) } }));
+            }
+        }
+        for (key, label) in joined {
+            if labels.improve(round, key, label) {
+                improved.insert(key);
+                if origins.contains(&key.0) {
+                    candidates.push(Candidate { key: label.time, label, arrive_at: None });
+                }
+            }
+        }
+        // 歩いて移れる停留所からは歩く時間だけ早く出る
+        for (key, label) in walk_labels(network, &labels.rounds[round], &sorted(&improved), false) {
+            if labels.improve(round, key, label) {
+                improved.insert(key);
+                if origins.contains(&key.0) {
+                    candidates.push(Candidate { key: label.time, label, arrive_at: None });
+                }
+            }
+        }
+
+        let best_candidate = candidates.into_iter().max_by_key(|x| x.key);
+        if let Some(candidate) = best_candidate
+            && labels.improve_target(round, candidate.key)
+            && let Some(journey) = reconstruct_arrive_by(network, &labels.rounds, round, candidate.label) {
+            res.push(journey);
+        }
+
+        if improved.is_empty() {
+            break;
+        }
+        marked = improved;
+    }
+    res
+}
+
+/// labelの時刻までにrouteのpositionで降りられる最も遅い便を返す
+/// 乗り換えの条件は、この便で着いてからlabelの便に乗るまでの待ち時間で判定する
+fn latest_trip(
+    network: &Network,
+    route_index: usize,
+    position: usize,
+    label: &Label,
+    departed_by: Option<usize>,
+) -> Option<(usize, Seconds)> {
+    let route = &network.routes[route_index];
+    let stop = route.stops[position];
+    let departed_trip = match label.parent {
+        Parent::Ride { route: from_route, trip, .. } if from_route == route_index => Some(trip),
+        _ => None,
+    };
+    let (latest, earliest) = match departed_by {
+        Some(departed_route) => {
+            let next_route = &network.routes[departed_route];
+            let transfar = TransfarKind {
+                from_station: &network.stations[stop],
+                to_station: &network.stations[transfar_stop(stop, label)],
+                from_mode: network.modes[route.mode],
+                to_mode: network.modes[next_route.mode],
+                to_ride: &next_route.ride_id,
+            };
+            let (min, max) = transfar_window(network, &transfar);
+            let departed_at = (label.time + walk_seconds(label)) as i64;
+            let latest = (departed_at - min).min(label.time as i64);
+            (Seconds::try_from(latest).ok()?, max.map(|x| departed_at - x))
+        }
+        None => (label.time, None),
+    };
+    route
+        .alightings_before(position, latest)
+        .iter()
+        .rev()
+        .take_while(|(time, _)| earliest.is_none_or(|x| *time as i64 >= x))
+        .find(|(_, trip)| Some(*trip) != departed_trip)
+        .map(|(time, trip)| (*trip, *time))
+}
+
+/// alight_positionに時刻がない便のうち、positionをbound以前に出る最も遅い便を返す
+fn latest_untimed_trip(
+    network: &Network,
+    route_index: usize,
+    position: usize,
+    alight_position: usize,
+    bound: Seconds,
+) -> Option<(usize, Seconds)> {
+    let route = &network.routes[route_index];
+    route
+        .boardings_before(position, bound)
+        .iter()
+        .rev()
+        .find(|(_, trip)| route.stop_time(*trip, alight_position).is_none())
+        .map(|(time, trip)| (*trip, *time))
+}
+
+/// 逆向きの探索では出発地のラベルから親をたどると、乗る順に便が並ぶ
+fn reconstruct_arrive_by(
+    network: &Network,
+    rounds: &[HashMap<LabelKey, Label>],
+    round: usize,
+    label: Label,
+) -> Option<Journey> {
+    let mut legs: Vec<JourneyLeg> = Vec::new();
+    let mut round = round;
+    let mut label = label;
+    loop {
+        match label.parent {
+            Parent::Origin => break,
+            Parent::Join { from } => {
+                label = *rounds[round].get(&from)?;
+            }
+            Parent::Walk { from, stop, duration } => {
+                legs.push(JourneyLeg {
+                    kind: LegKind::Walk,
+                    board_at: network.stations[stop].clone(),
+                    depart_at: ServiceTime::from_seconds(label.time),
+                    alight_at: network.stations[from.0].clone(),
+                    arrive_at: Some(ServiceTime::from_seconds(label.time + duration)),
+                    transfar_time: 0,
+                });
+                label = *rounds[round].get(&from)?;
+            }
+            Parent::Ride { route, trip, board_position, alight_position, from } => {
+                let route_data = &network.routes[route];
+                legs.push(JourneyLeg {
+                    kind: LegKind::Ride {
+                        trip_id: route_data.trip_ids[trip].clone(),
+                        ride_id: route_data.ride_id.clone(),
+                        realtime: route_data.realtime_at(trip, board_position),
+                        previous_day: route_data.previous_day[trip],
+                    },
+                    board_at: network.stations[route_data.stops[board_position]].clone(),
+                    depart_at: ServiceTime::from_seconds(route_data.stop_time(trip, board_position)?.time),
+                    alight_at: network.stations[route_data.stops[alight_position]].clone(),
+                    arrive_at: route_data.stop_time(trip, alight_position).map(|x| ServiceTime::from_seconds(x.time)),
+                    transfar_time: 0,
+                });
+                label = *rounds[round - 1].get(&from)?;
+                round -= 1;
+            }
+        }
+    }
+    finish_legs(&mut legs);
+    Some(Journey { legs })
+}
+
+/// 歩く区間の時刻を前後の便に合わせてから、便に乗るまでの待ち時間を計算する
+/// 便を降りてから歩く場合は降りてすぐ歩き、出発地から歩く場合は最初の便にちょうど間に合うように歩く
+fn finish_legs(legs: &mut [JourneyLeg]) {
+    for index in 0..legs.len() {
+        if legs[index].kind != LegKind::Walk {
+            continue;
+        }
+        let Some(arrive_at) = legs[index].arrive_at else {
+            continue;
+        };
+        let duration = arrive_at.seconds().saturating_sub(legs[index].depart_at.seconds());
+        let previous = index.checked_sub(1).and_then(|x| legs[x].arrive_at);
+        let depart_at = match (previous, legs.get(index + 1)) {
+            (Some(previous), _) => previous,
+            (None, Some(next)) => ServiceTime::from_seconds(next.depart_at.seconds().saturating_sub(duration)),
+            (None, None) => legs[index].depart_at,
+        };
+        legs[index].depart_at = depart_at;
+        legs[index].arrive_at = Some(ServiceTime::from_seconds(depart_at.seconds() + duration));
+    }
+    for index in 1..legs.len() {
+        if legs[index].kind == LegKind::Walk {
+            continue;
+        }
+        if let Some(arrive_at) = legs[index - 1].arrive_at {
+            legs[index].transfar_time = legs[index].depart_at.minutes_since(arrive_at);
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::raptor;
+    use crate::raptor::network::{Network, Trip};
+    use crate::test_fixture::{self, ride, time, trip_id, typed_trip};
+    use models::id::{ID, StationId};
+    use models::departure::DepartureType;
+    use models::ride::{CareerType, Ride};
+    use models::transfar_rule::TransfarRules;
+
+    fn network(trips: Vec<Trip>, rides: Vec<(&str, Ride)>) -> Network {
+        test_fixture::network(trips, rides, &[], TransfarRules::default())
+    }
+
+    fn stop(network: &Network, raw_id: &str) -> usize {
+        network.stop_of(&StationId::new(raw_id.to_string(), "")).expect("stop exists")
+    }
+
+    #[test]
+    fn 到着時刻と乗り換え回数についてパレート最適な経路を返す() {
+        let network = network(
+            vec![
+                typed_trip("DIRECT-0800", "DIRECT", &[("01", "08:00", DepartureType::Both), ("04", "09:30", DepartureType::Both)]),
+                typed_trip("A-0800", "A", &[("01", "08:00", DepartureType::Both), ("02", "08:20", DepartureType::Both)]),
+                typed_trip("B-0830", "B", &[("02", "08:30", DepartureType::Both), ("03", "08:40", DepartureType::Both)]),
+                typed_trip("C-0845", "C", &[("03", "08:45", DepartureType::Both), ("04", "09:00", DepartureType::Both)]),
+            ],
+            vec![
+                ("DIRECT", ride(CareerType::Bus, &["01", "04"])),
+                ("A", ride(CareerType::Bus, &["01", "02"])),
+                ("B", ride(CareerType::Bus, &["02", "03"])),
+                ("C", ride(CareerType::Bus, &["03", "04"])),
+            ],
+        );
+
+        let origins = [stop(&network, "01")].into_iter().collect();
+        let destinations = [stop(&network, "04")].into_iter().collect();
+        let actual = raptor(&network, &origins, &destinations, &[time("07:30").seconds()]);
+
+        // 乗り換えなしの経路と、2回乗り換えて早く着く経路の両方が残る
+        assert_eq!(2, actual.len());
+        assert_eq!(vec!["DIRECT-0800"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+        assert_eq!(
+            vec!["A-0800", "B-0830", "C-0845"],
+            actual[1].legs.iter().map(trip_id).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(time("09:00")), actual[1].legs[2].arrive_at);
+    }
+
+    #[test]
+    fn 降車専用と乗車専用の停車を区別する() {
+        let network = network(
+            vec![
+                typed_trip("A-0800", "A", &[("01", "08:00", DepartureType::Both), ("02", "08:10", DepartureType::Outgoing), ("03", "08:20", DepartureType::Both)]),
+                typed_trip("B-0815", "B", &[("02", "08:15", DepartureType::Both), ("04", "08:30", DepartureType::Both)]),
+                typed_trip("C-0830", "C", &[("03", "08:30", DepartureType::Both), ("04", "08:50", DepartureType::Both)]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "02", "03"])),
+                ("B", ride(CareerType::Bus, &["02", "04"])),
+                ("C", ride(CareerType::Bus, &["03", "04"])),
+            ],
+        );
+
+        let origins = [stop(&network, "01")].into_iter().collect();
+        let destinations = [stop(&network, "04")].into_iter().collect();
+        let actual = raptor(&network, &origins, &destinations, &[time("07:30").seconds()]);
+
+        // 02では降りられないので03で乗り換える
+        assert_eq!(1, actual.len());
+        assert_eq!(vec!["A-0800", "C-0830"], actual[0].legs.iter().map(trip_id).collect::<Vec<_>>());
+    }
+    #[test]
+    fn 複数の出発時刻では遅く出て早く着く経路に劣るものを返さない() {
+        let network = network(
+            vec![
+                typed_trip("A-0800", "A", &[("01", "08:00", DepartureType::Both), ("04", "08:30", DepartureType::Both)]),
+                typed_trip("A-0900", "A", &[("01", "09:00", DepartureType::Both), ("04", "09:30", DepartureType::Both)]),
+                typed_trip("B-0810", "B", &[("01", "08:10", DepartureType::Both), ("04", "09:40", DepartureType::Both)]),
+            ],
+            vec![
+                ("A", ride(CareerType::Bus, &["01", "04"])),
+                ("B", ride(CareerType::Bus, &["01", "04"])),
+            ],
+        );
+
+        let origins = [stop(&network, "01")].into_iter().collect();
+        let destinations = [stop(&network, "04")].into_iter().collect();
+        let departures = [time("08:00").seconds(), time("08:10").seconds(), time("09:00").seconds()];
+        let actual = raptor(&network, &origins, &destinations, &departures);
+
+        // 遅い出発時刻から探すので、9時の便、8時の便の順に見つかる。8時10分の便は9時の便より遅く着く
+        assert_eq!(
+            vec!["A-0900", "A-0800"],
+            actual.iter().map(|x| trip_id(&x.legs[0])).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use models::alert::{Alert, AlertEffect, AlertTarget};
+use std::error::Error;
+
+use super::feed::{to_dataset_id, FeedMessage};
+
+/// GTFS-RTのAlertのEffectのNO_SERVICE
+const NO_SERVICE: i32 = 1;
+
+/// 発車と乗り換えに当てはめる運行情報
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Alerts {
+    alerts: Vec<Alert>,
+}
+
+impl Alerts {
+    pub fn new(alerts: Vec<Alert>) -> Self {
+        Self { alerts }
+    }
+
+    /// prefixを指定すると、フィードのIDをgtfsのimportと同じ`<prefix>_<id>`にしてデータセットのIDにそろえる
+    /// 有効期間はoffsetのタイムゾーンの日付にする。事業者全体など、駅、系統、便のどれも指定していない対象は使わない
+    pub fn from_feed(feed: &FeedMessage, prefix: Option<&str>, offset: FixedOffset) -> Self {
+        let id = |raw: &str| to_dataset_id(prefix, raw);
+        let date = |x: Option<u64>| {
+            x.and_then(|y| DateTime::from_timestamp(y as i64, 0))
+                .map(|y| y.with_timezone(&offset).date_naive())
+        };
+        let mut alerts = vec![];
+        for entity in feed.entity.iter().filter(|x| !x.is_deleted.unwrap_or(false)) {
+            let Some(alert) = &entity.alert else {
+                continue;
+            };
+            let targets: Vec<AlertTarget> = alert
+                .informed_entity
+                .iter()
+                .map(|x| AlertTarget {
+                    station_id: x.stop_id.as_deref().map(id),
+                    ride_id: x.route_id.as_deref().map(id),
+                    trip_id: x.trip.as_ref().and_then(|y| y.trip_id.as_deref()).map(id),
+                })
+                .filter(|x| !x.is_empty())
+                .collect();
+            if targets.is_empty() {
+                continue;
+            }
+            // 期間が複数ある場合は、すべてを含む期間にする
+            let periods = &alert.active_period;
+            let from = periods.iter().map(|x| date(x.start)).min().flatten();
+            let until = if periods.iter().any(|x| x.end.is_none()) {
+                None
+            } else {
+                periods.iter().filter_map(|x| date(x.end)).max()
+            };
+            alerts.push(Alert {
+                alert_id: entity.id.clone().unwrap_or_default(),
+                effect: if alert.effect == Some(NO_SERVICE) { AlertEffect::NoService } else { AlertEffect::Notice },
+                targets,
+                from,
+                until,
+                header: alert.header_text.as_ref().and_then(|x| x.text()).unwrap_or_default().to_string(),
+                description: alert.description_text.as_ref().and_then(|x| x.text()).map(|x| x.to_string()),
+            });
+        }
+        Self { alerts }
+    }
+
+    /// GTFS-RTのフィード (protobuf) を読む
+    pub fn decode(bytes: &[u8], prefix: Option<&str>, offset: FixedOffset) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        Ok(Self::from_feed(&FeedMessage::from_bytes(bytes)?, prefix, offset))
+    }
+
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    /// service_dateの運行日のtrip_idの便 (ride_idの系統) がstation_idを出る発車が、運休か駅の閉鎖で取りやめならtrue
+    pub fn is_cancelled(&self, station_id: &str, ride_id: &str, trip_id: &str, service_date: NaiveDate) -> bool {
+        self.alerts
+            .iter()
+            .any(|x| x.effect == AlertEffect::NoService && x.applies_to(station_id, ride_id, trip_id, service_date))
+    }
+
+    /// 発車に当てはまるお知らせ
+    pub fn notices(&self, station_id: &str, ride_id: &str, trip_id: &str, service_date: NaiveDate) -> Vec<&Alert> {
+        self.alerts
+            .iter()
+            .filter(|x| x.effect == AlertEffect::Notice && x.applies_to(station_id, ride_id, trip_id, service_date))
+            .collect()
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::Alerts;
+    use crate::realtime::feed::{
+        Alert, EntitySelector, FeedEntity, FeedMessage, TimeRange, TranslatedString, Translation, TripDescriptor,
+    };
+    use chrono::{FixedOffset, NaiveDate};
+    use models::alert::AlertEffect;
+
+    fn text(ja: &str) -> Option<TranslatedString> {
+        let translation = |text: &str, language: &str| Translation {
+            text: Some(text.to_string()),
+            language: Some(language.to_string()),
+        };
+        Some(TranslatedString { translation: vec![translation("Detour", "en"), translation(ja, "ja")] })
+    }
+
+    fn entity(id: &str, effect: Option<i32>, informed_entity: Vec<EntitySelector>, active_period: Vec<TimeRange>) -> FeedEntity {
+        FeedEntity {
+            id: Some(id.to_string()),
+            is_deleted: None,
+            trip_update: None,
+            alert: Some(Alert {
+                active_period,
+                informed_entity,
+                effect,
+                header_text: text("工事のため迂回"),
+                description_text: None,
+            }),
+        }
+    }
+
+    fn selector(route_id: Option<&str>, trip_id: Option<&str>, stop_id: Option<&str>) -> EntitySelector {
+        EntitySelector {
+            agency_id: None,
+            route_id: route_id.map(|x| x.to_string()),
+            trip: trip_id.map(|x| TripDescriptor { trip_id: Some(x.to_string()), start_time: None, start_date: None, route_id: None }),
+            stop_id: stop_id.map(|x| x.to_string()),
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 4, day).unwrap()
+    }
+
+    #[test]
+    fn フィードの運休とお知らせを発車に当てはめられる() {
+        let feed = FeedMessage {
+            header: None,
+            entity: vec![
+                // 2025-04-25 00:00 (+09:00) から 2025-04-26 23:59 (+09:00) まで
+                entity("1", Some(1), vec![selector(None, Some("55G-D-0756"), None)], vec![TimeRange { start: Some(1745506800), end: Some(1745679540) }]),
+                entity("2", Some(4), vec![selector(Some("55G"), None, Some("050004"))], vec![]),
+                // 事業者全体の情報は使わない
+                entity("3", Some(1), vec![EntitySelector { agency_id: Some("HAKODATEBUS".to_string()), ..selector(None, None, None) }], vec![]),
+            ],
+        };
+        let alerts = Alerts::from_feed(&feed, Some("HAKODATEBUS"), FixedOffset::east_opt(9 * 60 * 60).unwrap());
+        assert_eq!(2, alerts.len());
+        assert_eq!((Some(date(25)), Some(date(26))), (alerts.alerts()[0].from, alerts.alerts()[0].until));
+        assert_eq!(AlertEffect::Notice, alerts.alerts()[1].effect);
+
+        let (station, ride) = ("HAKODATEBUS_050004", "HAKODATEBUS_55G-1");
+        assert!(alerts.is_cancelled(station, ride, "HAKODATEBUS_55G-D-0756", date(25)));
+        assert!(!alerts.is_cancelled(station, ride, "HAKODATEBUS_55G-D-0756", date(27)));
+        assert!(!alerts.is_cancelled(station, ride, "HAKODATEBUS_55G-D-1102", date(25)));
+
+        let notices = alerts.notices(station, ride, "HAKODATEBUS_55G-D-1102", date(25));
+        assert_eq!(vec!["工事のため迂回"], notices.iter().map(|x| x.header.as_str()).collect::<Vec<_>>());
+        assert!(alerts.notices("HAKODATEBUS_030023", ride, "HAKODATEBUS_55G-D-1102", date(25)).is_empty());
+    }
+}
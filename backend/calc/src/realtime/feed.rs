@@ -0,0 +1,160 @@
+use prost::Message;
+use std::error::Error;
+
+/// GTFS Realtime (gtfs-realtime.proto) のフィード
+/// 使うメッセージとフィールドだけをタグ番号も仕様と同じにして定義する。ここにないフィールドは読み飛ばされる
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedMessage {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<FeedHeader>,
+    #[prost(message, repeated, tag = "2")]
+    pub entity: Vec<FeedEntity>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedHeader {
+    #[prost(string, optional, tag = "1")]
+    pub gtfs_realtime_version: Option<String>,
+    /// POSIX時刻 (秒)
+    #[prost(uint64, optional, tag = "3")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FeedEntity {
+    #[prost(string, optional, tag = "1")]
+    pub id: Option<String>,
+    #[prost(bool, optional, tag = "2")]
+    pub is_deleted: Option<bool>,
+    #[prost(message, optional, tag = "3")]
+    pub trip_update: Option<TripUpdate>,
+    #[prost(message, optional, tag = "5")]
+    pub alert: Option<Alert>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TripUpdate {
+    #[prost(message, optional, tag = "1")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(message, repeated, tag = "2")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    #[prost(uint64, optional, tag = "4")]
+    pub timestamp: Option<u64>,
+    /// 便全体の遅れ (秒)。停車ごとの情報がない駅で使う
+    #[prost(int32, optional, tag = "5")]
+    pub delay: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TripDescriptor {
+    #[prost(string, optional, tag = "1")]
+    pub trip_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub start_time: Option<String>,
+    /// 運行日 (`YYYYMMDD`)
+    #[prost(string, optional, tag = "3")]
+    pub start_date: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub route_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StopTimeUpdate {
+    #[prost(uint32, optional, tag = "1")]
+    pub stop_sequence: Option<u32>,
+    #[prost(message, optional, tag = "2")]
+    pub arrival: Option<StopTimeEvent>,
+    #[prost(message, optional, tag = "3")]
+    pub departure: Option<StopTimeEvent>,
+    #[prost(string, optional, tag = "4")]
+    pub stop_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StopTimeEvent {
+    /// 時刻表からの遅れ (秒。早い場合は負)
+    #[prost(int32, optional, tag = "1")]
+    pub delay: Option<i32>,
+    /// 予測時刻 (POSIX時刻)
+    #[prost(int64, optional, tag = "2")]
+    pub time: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Alert {
+    #[prost(message, repeated, tag = "1")]
+    pub active_period: Vec<TimeRange>,
+    #[prost(message, repeated, tag = "5")]
+    pub informed_entity: Vec<EntitySelector>,
+    /// Effect (enum)。1がNO_SERVICE
+    #[prost(int32, optional, tag = "7")]
+    pub effect: Option<i32>,
+    #[prost(message, optional, tag = "10")]
+    pub header_text: Option<TranslatedString>,
+    #[prost(message, optional, tag = "11")]
+    pub description_text: Option<TranslatedString>,
+}
+
+/// startとendはPOSIX時刻 (秒)。ない方は制限しない
+#[derive(Clone, PartialEq, Message)]
+pub struct TimeRange {
+    #[prost(uint64, optional, tag = "1")]
+    pub start: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub end: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct EntitySelector {
+    #[prost(string, optional, tag = "1")]
+    pub agency_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub route_id: Option<String>,
+    #[prost(message, optional, tag = "4")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(string, optional, tag = "5")]
+    pub stop_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TranslatedString {
+    #[prost(message, repeated, tag = "1")]
+    pub translation: Vec<Translation>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Translation {
+    #[prost(string, optional, tag = "1")]
+    pub text: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub language: Option<String>,
+}
+
+impl TranslatedString {
+    /// 日本語の文面。なければ最初の文面
+    pub fn text(&self) -> Option<&str> {
+        self.translation
+            .iter()
+            .find(|x| x.language.as_deref().is_some_and(|y| y.starts_with("ja")))
+            .or(self.translation.first())
+            .and_then(|x| x.text.as_deref())
+    }
+}
+
+impl FeedMessage {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        Ok(Self::decode(bytes)?)
+    }
+}
+
+/// gtfsのimportと同じ規則でフィードのIDをデータセットのIDにする。prefixがなければそのまま使う
+pub(crate) fn to_dataset_id(prefix: Option<&str>, gtfs_id: &str) -> String {
+    let Some(prefix) = prefix else {
+        return gtfs_id.to_string();
+    };
+    let sanitized: String = gtfs_id
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() || x == '-' || x == '_' { x } else { '-' })
+        .collect();
+    format!("{}_{}", prefix, sanitized)
+}
@@ -0,0 +1,3 @@
+pub mod alerts;
+pub mod feed;
+pub mod trip_delays;
\ No newline at end of file
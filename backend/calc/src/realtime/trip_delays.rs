@@ -0,0 +1,258 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use models::{
+    id::{StationId, ID},
+    ride::Ride,
+    service_time::ServiceTime,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+use super::feed::{to_dataset_id, FeedMessage};
+use crate::raptor::network::route_position;
+
+/// 停車ごとの遅れ
+#[derive(Debug, Clone, PartialEq)]
+struct StopDelay {
+    /// データセットの駅のID (ループの`~n`はつかない)
+    station_id: String,
+    /// 駅を出るときの遅れ (秒)。出発の遅れがなければ到着の遅れ
+    delay: i32,
+}
+
+/// 1つの便のTripUpdate
+#[derive(Debug, Clone, PartialEq)]
+struct TripDelay {
+    /// 運行日。フィードになければどの運行日の便にも使う
+    start_date: Option<NaiveDate>,
+    /// 便全体の遅れ (秒)
+    delay: Option<i32>,
+    /// 停車順に並ぶ
+    stops: Vec<StopDelay>,
+}
+
+/// ある便がある駅を出るときのリアルタイムの情報
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RealtimeStatus {
+    /// 便のTripUpdateがあればtrue
+    pub realtime: bool,
+    /// 時刻表からの遅れ (秒。早い場合は負)。TripUpdateがあってもこの駅の遅れがわからなければNone
+    pub delay: Option<i32>,
+}
+
+impl RealtimeStatus {
+    /// 遅れを反映した時刻
+    pub fn apply(&self, time: ServiceTime) -> ServiceTime {
+        self.delay.map_or(time, |x| time.delayed(x))
+    }
+
+    /// 遅れを分に丸めたもの
+    pub fn delay_minutes(&self) -> Option<i64> {
+        self.delay.map(|x| (x as f64 / 60.0).round() as i64)
+    }
+}
+
+/// GTFS-RTのTripUpdateから読み取った、便 (trip_id) ごとの遅れ
+/// 予測時刻 (`time`) だけで遅れ (`delay`) がない停車は使わない
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TripDelays {
+    trips: HashMap<String, Vec<TripDelay>>,
+    /// フィードが作られた日時
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl TripDelays {
+    /// prefixを指定すると、フィードのtrip_idとstop_idをgtfsのimportと同じ`<prefix>_<id>`にしてデータセットのIDにそろえる
+    pub fn from_feed(feed: &FeedMessage, prefix: Option<&str>) -> Self {
+        let id = |raw: &str| to_dataset_id(prefix, raw);
+        let mut trips: HashMap<String, Vec<TripDelay>> = HashMap::new();
+        for entity in feed.entity.iter().filter(|x| !x.is_deleted.unwrap_or(false)) {
+            let Some(trip_update) = &entity.trip_update else {
+                continue;
+            };
+            let Some(trip) = &trip_update.trip else {
+                continue;
+            };
+            let Some(trip_id) = trip.trip_id.as_deref() else {
+                continue;
+            };
+            let start_date = trip
+                .start_date
+                .as_deref()
+                .and_then(|x| NaiveDate::parse_from_str(x, "%Y%m%d").ok());
+            let stops = trip_update
+                .stop_time_update
+                .iter()
+                .filter_map(|x| {
+                    let station_id = id(x.stop_id.as_deref()?);
+                    let delay = x.departure.as_ref().and_then(|y| y.delay).or(x.arrival.as_ref().and_then(|y| y.delay))?;
+                    Some(StopDelay { station_id, delay })
+                })
+                .collect();
+            trips.entry(id(trip_id)).or_default().push(TripDelay {
+                start_date,
+                delay: trip_update.delay,
+                stops,
+            });
+        }
+
+        Self {
+            trips,
+            timestamp: feed
+                .header
+                .as_ref()
+                .and_then(|x| x.timestamp)
+                .and_then(|x| DateTime::from_timestamp(x as i64, 0)),
+        }
+    }
+
+    /// GTFS-RTのフィード (protobuf) を読む
+    pub fn decode(bytes: &[u8], prefix: Option<&str>) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        Ok(Self::from_feed(&FeedMessage::from_bytes(bytes)?, prefix))
+    }
+
+    /// 遅れの情報がある便の数
+    pub fn len(&self) -> usize {
+        self.trips.values().map(|x| x.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trips.is_empty()
+    }
+
+    /// service_dateの運行日のtrip_idの便について、rideのrouteの各駅を出るときの遅れ (秒) を返す
+    /// 停車ごとの遅れは次に情報がある停車まで続き、最初に情報がある停車より前は便全体の遅れを使う
+    /// この便の情報がなければNone
+    pub fn delays_along(&self, trip_id: &str, service_date: NaiveDate, ride: &Ride) -> Option<Vec<Option<i32>>> {
+        let trip = self
+            .trips
+            .get(trip_id)?
+            .iter()
+            .find(|x| x.start_date.is_none_or(|y| y == service_date))?;
+        let mut res: Vec<Option<i32>> = vec![trip.delay; ride.route.len()];
+        let mut next = 0;
+        for stop in trip.stops.iter() {
+            // 停車はrouteの順に並んでいるので、前の停車より後ろから探す
+            let Some(offset) = ride.route[next..].iter().position(|x| x.get_raw_id() == stop.station_id) else {
+                continue;
+            };
+            for delay in res[next + offset..].iter_mut() {
+                *delay = Some(stop.delay);
+            }
+            next += offset + 1;
+        }
+        Some(res)
+    }
+
+    /// service_dateの運行日のtrip_idの便が、rideのrouteのstation_id (loop_count周目) を出るときの情報
+    pub fn status_at(
+        &self,
+        trip_id: &str,
+        service_date: NaiveDate,
+        ride: &Ride,
+        station_id: &StationId,
+        loop_count: i32,
+    ) -> RealtimeStatus {
+        let Some(delays) = self.delays_along(trip_id, service_date, ride) else {
+            return RealtimeStatus::default();
+        };
+        RealtimeStatus {
+            realtime: true,
+            delay: route_position(ride, station_id, loop_count).and_then(|x| delays[x]),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{RealtimeStatus, TripDelays};
+    use crate::realtime::feed::{FeedEntity, FeedHeader, FeedMessage, StopTimeEvent, StopTimeUpdate, TripDescriptor, TripUpdate};
+    use crate::test_fixture;
+    use chrono::NaiveDate;
+    use models::id::{StationId, ID};
+    use models::ride::{CareerType, Ride};
+    use prost::Message;
+
+    fn ride(route: &[&str]) -> Ride {
+        test_fixture::ride(CareerType::Bus, route)
+    }
+
+    fn stop(stop_id: &str, arrival: Option<i32>, departure: Option<i32>) -> StopTimeUpdate {
+        let event = |delay: Option<i32>| delay.map(|x| StopTimeEvent { delay: Some(x), time: None });
+        StopTimeUpdate {
+            stop_sequence: None,
+            arrival: event(arrival),
+            departure: event(departure),
+            stop_id: Some(stop_id.to_string()),
+        }
+    }
+
+    fn feed(trip_id: &str, start_date: Option<&str>, delay: Option<i32>, stops: Vec<StopTimeUpdate>) -> Vec<u8> {
+        FeedMessage {
+            header: Some(FeedHeader { gtfs_realtime_version: Some("2.0".to_string()), timestamp: Some(1745535600) }),
+            entity: vec![FeedEntity {
+                id: Some("1".to_string()),
+                is_deleted: None,
+                alert: None,
+                trip_update: Some(TripUpdate {
+                    trip: Some(TripDescriptor {
+                        trip_id: Some(trip_id.to_string()),
+                        start_time: None,
+                        start_date: start_date.map(|x| x.to_string()),
+                        route_id: None,
+                    }),
+                    stop_time_update: stops,
+                    timestamp: None,
+                    delay,
+                }),
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 4, 25).unwrap()
+    }
+
+    #[test]
+    fn 停車ごとの遅れは次の停車の情報まで続く() {
+        let bytes = feed("55G-0756", Some("20250425"), Some(60), vec![stop("02", Some(300), None), stop("04", Some(600), Some(540))]);
+        let delays = TripDelays::decode(&bytes, Some("HAKODATEBUS")).unwrap();
+        assert_eq!(1, delays.len());
+        assert_eq!(Some(1745535600), delays.timestamp.map(|x| x.timestamp()));
+
+        let ride = ride(&["HAKODATEBUS_01", "HAKODATEBUS_02", "HAKODATEBUS_03", "HAKODATEBUS_04", "HAKODATEBUS_05"]);
+        let actual = delays.delays_along("HAKODATEBUS_55G-0756", date(), &ride);
+        assert_eq!(Some(vec![Some(60), Some(300), Some(300), Some(540), Some(540)]), actual);
+
+        // 運行日が違う便や、情報がない便には使わない
+        assert_eq!(None, delays.delays_along("HAKODATEBUS_55G-0756", date().succ_opt().unwrap(), &ride));
+        assert_eq!(None, delays.delays_along("HAKODATEBUS_55G-1102", date(), &ride));
+
+        let station_id = StationId::new("HAKODATEBUS_03".to_string(), "");
+        let expected = RealtimeStatus { realtime: true, delay: Some(300) };
+        assert_eq!(expected, delays.status_at("HAKODATEBUS_55G-0756", date(), &ride, &station_id, 1));
+        assert_eq!(Some(5), expected.delay_minutes());
+        assert_eq!(RealtimeStatus::default(), delays.status_at("HAKODATEBUS_55G-1102", date(), &ride, &station_id, 1));
+    }
+
+    #[test]
+    fn ループする経路では前の停車より後ろの駅に遅れを当てる() {
+        let bytes = feed("7A-1314", None, None, vec![stop("01", None, Some(0)), stop("02", Some(120), None), stop("01", Some(240), None)]);
+        let delays = TripDelays::decode(&bytes, None).unwrap();
+
+        let ride = ride(&["01", "02", "03", "01~2"]);
+        let actual = delays.delays_along("7A-1314", date(), &ride);
+        assert_eq!(Some(vec![Some(0), Some(120), Some(120), Some(240)]), actual);
+
+        // 経路にない周回の停車には遅れを当てない
+        let station_id = StationId::new("01".to_string(), "");
+        assert_eq!(Some(240), delays.status_at("7A-1314", date(), &ride, &station_id, 2).delay);
+        assert_eq!(RealtimeStatus { realtime: true, delay: None }, delays.status_at("7A-1314", date(), &ride, &station_id, 3));
+    }
+
+    #[test]
+    fn 読めないフィードはエラーになる() {
+        assert!(TripDelays::decode(&[0xff, 0xff], None).is_err());
+    }
+}
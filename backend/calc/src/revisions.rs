@@ -0,0 +1,125 @@
+use chrono::NaiveDate;
+use models::{
+    id::{StationId, ID},
+    response::revisions::{ResRevision, ResRevisions},
+    timetable::CalendarVersion,
+};
+use repositories::traits::station_repository::StationRepository;
+use std::error::Error;
+
+use crate::dataset::Dataset;
+
+fn to_response(valid_from: Option<NaiveDate>, version: &CalendarVersion) -> ResRevision {
+    ResRevision {
+        calendar_id: version.calendar_id.get_raw_id(),
+        valid_from: valid_from.map(|x| x.format("%Y-%m-%d").to_string()),
+        valid_until: version.valid_until.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// 駅の時刻表について、dateに有効なカレンダーと、それより後に始まるカレンダー (ダイヤ改正) を返す
+pub fn get_revisions(
+    dataset: &Dataset,
+    raw_station_id: &str,
+    date: NaiveDate,
+) -> Result<ResRevisions, Box<dyn Error + Send + Sync + 'static>> {
+    let station_id = StationId::new(raw_station_id.to_string(), dataset.data_root_path());
+    let station = dataset.find_by_id(station_id.clone())?;
+    let timetable = dataset
+        .timetable(&station_id)
+        .ok_or(format!("Timetable for {} not found.", raw_station_id))?;
+    let current = timetable.get_valid_calendar(date).and_then(|current| {
+        timetable
+            .version_starts()
+            .into_iter()
+            .find(|(_, x)| **x == current)
+            .map(|(starts, x)| to_response(starts, x))
+    });
+    let upcoming = timetable
+        .upcoming_versions(date)
+        .into_iter()
+        .map(|(starts, x)| to_response(Some(starts), x))
+        .collect();
+
+    Ok(ResRevisions {
+        station: station.into(),
+        date: date.format("%Y-%m-%d").to_string(),
+        current,
+        upcoming,
+    })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::get_revisions;
+    use crate::dataset::Dataset;
+    use chrono::NaiveDate;
+    use crate::test_fixture::{calendar_json, write_dataset};
+    use models::response::revisions::ResRevision;
+
+    #[test]
+    fn 有効なカレンダーとこれから始まるカレンダーを返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        let actual = get_revisions(&dataset, "HAKODATEBUS_050004", NaiveDate::from_ymd_opt(2025, 4, 25).unwrap()).unwrap();
+
+        assert_eq!("2025-04-25", actual.date);
+        let expected = ResRevision {
+            calendar_id: "HAKODATEBUS_050004_0001".to_string(),
+            valid_from: None,
+            valid_until: "2025-10-01".to_string(),
+        };
+        assert_eq!(Some(expected), actual.current);
+        assert!(actual.upcoming.is_empty());
+
+        // 期限が切れた後は有効なカレンダーがない
+        let expired = get_revisions(&dataset, "HAKODATEBUS_050004", NaiveDate::from_ymd_opt(2025, 10, 2).unwrap()).unwrap();
+        assert_eq!(None, expired.current);
+        assert!(get_revisions(&dataset, "UNKNOWN", NaiveDate::from_ymd_opt(2025, 4, 25).unwrap()).is_err());
+    }
+
+    #[test]
+    fn valid_fromがないバージョンは前のバージョンの翌日から始まる() {
+        let mut files: Vec<(String, String)> = vec![
+            (
+                "station/A/01.json".to_string(),
+                r#"{"station_id": "A_01", "name": "01", "join": [], "pronounce": ""}"#.to_string(),
+            ),
+            (
+                "timetable/A/01.json".to_string(),
+                r#"{"station_id": "A_01", "versions": [
+                    {"calendar_id": "A_01_0001", "valid_from": "2025/06/01", "valid_until": "2025/11/30"},
+                    {"calendar_id": "A_01_0002", "valid_until": "2026/03/31"}]}"#.to_string(),
+            ),
+        ];
+        for id in ["0001", "0002"] {
+            files.push((format!("calendar/A/01/{}.json", id), calendar_json(&format!("A_01_{}", id))));
+            files.push((
+                format!("departure-pattern/A/01/{}.json", id),
+                format!(r#"{{"pattern_id": "A_01_{}", "departures": []}}"#, id),
+            ));
+        }
+        let dataset = write_dataset("revisions", &files).expect("dataset should be valid");
+
+        let revision = |calendar_id: &str, valid_from: &str, valid_until: &str| ResRevision {
+            calendar_id: calendar_id.to_string(),
+            valid_from: Some(valid_from.to_string()),
+            valid_until: valid_until.to_string(),
+        };
+        // 最初のバージョンが始まる前は有効なカレンダーがなく、2つとも始まる前のものとして返す
+        let before = get_revisions(&dataset, "A_01", NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert_eq!(None, before.current);
+        assert_eq!(
+            vec![revision("A_01_0001", "2025-06-01", "2025-11-30"), revision("A_01_0002", "2025-12-01", "2026-03-31")],
+            before.upcoming
+        );
+
+        let during = get_revisions(&dataset, "A_01", NaiveDate::from_ymd_opt(2025, 7, 1).unwrap()).unwrap();
+        assert_eq!(Some(revision("A_01_0001", "2025-06-01", "2025-11-30")), during.current);
+        assert_eq!(vec![revision("A_01_0002", "2025-12-01", "2026-03-31")], during.upcoming);
+
+        let after = get_revisions(&dataset, "A_01", NaiveDate::from_ymd_opt(2025, 12, 1).unwrap()).unwrap();
+        assert_eq!(Some(revision("A_01_0002", "2025-12-01", "2026-03-31")), after.current);
+        assert!(after.upcoming.is_empty());
+    }
+}
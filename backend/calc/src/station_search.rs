@@ -0,0 +1,138 @@
+use models::{id::ID, station::Station};
+
+use crate::dataset::Dataset;
+use crate::kana::{to_hiragana, to_romaji};
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// 時刻表がある駅を、駅名、読みがな (ひらがな、カタカナ)、読みがなのローマ字で探し、よく一致するものから返す
+/// 完全一致、前方一致、部分一致、前方のあいまい一致 (タイプミス) の順に並べる
+pub fn search_stations(dataset: &Dataset, query: &str, limit: usize) -> Vec<Station> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return vec![];
+    }
+    let is_romaji = query.chars().all(|x| x.is_ascii_alphanumeric());
+    let romaji_query = fold_long_vowels(&query);
+
+    let mut matched: Vec<(usize, &Station)> = dataset
+        .valid_stations()
+        .into_iter()
+        .filter_map(|station| {
+            let score = if is_romaji {
+                let romaji = fold_long_vowels(&to_romaji(&station.pronounce));
+                match_score(&romaji, &romaji_query)
+            } else {
+                let name = match_score(&normalize(&station.name), &query);
+                let pronounce = match_score(&normalize(&station.pronounce), &query);
+                name.into_iter().chain(pronounce).min()
+            };
+            score.map(|x| (x, station))
+        })
+        .collect();
+    matched.sort_by_key(|(score, station)| (*score, station.name.chars().count(), station.station_id.get_raw_id()));
+    matched.into_iter().take(limit).map(|(_, station)| station.clone()).collect()
+}
+
+/// 空白を除き、全角英数字を半角の小文字に、カタカナをひらがなにそろえる
+fn normalize(raw: &str) -> String {
+    let halfwidth: String = raw
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .map(|x| match x {
+            '！'..='～' => char::from_u32(x as u32 - 0xFEE0).unwrap_or(x),
+            _ => x,
+        })
+        .collect();
+    to_hiragana(&halfwidth.to_lowercase())
+}
+
+/// 長音の書き方の違い (`chuuou`、`chuo`、`chuoo`) を吸収するため、同じ母音の連続と`ou`を1文字にする
+fn fold_long_vowels(romaji: &str) -> String {
+    let mut res = String::new();
+    for x in romaji.chars() {
+        let previous = res.chars().last();
+        let long = match (previous, x) {
+            (Some(previous), _) if previous == x && "aiueo".contains(x) => true,
+            (Some('o'), 'u') => true,
+            _ => false,
+        };
+        if !long {
+            res.push(x);
+        }
+    }
+    res
+}
+
+/// 小さいほどよく一致している。一致しなければNone
+fn match_score(candidate: &str, query: &str) -> Option<usize> {
+    if candidate == query {
+        return Some(0);
+    }
+    if candidate.starts_with(query) {
+        return Some(1);
+    }
+    if candidate.contains(query) {
+        return Some(2);
+    }
+    // 4文字につき1文字 (最大2文字) までの間違いを許す
+    let allowed = (query.chars().count() / 4).min(2);
+    let distance = prefix_distance(candidate, query);
+    (allowed > 0 && distance <= allowed).then_some(3 + distance)
+}
+
+/// queryと、candidateの先頭のいずれかとの編集距離のうち最小のもの
+fn prefix_distance(candidate: &str, query: &str) -> usize {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let query: Vec<char> = query.chars().collect();
+    // previous[j]: queryの先頭i文字とcandidateの先頭j文字の編集距離
+    let mut previous: Vec<usize> = (0..=candidate.len()).collect();
+    for (i, q) in query.iter().enumerate() {
+        let mut current = vec![i + 1; candidate.len() + 1];
+        for (j, c) in candidate.iter().enumerate() {
+            let replace = previous[j] + usize::from(q != c);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous.into_iter().min().unwrap_or(0)
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{fold_long_vowels, prefix_distance, search_stations};
+    use crate::dataset::Dataset;
+    use models::id::ID;
+
+    fn search(dataset: &Dataset, query: &str) -> Vec<String> {
+        search_stations(dataset, query, 5).iter().map(|x| x.station_id.get_raw_id()).collect()
+    }
+
+    #[test]
+    fn 駅名と読みがなとローマ字のどれでも探せる() {
+        let dataset = Dataset::load("../data").unwrap();
+        for query in ["函館", "はこだて", "ハコダテ", "hakodate", "Hakodate", "ｈａｋｏｄａｔｅ"] {
+            let actual = search(&dataset, query);
+            assert_eq!(Some(&"JRH_H75".to_string()), actual.first(), "query: {}", query);
+            assert!(actual.contains(&"HAKODATEBUS_020010".to_string()), "query: {}", query);
+        }
+    }
+
+    #[test]
+    fn 長音の書き方やタイプミスがあっても探せる() {
+        let dataset = Dataset::load("../data").unwrap();
+        assert_eq!(Some(&"HAKODATEBUS_050004".to_string()), search(&dataset, "kameda chugakko").first());
+        assert_eq!(Some(&"JRH_H75".to_string()), search(&dataset, "hakodte").first());
+        assert!(search(&dataset, "xyz").is_empty());
+        assert!(search(&dataset, " ").is_empty());
+    }
+
+    #[test]
+    fn 先頭との編集距離を計算できる() {
+        assert_eq!(0, prefix_distance("hakodate", "hako"));
+        assert_eq!(1, prefix_distance("hakodate", "hakodte"));
+        assert_eq!("chuo", fold_long_vowels("chuuou"));
+        assert_eq!("chuo", fold_long_vowels("chuoo"));
+    }
+}
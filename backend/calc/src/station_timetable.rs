@@ -0,0 +1,144 @@
+use chrono::NaiveDate;
+use models::{
+    departure::Departure,
+    id::{StationId, ID},
+    response::station_timetable::{
+        ResResolvedPattern, ResStationTimetable, ResTimetableDeparture, ResTimetableGroup, ResTimetableHour,
+    },
+    station::Station,
+};
+use repositories::traits::station_repository::StationRepository;
+use std::error::Error;
+
+use crate::dataset::Dataset;
+
+/// 駅とjoinでつながった駅のdateの発車 (乗車できるもの) をすべて、時間帯ごと、種別と行き先ごとにまとめて返す
+pub fn get_station_timetable(
+    dataset: &Dataset,
+    raw_station_id: &str,
+    date: NaiveDate,
+) -> Result<ResStationTimetable, Box<dyn Error + Send + Sync + 'static>> {
+    let station_id = StationId::new(raw_station_id.to_string(), dataset.data_root_path());
+    let root_station = dataset.find_by_id(station_id)?;
+    let mut stations: Vec<Station> = vec![root_station.clone()];
+    for other_station_id in root_station.join.iter() {
+        if let Some(other_station) = dataset.station(other_station_id)
+            && dataset.check_is_valid(other_station.clone())
+        {
+            stations.push(other_station.clone());
+        }
+    }
+
+    let mut patterns: Vec<ResResolvedPattern> = Vec::new();
+    let mut departures: Vec<(&Station, &Departure)> = Vec::new();
+    for (index, station) in stations.iter().enumerate() {
+        // joinでつながった駅は、有効なカレンダーがなければこの日は発車がないものとして扱う
+        let resolved = match dataset.resolve_pattern(&station.station_id, date) {
+            Ok(resolved) => resolved,
+            Err(e) if index == 0 => return Err(e),
+            Err(_) => continue,
+        };
+        patterns.push(ResResolvedPattern {
+            station: station.clone().into(),
+            calendar_id: resolved.calendar.calendar_id.clone(),
+            pattern_id: resolved.pattern.pattern_id.get_raw_id(),
+            source: resolved.source,
+        });
+        departures.extend(
+            resolved.pattern.departures.iter().filter(|x| x.departure_type.can_board()).map(|x| (station, x)),
+        );
+    }
+    departures.sort_by_key(|(_, x)| x.time);
+
+    let mut hours: Vec<ResTimetableHour> = Vec::new();
+    // 日付をまたいだ発車を同じ日の0時台と分けるため、運行日の0時からの時間で区切る
+    let mut current_hour: Option<u32> = None;
+    for (station, departure) in departures {
+        let ride = dataset
+            .ride(&departure.ride_id)
+            .ok_or(format!("Ride {} not found.", departure.ride_id.get_raw_id()))?;
+        let service_hour = departure.time.seconds() / 3600;
+        if current_hour != Some(service_hour) {
+            hours.push(ResTimetableHour { hour: service_hour % 24, groups: vec![] });
+            current_hour = Some(service_hour);
+        }
+        let groups = &mut hours.last_mut().expect("hour is pushed above").groups;
+
+        let at = station.station_id.get_raw_id();
+        let to = ride.to.get_raw_id();
+        let index = match groups.iter().position(|x| x.at.id == at && x.ride_type == ride.ride_type && x.to.id == to) {
+            Some(index) => index,
+            None => {
+                groups.push(ResTimetableGroup {
+                    ride_type: ride.ride_type.clone(),
+                    aka_type: if ride.aka_type.is_empty() { None } else { Some(ride.aka_type.clone()) },
+                    type_foreground: ride.type_foreground.clone(),
+                    type_background: ride.type_background.clone(),
+                    type_pronounce: ride.type_pronounce.clone(),
+                    career_type: ride.career_type,
+                    at: station.clone().into(),
+                    to: dataset.find_by_id(ride.to.clone())?.into(),
+                    departures: vec![],
+                });
+                groups.len() - 1
+            }
+        };
+        groups[index].departures.push(ResTimetableDeparture {
+            depart_at: departure.time.wall_clock().format("%H:%M").to_string(),
+            trip_id: departure.trip_id.clone(),
+        });
+    }
+
+    Ok(ResStationTimetable {
+        station: root_station.into(),
+        date: date.format("%Y-%m-%d").to_string(),
+        patterns,
+        hours,
+    })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::get_station_timetable;
+    use crate::dataset::Dataset;
+    use chrono::NaiveDate;
+    use models::calendar::PatternSource;
+    use models::id::{StationId, ID};
+
+    #[test]
+    fn 一日の発車を時間帯と種別と行き先ごとにまとめる() {
+        let dataset = Dataset::load("../data").unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 4, 25).unwrap();
+        let actual = get_station_timetable(&dataset, "HAKODATEBUS_050004", date).unwrap();
+
+        assert_eq!("2025-04-25", actual.date);
+        assert_eq!(1, actual.patterns.len());
+        assert_eq!("HAKODATEBUS_050004_0001", actual.patterns[0].pattern_id);
+        assert_eq!(PatternSource::Weekday, actual.patterns[0].source);
+
+        // 時間帯は早い順に並び、同じ時間帯に同じ種別と行き先のまとまりは1つだけ
+        let hours: Vec<u32> = actual.hours.iter().map(|x| x.hour).collect();
+        let mut sorted = hours.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, hours);
+        for hour in actual.hours.iter() {
+            for (index, group) in hour.groups.iter().enumerate() {
+                assert!(group.departures.iter().all(|x| x.depart_at.starts_with(&format!("{:02}:", hour.hour))));
+                assert!(hour.groups[index + 1..].iter().all(|x| x.ride_type != group.ride_type || x.to != group.to));
+            }
+        }
+
+        let station_id = StationId::new("HAKODATEBUS_050004".to_string(), dataset.data_root_path());
+        let boardable = dataset
+            .pattern_on(&station_id, date)
+            .unwrap()
+            .departures
+            .iter()
+            .filter(|x| x.departure_type.can_board())
+            .count();
+        let actual_count: usize = actual.hours.iter().flat_map(|x| x.groups.iter()).map(|x| x.departures.len()).sum();
+        assert_eq!(boardable, actual_count);
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::NaiveDate;
+use std::fs;
+use std::path::{Path, PathBuf};
+use models::departure::DepartureType;
+use models::id::{ID, RideId, StationId};
+use models::ride::{CareerType, Ride};
+use models::service_time::ServiceTime;
+use models::station::Station;
+use models::transfar_rule::TransfarRules;
+
+use crate::dataset::{Dataset, DatasetError};
+use crate::journey::{JourneyLeg, LegKind};
+use crate::raptor::network::{Network, Trip, TripStop};
+use crate::realtime::feed::{FeedEntity, FeedMessage, StopTimeEvent, StopTimeUpdate, TripDescriptor, TripUpdate};
+use crate::realtime::trip_delays::TripDelays;
+
+/// routeの駅を順に通り、最後の駅を行き先にする路線
+pub(crate) fn ride(career_type: CareerType, route: &[&str]) -> Ride {
+    Ride {
+        ride_type: "".to_string(),
+        aka_type: "".to_string(),
+        type_foreground: "".to_string(),
+        type_background: "".to_string(),
+        type_pronounce: "".to_string(),
+        to: StationId::new(route.last().unwrap().to_string(), ""),
+        career_type,
+        route: route.iter().map(|x| StationId::new(x.to_string(), "")).collect(),
+    }
+}
+
+pub(crate) fn time(raw: &str) -> ServiceTime {
+    ServiceTime::parse(raw).expect("valid time")
+}
+
+/// どの駅でも乗り降りできる便
+pub(crate) fn trip(trip_id: &str, ride_id: &str, stops: &[(&str, &str)]) -> Trip {
+    let stops: Vec<(&str, &str, DepartureType)> = stops.iter().map(|(station, at)| (*station, *at, DepartureType::Both)).collect();
+    typed_trip(trip_id, ride_id, &stops)
+}
+
+/// 駅ごとに乗り降りできるかを指定する便
+pub(crate) fn typed_trip(trip_id: &str, ride_id: &str, stops: &[(&str, &str, DepartureType)]) -> Trip {
+    Trip {
+        trip_id: trip_id.to_string(),
+        ride_id: RideId::new(ride_id.to_string(), ""),
+        stops: stops
+            .iter()
+            .map(|(station, at, departure_type)| TripStop {
+                station_id: StationId::new(station.to_string(), ""),
+                time: time(at),
+                loop_count: 1,
+                departure_type: *departure_type,
+            })
+            .collect(),
+        previous_day: false,
+    }
+}
+
+/// 2025/04/25の運行日のNetwork。駅の情報がなければjoinも歩く経路もない
+pub(crate) fn network(trips: Vec<Trip>, rides: Vec<(&str, Ride)>, stations: &[Station], rules: TransfarRules) -> Network {
+    Network::build(
+        NaiveDate::from_ymd_opt(2025, 4, 25).expect("valid date"),
+        trips,
+        rides.into_iter().map(|(id, ride)| (id.to_string(), ride)).collect(),
+        stations,
+        rules,
+    )
+}
+
+/// 歩く区間は"WALK"にする
+pub(crate) fn trip_id(leg: &JourneyLeg) -> &str {
+    match &leg.kind {
+        LegKind::Ride { trip_id, .. } => trip_id,
+        LegKind::Walk => "WALK",
+    }
+}
+
+/// trip_idの便がstop_idの駅からdelay秒遅れているフィード
+pub(crate) fn delayed(trip_id: &str, stop_id: &str, delay: i32) -> TripDelays {
+    let feed = FeedMessage {
+        header: None,
+        entity: vec![FeedEntity {
+            id: Some("1".to_string()),
+            is_deleted: None,
+            alert: None,
+            trip_update: Some(TripUpdate {
+                trip: Some(TripDescriptor { trip_id: Some(trip_id.to_string()), start_time: None, start_date: None, route_id: None }),
+                stop_time_update: vec![StopTimeUpdate {
+                    stop_sequence: None,
+                    arrival: None,
+                    departure: Some(StopTimeEvent { delay: Some(delay), time: None }),
+                    stop_id: Some(stop_id.to_string()),
+                }],
+                timestamp: None,
+                delay: None,
+            }),
+        }],
+    };
+    TripDelays::from_feed(&feed, None)
+}
+
+/// テストのために書いたデータのルート。テストが途中で失敗しても消えるように、dropしたときに消す
+struct TempDataRoot {
+    path: PathBuf,
+}
+
+impl Drop for TempDataRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// filesの (データのルートからのパス, 内容) を一時ディレクトリに書いて読み込む
+/// 種類ごとのディレクトリはファイルがなくても作る。nameはテストごとに変えて、同時に実行しても重ならないようにする
+pub(crate) fn write_dataset<P: AsRef<str>, C: AsRef<str>>(name: &str, files: &[(P, C)]) -> Result<Dataset, DatasetError> {
+    let root = TempDataRoot { path: std::env::temp_dir().join(format!("transfar-{}-{}", name, std::process::id())) };
+    for dir in ["station", "timetable", "calendar", "departure-pattern", "ride"] {
+        fs::create_dir_all(root.path.join(dir)).expect("create dir");
+    }
+    for (path, content) in files {
+        let path = root.path.join(path.as_ref());
+        fs::create_dir_all(path.parent().unwrap_or(Path::new(""))).expect("create dir");
+        fs::write(path, content.as_ref()).expect("write file");
+    }
+    Dataset::load(root.path.to_str().expect("utf-8 path"))
+}
+
+/// 毎日calendar_idと同じIDの発車パターンを使うカレンダー
+pub(crate) fn calendar_json(calendar_id: &str) -> String {
+    format!(
+        r#"{{"calendar_id": "{0}", "monday": "{0}", "tuesday": "{0}", "wednesday": "{0}", "thursday": "{0}",
+            "friday": "{0}", "saturday": "{0}", "sunday": "{0}", "exception": []}}"#,
+        calendar_id
+    )
+}
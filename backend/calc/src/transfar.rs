@@ -1,68 +1,78 @@
-use chrono::{DateTime, FixedOffset, NaiveDate};
+use chrono::NaiveDate;
 use models::{
-    calendar::Calendar,
-    departure::{Departure, DeparturePattern},
+    departure::Departure,
     id::{StationId, ID},
-    response::{station::ResStation, transfar::ResTransfar},
-    ride::Ride,
+    response::{alert::ResAlert, station::ResStation, transfar::ResTransfar},
     station::Station,
-    timetable::TimeTable,
-};
-use repositories::{
-    impls::station_repository::StationRepositoryImpl, traits::station_repository::StationRepository,
+    transfar_rule::TransfarKind,
 };
+use repositories::traits::station_repository::StationRepository;
 use std::collections::{HashSet, VecDeque};
 use std::error::Error;
 
+use crate::dataset::Dataset;
+use crate::raptor::network::route_position;
+use crate::realtime::alerts::Alerts;
+use crate::realtime::trip_delays::{RealtimeStatus, TripDelays};
+
 #[derive(Clone, Debug)]
 pub struct TransfarChain {
+    /// timeはリアルタイムの遅れを反映した時刻
     pub departure: Departure,
     pub ride_at: StationId,
     pub ride_for: StationId,
     pub transfar_time: i64,
+    pub realtime: RealtimeStatus,
 }
 
 /// ある駅であるDepartureを選んだ際に可能な乗り継ぎを列挙する
+/// service_dateはdepartureの運行日。乗り継ぎ先も同じ運行日の便から探す
+/// 乗り継ぎはリアルタイムの遅れを反映した時刻で判定し、運休の便には乗り継がない
 pub(crate) fn calc_transfars(
+    dataset: &Dataset,
+    delays: &TripDelays,
+    alerts: &Alerts,
     departure: &Departure,
     start: &Station,
-    datetime: DateTime<FixedOffset>,
-) -> Vec<Vec<ResTransfar>> {
-    let station_repository = StationRepositoryImpl;
-    let chains = build_departure_path(&start.station_id, &departure, datetime, &station_repository);
+    service_date: NaiveDate,
+) -> Result<Vec<Vec<ResTransfar>>, Box<dyn Error + Send + Sync + 'static>> {
+    let chains = build_departure_path(dataset, delays, alerts, &start.station_id, departure, service_date);
     let mut res: Vec<Vec<ResTransfar>> = Vec::new();
     // DFSの関係上乗り換えに時間がかかるやつから出てくるのでここで逆順にする
     for chain in chains.into_iter().rev() {
-        let res_chain: Vec<ResTransfar> = chain
+        let res_chain = chain
             .iter()
             .map(|x| {
-                let ride = Ride::from_id(x.departure.ride_id.get_raw_id()).unwrap();
-                let to: ResStation = station_repository
-                    .from_id(x.ride_for.clone())
-                    .unwrap()
-                    .into();
-                let at: ResStation = station_repository.from_id(x.ride_at.clone()).unwrap().into();
-                ResTransfar {
+                let ride = dataset
+                    .ride(&x.departure.ride_id)
+                    .ok_or(format!("Ride {} not found.", x.departure.ride_id.get_raw_id()))?;
+                let to: ResStation = dataset.find_by_id(x.ride_for.clone())?.into();
+                let at: ResStation = dataset.find_by_id(x.ride_at.clone())?.into();
+                Ok(ResTransfar {
                     ride_type: ride.ride_type.clone(),
                     type_foreground: ride.type_foreground.clone(),
                     type_background: ride.type_background.clone(),
-                    to: to,
-                    career_type: ride.career_type.clone(),
-                    depart_at: x.departure.time.format("%H:%M").to_string(),
-                    at: at,
+                    to,
+                    career_type: ride.career_type,
+                    depart_at: x.departure.time.wall_clock().format("%H:%M").to_string(),
+                    delay_minutes: x.realtime.delay_minutes(),
+                    realtime: x.realtime.realtime,
+                    alerts: notices(dataset, alerts, &x.departure, &x.ride_at, service_date),
+                    at,
                     transfar_time: x.transfar_time
-                }
+                })
             })
-            .collect::<_>();
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync + 'static>>>()?;
         res.push(res_chain);
     }
-    res
+    Ok(res)
 }
 
 /// ある駅よりあとの停車駅を抽出する
 /// 形式的にはsplitで右側に寄せる
 /// \[1, 2, 3, 4, 2, 5\]でstart=2のとき\[\[1\], \[2, 3, 4\], \[2, 5\]\]になる
 fn calc_stop_after(
+    dataset: &Dataset,
     route: Vec<StationId>,
     start: &StationId,
     station_loop_count: i32,
@@ -71,7 +81,7 @@ fn calc_stop_after(
     let mut current: Vec<StationId> = Vec::new();
 
     for station in route.into_iter() {
-        if station.is_same_station(start) {
+        if dataset.is_same_station(&station, start) {
             chunks.push(std::mem::take(&mut current));
             current.push(station);
         } else {
@@ -95,82 +105,154 @@ fn get_transfarable_departures(
     transfar_from: &Departure,
 ) -> Vec<Departure> {
     let mut sorted = departures.clone();
-    sorted.sort_by(|x, y| x.time.cmp(&y.time));
+    sorted.sort_by_key(|x| x.time);
     sorted
         .iter()
         .skip_while(|x| {
             x.trip_id != transfar_from.trip_id || x.loop_count != transfar_from.loop_count
         })
-        .filter(|x| x.departure_type != "incoming".to_string())
+        .filter(|x| x.departure_type.can_board())
         .map(|x| x.to_owned())
         .collect()
 }
 
 /// ある駅のDepartureをjoinしている駅も含めてすべて取得する
-/// 結果は時刻順にソートされている
+/// 時刻はリアルタイムの遅れを反映したもので、結果はその時刻順にソートされている
+/// 運休や駅の閉鎖で取りやめになった発車は含めない
 fn get_departures_from_station_id(
+    dataset: &Dataset,
+    delays: &TripDelays,
+    alerts: &Alerts,
     id: &StationId,
     today: NaiveDate,
-    station_repository: &dyn StationRepository,
 ) -> Result<Vec<Departure>, Box<dyn Error + Sync + Send + 'static>> {
-    let mut already_joined: HashSet<String> = HashSet::new();
-    already_joined.insert(id.get_raw_id());
-    let timetable = TimeTable::from_station_id(id.clone())?;
-    let current_calendar = timetable
-        .get_valid_calendar(today)
-        .ok_or("There are no valid calendar version.")?;
-    let calendar_res = Calendar::from_id(current_calendar.calendar_id)?;
-    let pattern_id = calendar_res.get_pattern_id(today);
-
-    let mut root_departure_pattern = DeparturePattern::from_id(pattern_id)?.departures;
-    let station = station_repository.from_id(id.clone())?;
+    let mut root_departure_pattern = dataset.pattern_on(id, today)?.departures.clone();
+    let station = dataset.find_by_id(id.clone())?;
     for other_station_id in station.join {
-        already_joined.insert(other_station_id.get_raw_id());
-        let other_station = station_repository.from_id(other_station_id.clone());
-        if other_station.is_err() {
+        let Some(other_station) = dataset.station(&other_station_id) else {
             continue;
-        }
-        if !station_repository.check_is_valid(other_station.unwrap()) {
+        };
+        if !dataset.check_is_valid(other_station.clone()) {
             continue;
         }
 
-        let other_timetable = TimeTable::from_station_id(other_station_id.clone())?;
-        let other_current_calendar = other_timetable
-            .get_valid_calendar(today)
-            .ok_or("There are no valid calendar version.")?;
-        let other_calendar_res = Calendar::from_id(other_current_calendar.calendar_id)?;
-        let other_pattern_id = other_calendar_res.get_pattern_id(today);
-        DeparturePattern::from_id(other_pattern_id)?
+        dataset.pattern_on(&other_station_id, today)?
             .departures
-            .into_iter()
-            .for_each(|x| root_departure_pattern.push(x));
+            .iter()
+            .for_each(|x| root_departure_pattern.push(x.clone()));
     }
 
+    if !alerts.is_empty() {
+        root_departure_pattern.retain(|x| !is_cancelled(dataset, alerts, x, id, today));
+    }
+    if !delays.is_empty() {
+        for departure in root_departure_pattern.iter_mut() {
+            departure.time = realtime_status(dataset, delays, departure, id, today).apply(departure.time);
+        }
+    }
     root_departure_pattern.sort_by_key(|x| x.time);
     Ok(root_departure_pattern)
 }
 
+/// departureが実際に出る駅。joinでつながった駅から出る便もあるので、Rideのrouteにある駅を探す
+fn boarding_station(dataset: &Dataset, departure: &Departure, station_id: &StationId) -> StationId {
+    let Some(ride) = dataset.ride(&departure.ride_id) else {
+        return station_id.clone();
+    };
+    let joined = dataset.station(station_id).map(|x| x.join.clone()).unwrap_or_default();
+    std::iter::once(station_id)
+        .chain(joined.iter())
+        .find(|x| route_position(ride, x, departure.loop_count).is_some())
+        .unwrap_or(station_id)
+        .clone()
+}
+
+/// departureがstation_id (joinでつながった駅を含む) を出るときのリアルタイムの情報
+pub(crate) fn realtime_status(
+    dataset: &Dataset,
+    delays: &TripDelays,
+    departure: &Departure,
+    station_id: &StationId,
+    service_date: NaiveDate,
+) -> RealtimeStatus {
+    let Some(ride) = dataset.ride(&departure.ride_id) else {
+        return RealtimeStatus::default();
+    };
+    let at = boarding_station(dataset, departure, station_id);
+    delays.status_at(&departure.trip_id, service_date, ride, &at, departure.loop_count)
+}
+
+/// departureがstation_id (joinでつながった駅を含む) を出る発車が、運休か駅の閉鎖で取りやめならtrue
+pub(crate) fn is_cancelled(
+    dataset: &Dataset,
+    alerts: &Alerts,
+    departure: &Departure,
+    station_id: &StationId,
+    service_date: NaiveDate,
+) -> bool {
+    !alerts.is_empty() && alerts.is_cancelled(
+        &boarding_station(dataset, departure, station_id).get_raw_id(),
+        &departure.ride_id.get_raw_id(),
+        &departure.trip_id,
+        service_date,
+    )
+}
+
+/// departureがstation_id (joinでつながった駅を含む) を出る発車に当てはまるお知らせ
+pub(crate) fn notices(
+    dataset: &Dataset,
+    alerts: &Alerts,
+    departure: &Departure,
+    station_id: &StationId,
+    service_date: NaiveDate,
+) -> Vec<ResAlert> {
+    if alerts.is_empty() {
+        return vec![];
+    }
+    alerts
+        .notices(
+            &boarding_station(dataset, departure, station_id).get_raw_id(),
+            &departure.ride_id.get_raw_id(),
+            &departure.trip_id,
+            service_date,
+        )
+        .into_iter()
+        .map(ResAlert::from)
+        .collect()
+}
+
+/// root_departureは時刻表の時刻のもの。乗り継ぎはリアルタイムの遅れを反映した時刻で探す
+/// 運休や駅の閉鎖で取りやめになった発車には乗り継がない。root_departureが取りやめなら何も返さない
 pub fn build_departure_path(
+    dataset: &Dataset,
+    delays: &TripDelays,
+    alerts: &Alerts,
     root_station: &StationId,
     root_departure: &Departure,
-    datetime: DateTime<FixedOffset>,
-    station_repository: &dyn StationRepository,
+    service_date: NaiveDate,
 ) -> Vec<Vec<TransfarChain>> {
-    let today = datetime.date_naive();
+    let today = service_date;
     let mut stack: VecDeque<Vec<TransfarChain>> = VecDeque::new();
     let mut res: Vec<Vec<TransfarChain>> = Vec::new();
     // 初期状態 (乗り換え検索の最初の駅であるdepartureに乗車した状態) をpush
-    let Ok(root_ride) = Ride::from_id(root_departure.ride_id.clone().get_raw_id()) else {
+    if is_cancelled(dataset, alerts, root_departure, root_station, today) {
+        return vec![];
+    }
+    let Some(root_ride) = dataset.ride(&root_departure.ride_id) else {
+        return vec![];
+    };
+    let Some(root_last_stop) = root_ride.route.last().cloned() else {
         return vec![];
     };
-    let root_last_stop = root_ride.route.last().unwrap().to_owned();
+    let root_realtime = realtime_status(dataset, delays, root_departure, root_station, today);
     stack.push_back(
         vec![
             TransfarChain{ 
-                departure: root_departure.clone(),
+                departure: Departure { time: root_realtime.apply(root_departure.time), ..root_departure.clone() },
                 ride_at: root_station.clone(),
                 ride_for: root_last_stop,
                 transfar_time: 0,
+                realtime: root_realtime,
             }
         ]
     );
@@ -185,25 +267,27 @@ pub fn build_departure_path(
             res.push(chain.clone());
         }
 
-        let transfar_from = chain.last().unwrap().to_owned();
-        let Ok(ride) = Ride::from_id(transfar_from.departure.ride_id.get_raw_id()) else {
+        let Some(transfar_from) = chain.last().cloned() else {
+            continue;
+        };
+        let Some(ride) = dataset.ride(&transfar_from.departure.ride_id) else {
             continue;
         };
 
         // 次駅以降を対象にに乗り換え可能駅と乗り換えを探す
-        let stop_after = calc_stop_after(ride.route, &transfar_from.ride_at, transfar_from.ride_at.get_loop_count())
+        let stop_after = calc_stop_after(dataset, ride.route.clone(), &transfar_from.ride_at, transfar_from.ride_at.get_loop_count())
             .into_iter()
             .skip(1)
             .collect::<Vec<_>>();
         for station in stop_after {
-            let Ok(pattern) = get_departures_from_station_id(&station, today, station_repository) else {
+            let Ok(pattern) = get_departures_from_station_id(dataset, delays, alerts, &station, today) else {
                 continue;
             };
             
             // 今乗っている便が到着するときのdepartureを探す
             let Some(arrive) = pattern
                 .iter()
-                .filter(|x| x.departure_type == "incoming".to_string() || x.departure_type == "both".to_string())
+                .filter(|x| x.departure_type.can_alight())
                 .find(|x| {
                     x.trip_id == transfar_from.departure.trip_id && x.time >= transfar_from.departure.time
                 }) else {
@@ -213,22 +297,23 @@ pub fn build_departure_path(
                 };
             
             // 自分の出発情報を先頭に含むので1つskipする
-            let depart_after = get_transfarable_departures(pattern.clone(), &arrive)
+            let depart_after = get_transfarable_departures(pattern.clone(), arrive)
                 .into_iter()
                 .skip(1)
-                .filter(|x| x.departure_type != "incoming".to_string());
+                .filter(|x| x.departure_type.can_board());
             let mut ride_id_memo: HashSet<String> = HashSet::new();
             for target in depart_after {
                 // 最低限の枝狩り (あとから発車する同じ系統 | すでに乗り換えが見つかっているのと同じ系統) を行う
                 if target.ride_id != arrive.ride_id && !ride_id_memo.contains(&target.ride_id.get_raw_id()) {
-                    let explored_stations = get_explored_stations(&chain);
-                    let Ok(target_ride) = Ride::from_id(target.ride_id.get_raw_id()) else { continue; };
+                    let explored_stations = get_explored_stations(dataset, &chain);
+                    let Some(target_ride) = dataset.ride(&target.ride_id) else { continue; };
                     // debug!("target: {:?}, target_original_route: {:?}", target_ride, target_ride.route);
-                    let target_route = calc_stop_after(target_ride.route, &station, target.loop_count)
+                    let target_route = calc_stop_after(dataset, target_ride.route.clone(), &station, target.loop_count)
                         .into_iter()
                         .collect::<Vec<_>>();
                     // debug!("explored_stations: {:?}\ntarget_route: {:?}", explored_stations, target_route);
                     let Some(valid_destinations) = find_valid_transfar_route(
+                        dataset,
                         explored_stations,
                         target_route,
                         &station,
@@ -238,9 +323,16 @@ pub fn build_departure_path(
                         continue;
                     };
 
-                    let transfar_time = (target.time-arrive.time).num_minutes();
+                    let transfar_time = target.time.minutes_since(arrive.time);
                     // 乗り換え時間が長過ぎる or 短すぎる場合は除く
-                    if !is_acceptable_transfar_time(&ride.career_type, &target_ride.career_type, transfar_time) {
+                    let transfar = TransfarKind {
+                        from_station: &station,
+                        to_station: &station,
+                        from_mode: ride.career_type,
+                        to_mode: target_ride.career_type,
+                        to_ride: &target.ride_id,
+                    };
+                    if !dataset.transfar_rules().is_acceptable(&transfar, transfar_time) {
                         continue;
                     }
 
@@ -249,8 +341,10 @@ pub fn build_departure_path(
                     next.push(TransfarChain {
                         departure: target.clone(),
                         ride_at: station.clone(),
-                        ride_for: valid_destinations.last().unwrap().to_owned(),
-                        transfar_time: transfar_time,
+                        // find_valid_transfar_routeは空でないVecしか返さない
+                        ride_for: valid_destinations[valid_destinations.len() - 1].clone(),
+                        transfar_time,
+                        realtime: realtime_status(dataset, delays, &target, &station, today),
                     });
                     stack.push_back(next);
                 }
@@ -260,55 +354,30 @@ pub fn build_departure_path(
     res
 }
 
-fn is_acceptable_transfar_time(
-    from_type: &str,
-    to_type: &str,
-    transfar_time: i64,
-) -> bool {
-    if from_type == "BUS" && to_type == "BUS" {
-        // バス→バス: 0分以上150分以下までを許容
-        0 <= transfar_time && transfar_time <= 150
-    }
-    else if from_type == "BUS" && to_type == "AIRPLANE" {
-        // バス→飛行機: 30分以上240分以下までを許容
-        30 <= transfar_time && transfar_time <= 240
-    }
-    else if from_type == "BUS" && to_type == "LTDEXPTRAIN" {
-        10 <= transfar_time && transfar_time <= 120
-    }
-    else if from_type == "AIRPLANE" && to_type == "AIRPLANE" {
-        // 飛行機→飛行機: トランジットの可能性を考え、0分以上なら全て許容
-        0 <= transfar_time
-    }
-    else {
-        0 <= transfar_time
-    }
-}
-
 /// Vec<TransfarChain>のそれぞれから既に到達可能だった行き先 (逆方向に向かったときも含めて) をすべてVecにまとめて返す
 fn get_explored_stations(
-    chain: &Vec<TransfarChain>
+    dataset: &Dataset,
+    chain: &[TransfarChain]
 ) -> Vec<StationId> {
     chain
-        .into_iter()
-        .map(|x| x.departure.ride_id.clone())
-        .filter_map(|x| Ride::from_id(x.get_raw_id()).ok())
-        .map(|x| x.route)
-        .flatten()
+        .iter()
+        .filter_map(|x| dataset.ride(&x.departure.ride_id))
+        .flat_map(|x| x.route.iter().cloned())
         .collect::<Vec<StationId>>()
 }
 
 fn find_valid_transfar_route(
+    dataset: &Dataset,
     explored_stations: Vec<StationId>,
     target_route: Vec<StationId>,
     transfar_at: &StationId,
     transfar_to: &Departure,
 ) -> Option<Vec<StationId>> {
     let valid_destinations: Vec<StationId> = 
-        calc_stop_after(target_route, transfar_at, transfar_to.loop_count)
+        calc_stop_after(dataset, target_route, transfar_at, transfar_to.loop_count)
             .into_iter()
-            .skip_while(|x| x.is_same_station(&transfar_at))
-            .take_while(|x| explored_stations.iter().find(|y| y.is_same_station(x)) == None)
+            .skip_while(|x| dataset.is_same_station(x, transfar_at))
+            .take_while(|x| !explored_stations.iter().any(|y| dataset.is_same_station(y, x)))
             .collect();
     if valid_destinations.is_empty() {
         None
@@ -321,60 +390,44 @@ fn find_valid_transfar_route(
 #[allow(non_snake_case)]
 #[cfg(test)]
 mod test {
-    use super::{calc_stop_after, find_valid_transfar_route, get_transfarable_departures};
-    use chrono::NaiveTime;
-    use models::departure::Departure;
+    use super::{calc_stop_after, find_valid_transfar_route};
+    use crate::dataset::Dataset;
+    use models::departure::{Departure, DepartureType};
     use models::id::{ID, RideId, StationId};
-    use models::response::transfar::ResTransfar;
-    use models::ride::Ride;
-    use repositories::development::station_repository::DevelopmentStationRepository;
-    use repositories::traits::station_repository::StationRepository;
-
-    fn dummy_ride(to: StationId, route: Vec<StationId>) -> Ride {
-        Ride {
-            ride_type: "".to_string(),
-            aka_type: "".to_string(),
-            type_foreground: "".to_string(),
-            type_background: "".to_string(),
-            type_pronounce: "".to_string(),
-            to: to,
-            career_type: "".to_string(),
-            route: route,
-        }
-    }
+    use models::service_time::ServiceTime;
 
     #[test]
     fn stop_afterを正しく計算できる_通常ケース() {
-        let route: Vec<StationId> = (1..=10).map(|x| StationId::new(x.to_string())).collect();
-        let start = StationId::new(4.to_string());
-        let actual = calc_stop_after(route.clone(), &start, 1);
-        let expected: Vec<StationId> = (4..=10).map(|x| StationId::new(x.to_string())).collect();
+        let route: Vec<StationId> = (1..=10).map(|x| StationId::new(x.to_string(), "")).collect();
+        let start = StationId::new(4.to_string(), "");
+        let actual = calc_stop_after(&Dataset::default(), route.clone(), &start, 1);
+        let expected: Vec<StationId> = (4..=10).map(|x| StationId::new(x.to_string(), "")).collect();
         assert_eq!(actual, expected);
     }
 
     #[test]
     fn stop_afterを正しく計算できる_環状ケース() {
         let route: Vec<StationId> = vec![
-            StationId::new("Hikarigaoka".to_string()),
-            StationId::new("Nakano-Sakaue".to_string()),
-            StationId::new("Tochomae~1".to_string()),
-            StationId::new("Shinjuku".to_string()),
-            StationId::new("Kokuritsu-kyogijo".to_string()),
-            StationId::new("Daimon".to_string()),
-            StationId::new("Ueno-Okachimachi".to_string()),
-            StationId::new("Idabashi".to_string()),
-            StationId::new("Tochomae~2".to_string()),
-            StationId::new("Nishi-Shinjuku-Gochome".to_string()),
+            StationId::new("Hikarigaoka".to_string(), ""),
+            StationId::new("Nakano-Sakaue".to_string(), ""),
+            StationId::new("Tochomae~1".to_string(), ""),
+            StationId::new("Shinjuku".to_string(), ""),
+            StationId::new("Kokuritsu-kyogijo".to_string(), ""),
+            StationId::new("Daimon".to_string(), ""),
+            StationId::new("Ueno-Okachimachi".to_string(), ""),
+            StationId::new("Idabashi".to_string(), ""),
+            StationId::new("Tochomae~2".to_string(), ""),
+            StationId::new("Nishi-Shinjuku-Gochome".to_string(), ""),
         ];
-        let start = StationId::new("Tochomae".to_string());
-        let actual = calc_stop_after(route.clone(), &start, 1);
+        let start = StationId::new("Tochomae".to_string(), "");
+        let actual = calc_stop_after(&Dataset::default(), route.clone(), &start, 1);
         let expected: Vec<StationId> = vec![
-            StationId::new("Tochomae~1".to_string()),
-            StationId::new("Shinjuku".to_string()),
-            StationId::new("Kokuritsu-kyogijo".to_string()),
-            StationId::new("Daimon".to_string()),
-            StationId::new("Ueno-Okachimachi".to_string()),
-            StationId::new("Idabashi".to_string()),
+            StationId::new("Tochomae~1".to_string(), ""),
+            StationId::new("Shinjuku".to_string(), ""),
+            StationId::new("Kokuritsu-kyogijo".to_string(), ""),
+            StationId::new("Daimon".to_string(), ""),
+            StationId::new("Ueno-Okachimachi".to_string(), ""),
+            StationId::new("Idabashi".to_string(), ""),
         ];
         assert_eq!(expected, actual);
     }
@@ -382,22 +435,22 @@ mod test {
     #[test]
     fn stop_afterを正しく計算できる_環状ケース2回目以降() {
         let route: Vec<StationId> = vec![
-            StationId::new("Hikarigaoka".to_string()),
-            StationId::new("Nakano-Sakaue".to_string()),
-            StationId::new("Tochomae~1".to_string()),
-            StationId::new("Shinjuku".to_string()),
-            StationId::new("Kokuritsu-kyogijo".to_string()),
-            StationId::new("Daimon".to_string()),
-            StationId::new("Ueno-Okachimachi".to_string()),
-            StationId::new("Idabashi".to_string()),
-            StationId::new("Tochomae~2".to_string()),
-            StationId::new("Nishi-Shinjuku-Gochome".to_string()),
+            StationId::new("Hikarigaoka".to_string(), ""),
+            StationId::new("Nakano-Sakaue".to_string(), ""),
+            StationId::new("Tochomae~1".to_string(), ""),
+            StationId::new("Shinjuku".to_string(), ""),
+            StationId::new("Kokuritsu-kyogijo".to_string(), ""),
+            StationId::new("Daimon".to_string(), ""),
+            StationId::new("Ueno-Okachimachi".to_string(), ""),
+            StationId::new("Idabashi".to_string(), ""),
+            StationId::new("Tochomae~2".to_string(), ""),
+            StationId::new("Nishi-Shinjuku-Gochome".to_string(), ""),
         ];
-        let start = StationId::new("Tochomae".to_string());
-        let actual = calc_stop_after(route.clone(), &start, 2);
+        let start = StationId::new("Tochomae".to_string(), "");
+        let actual = calc_stop_after(&Dataset::default(), route.clone(), &start, 2);
         let expected: Vec<StationId> = vec![
-            StationId::new("Tochomae~2".to_string()),
-            StationId::new("Nishi-Shinjuku-Gochome".to_string()),
+            StationId::new("Tochomae~2".to_string(), ""),
+            StationId::new("Nishi-Shinjuku-Gochome".to_string(), ""),
         ];
         assert_eq!(expected, actual);
     }
@@ -405,36 +458,51 @@ mod test {
     #[test]
     fn debug_find_valid_transfar() {
         let explored_stations = vec![
-            StationId::new("01".to_string()),
-            StationId::new("02".to_string()),
-            StationId::new("03".to_string()),
+            StationId::new("01".to_string(), ""),
+            StationId::new("02".to_string(), ""),
+            StationId::new("03".to_string(), ""),
         ];
         let target_route = vec![
-            StationId::new("00".to_string()),
-            StationId::new("02".to_string()),
-            StationId::new("04".to_string()),
+            StationId::new("00".to_string(), ""),
+            StationId::new("02".to_string(), ""),
+            StationId::new("04".to_string(), ""),
         ];
-        let transfar_at = StationId::new("02".to_string());
+        let transfar_at = StationId::new("02".to_string(), "");
         let transfar_to = Departure {
-            ride_id: RideId::new("Hoge".to_string()),
+            ride_id: RideId::new("Hoge".to_string(), ""),
             trip_id: "Hoge".to_string(),
-            time: NaiveTime::from_hms_opt(12, 0, 0).expect("Valid hms"),
+            time: ServiceTime::from_hm(12, 0),
             loop_count: 1,
-            departure_type: "both".to_string()
+            departure_type: DepartureType::Both
         };
-        let actual = find_valid_transfar_route(explored_stations, target_route, &transfar_at, &transfar_to);
+        let actual = find_valid_transfar_route(&Dataset::default(), explored_stations, target_route, &transfar_at, &transfar_to);
 
         assert!(actual.is_some());
     }
 /* todo: ちゃんとテストが動くように治す
+    use models::ride::{CareerType, Ride};
+
+    fn dummy_ride(to: StationId, route: Vec<StationId>) -> Ride {
+        Ride {
+            ride_type: "".to_string(),
+            aka_type: "".to_string(),
+            type_foreground: "".to_string(),
+            type_background: "".to_string(),
+            type_pronounce: "".to_string(),
+            to,
+            career_type: CareerType::Bus,
+            route,
+        }
+    }
+
     #[test]
     fn このあと出発するDepartureを正しく抽出できる() {
         let departures: Vec<Departure> = (0..5)
             .into_iter()
             .map(|x| Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: x.to_string(),
-                time: NaiveTime::from_hms_opt(x + 7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(x + 7, 0),
                 loop_count: 0,
             })
             .collect();
@@ -443,15 +511,15 @@ mod test {
 
         let expected_depart_after = vec![
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "3".to_string(),
-                time: NaiveTime::from_hms_opt(10, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(10, 0),
                 loop_count: 0,
             },
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "4".to_string(),
-                time: NaiveTime::from_hms_opt(11, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(11, 0),
                 loop_count: 0,
             },
         ];
@@ -463,55 +531,55 @@ mod test {
     fn loop_countがあってもこのあと出発するDepartureを正しく抽出できる() {
         let departures = vec![
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "0".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 0,
             },
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "0".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 1,
             },
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "1".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 0,
             },
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "2".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 0,
             },
         ];
 
         let cur_departure = Departure {
-            ride_id: RideId::new("TargetRide".to_string()),
+            ride_id: RideId::new("TargetRide".to_string(), ""),
             trip_id: "0".to_string(),
-            time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+            time: ServiceTime::from_hm(7, 0),
             loop_count: 1,
         };
 
         let expected_depart_after: Vec<Departure> = vec![
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "0".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 1,
             },
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "1".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 0,
             },
             Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: "2".to_string(),
-                time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(7, 0),
                 loop_count: 0,
             },
         ];
@@ -525,16 +593,16 @@ mod test {
         let departures: Vec<Departure> = (0..5)
             .into_iter()
             .map(|x| Departure {
-                ride_id: RideId::new("TargetRide".to_string()),
+                ride_id: RideId::new("TargetRide".to_string(), ""),
                 trip_id: x.to_string(),
-                time: NaiveTime::from_hms_opt(x + 7, 0, 0).expect("Valid hms"),
+                time: ServiceTime::from_hm(x + 7, 0),
                 loop_count: 0,
             })
             .collect();
         let transfar_from = Departure {
-            ride_id: RideId::new("TargetRide".to_string()),
+            ride_id: RideId::new("TargetRide".to_string(), ""),
             trip_id: "0".to_string(),
-            time: NaiveTime::from_hms_opt(7, 0, 0).expect("Valid hms"),
+            time: ServiceTime::from_hm(7, 0),
             loop_count: 1,
         };
         let actual = get_transfarable_departures(departures, &transfar_from);
@@ -544,36 +612,36 @@ mod test {
 
     #[test]
     fn valid_transfar_ナイーブなケース() {
-        let cur_station = StationId::new("03".to_string());
+        let cur_station = StationId::new("03".to_string(), "");
         let cur_ride = dummy_ride(
-            StationId::new("04".to_string()),
+            StationId::new("04".to_string(), ""),
             vec![
-                StationId::new("01".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
-                StationId::new("04".to_string()),
+                StationId::new("01".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
+                StationId::new("04".to_string(), ""),
             ],
         );
         let target_departure = Departure {
-            ride_id: RideId::new("B".to_string()),
+            ride_id: RideId::new("B".to_string(), ""),
             trip_id: "B".to_string(),
-            time: NaiveTime::from_hms_opt(12, 10, 0).expect("valid hms"),
+            time: ServiceTime::from_hm(12, 10),
             loop_count: 1,
         };
         let target_ride = dummy_ride(
-            StationId::new("07".to_string()),
+            StationId::new("07".to_string(), ""),
             vec![
-                StationId::new("05".to_string()),
-                StationId::new("03".to_string()),
-                StationId::new("06".to_string()),
-                StationId::new("07".to_string()),
+                StationId::new("05".to_string(), ""),
+                StationId::new("03".to_string(), ""),
+                StationId::new("06".to_string(), ""),
+                StationId::new("07".to_string(), ""),
             ],
         );
 
         let station_repository = DevelopmentStationRepository::new(|_| vec![]);
         let actual = calc_transfar(
             cur_ride.route.clone(),
-            &NaiveTime::from_hms_opt(12, 0, 0).expect("valid hms"),
+            &ServiceTime::from_hm(12, 0),
             &cur_station,
             &target_departure,
             &target_ride,
@@ -584,12 +652,12 @@ mod test {
             ride_type: "".to_string(),
             type_foreground: "".to_string(),
             type_background: "".to_string(),
-            at: station_repository.from_id(cur_station).unwrap().into(),
+            at: station_repository.find_by_id(cur_station).unwrap().into(),
             to: station_repository
-                .from_id(StationId::new("07".to_string()))
+                .find_by_id(StationId::new("07".to_string(), ""))
                 .unwrap()
                 .into(),
-            career_type: "".to_string(),
+            career_type: CareerType::Bus,
             depart_at: "12:10".to_string(),
             transfar_time: 10,
         };
@@ -598,34 +666,34 @@ mod test {
 
     #[test]
     fn calc_transfar_invalid_戻るケース() {
-        let cur_station = StationId::new("02".to_string());
+        let cur_station = StationId::new("02".to_string(), "");
         let cur_ride = dummy_ride(
-            StationId::new("03".to_string()),
+            StationId::new("03".to_string(), ""),
             vec![
-                StationId::new("01".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
+                StationId::new("01".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
             ],
         );
         let target_departure = Departure {
-            ride_id: RideId::new("B".to_string()),
+            ride_id: RideId::new("B".to_string(), ""),
             trip_id: "B".to_string(),
-            time: NaiveTime::from_hms_opt(12, 10, 0).expect("valid hms"),
+            time: ServiceTime::from_hm(12, 10),
             loop_count: 1,
         };
         let target_ride = dummy_ride(
-            StationId::new("01".to_string()),
+            StationId::new("01".to_string(), ""),
             vec![
-                StationId::new("04".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("01".to_string()),
+                StationId::new("04".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("01".to_string(), ""),
             ],
         );
 
         let station_repository = DevelopmentStationRepository::new(|_| vec![]);
         let actual = calc_transfar(
             cur_ride.route.clone(),
-            &NaiveTime::from_hms_opt(12, 0, 0).expect("valid hms"),
+            &ServiceTime::from_hm(12, 0),
             &cur_station,
             &target_departure,
             &target_ride,
@@ -636,36 +704,36 @@ mod test {
 
     #[test]
     fn calc_transfar_valid_合流するけど別ルートなケース() {
-        let cur_station = StationId::new("02".to_string());
+        let cur_station = StationId::new("02".to_string(), "");
         let cur_ride = dummy_ride(
-            StationId::new("03".to_string()),
+            StationId::new("03".to_string(), ""),
             vec![
-                StationId::new("01".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
+                StationId::new("01".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
             ],
         );
         let target_departure = Departure {
-            ride_id: RideId::new("B".to_string()),
+            ride_id: RideId::new("B".to_string(), ""),
             trip_id: "B".to_string(),
-            time: NaiveTime::from_hms_opt(12, 10, 0).expect("valid hms"),
+            time: ServiceTime::from_hm(12, 10),
             loop_count: 1,
         };
         let target_ride = dummy_ride(
-            StationId::new("01".to_string()),
+            StationId::new("01".to_string(), ""),
             vec![
-                StationId::new("04".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("05".to_string()),
-                StationId::new("06".to_string()),
-                StationId::new("01".to_string()),
+                StationId::new("04".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("05".to_string(), ""),
+                StationId::new("06".to_string(), ""),
+                StationId::new("01".to_string(), ""),
             ],
         );
 
         let station_repository = DevelopmentStationRepository::new(|_| vec![]);
         let actual = calc_transfar(
             cur_ride.route.clone(),
-            &NaiveTime::from_hms_opt(12, 0, 0).expect("valid hms"),
+            &ServiceTime::from_hm(12, 0),
             &cur_station,
             &target_departure,
             &target_ride,
@@ -676,12 +744,12 @@ mod test {
             ride_type: "".to_string(),
             type_foreground: "".to_string(),
             type_background: "".to_string(),
-            at: station_repository.from_id(cur_station).unwrap().into(),
+            at: station_repository.find_by_id(cur_station).unwrap().into(),
             to: station_repository
-                .from_id(StationId::new("06".to_string()))
+                .find_by_id(StationId::new("06".to_string(), ""))
                 .unwrap()
                 .into(),
-            career_type: "".to_string(),
+            career_type: CareerType::Bus,
             depart_at: "12:10".to_string(),
             transfar_time: 10,
         };
@@ -690,33 +758,33 @@ mod test {
 
     #[test]
     fn calc_transfar_invalid_相手側が終点なケース() {
-        let cur_station = StationId::new("02".to_string());
+        let cur_station = StationId::new("02".to_string(), "");
         let cur_ride = dummy_ride(
-            StationId::new("03".to_string()),
+            StationId::new("03".to_string(), ""),
             vec![
-                StationId::new("01".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
+                StationId::new("01".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
             ],
         );
         let target_departure = Departure {
-            ride_id: RideId::new("B".to_string()),
+            ride_id: RideId::new("B".to_string(), ""),
             trip_id: "B".to_string(),
-            time: NaiveTime::from_hms_opt(12, 10, 0).expect("valid hms"),
+            time: ServiceTime::from_hm(12, 10),
             loop_count: 1,
         };
         let target_ride = dummy_ride(
-            StationId::new("02".to_string()),
+            StationId::new("02".to_string(), ""),
             vec![
-                StationId::new("04".to_string()),
-                StationId::new("02".to_string()),
+                StationId::new("04".to_string(), ""),
+                StationId::new("02".to_string(), ""),
             ],
         );
 
         let station_repository = DevelopmentStationRepository::new(|_| vec![]);
         let actual = calc_transfar(
             cur_ride.route.clone(),
-            &NaiveTime::from_hms_opt(12, 0, 0).expect("valid hms"),
+            &ServiceTime::from_hm(12, 0),
             &cur_station,
             &target_departure,
             &target_ride,
@@ -727,37 +795,37 @@ mod test {
 
     #[test]
     fn calc_transfar_invalid_並走区間() {
-        let cur_station = StationId::new("02".to_string());
+        let cur_station = StationId::new("02".to_string(), "");
         let cur_ride = dummy_ride(
-            StationId::new("03".to_string()),
+            StationId::new("03".to_string(), ""),
             vec![
-                StationId::new("01".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
-                StationId::new("04".to_string()),
+                StationId::new("01".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
+                StationId::new("04".to_string(), ""),
             ],
         );
         let target_departure = Departure {
-            ride_id: RideId::new("B".to_string()),
+            ride_id: RideId::new("B".to_string(), ""),
             trip_id: "B".to_string(),
-            time: NaiveTime::from_hms_opt(12, 10, 0).expect("valid hms"),
+            time: ServiceTime::from_hm(12, 10),
             loop_count: 1,
         };
         let target_ride = dummy_ride(
-            StationId::new("02".to_string()),
+            StationId::new("02".to_string(), ""),
             vec![
-                StationId::new("05".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
-                StationId::new("04".to_string()),
-                StationId::new("06".to_string()),
+                StationId::new("05".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
+                StationId::new("04".to_string(), ""),
+                StationId::new("06".to_string(), ""),
             ],
         );
 
         let station_repository = DevelopmentStationRepository::new(|_| vec![]);
         let actual = calc_transfar(
             cur_ride.route.clone(),
-            &NaiveTime::from_hms_opt(12, 0, 0).expect("valid hms"),
+            &ServiceTime::from_hm(12, 0),
             &cur_station,
             &target_departure,
             &target_ride,
@@ -768,33 +836,33 @@ mod test {
 
     #[test]
     fn calc_transfar_valid_相手側が始点なケース() {
-        let cur_station = StationId::new("02".to_string());
+        let cur_station = StationId::new("02".to_string(), "");
         let cur_ride = dummy_ride(
-            StationId::new("03".to_string()),
+            StationId::new("03".to_string(), ""),
             vec![
-                StationId::new("01".to_string()),
-                StationId::new("02".to_string()),
-                StationId::new("03".to_string()),
+                StationId::new("01".to_string(), ""),
+                StationId::new("02".to_string(), ""),
+                StationId::new("03".to_string(), ""),
             ],
         );
         let target_departure = Departure {
-            ride_id: RideId::new("B".to_string()),
+            ride_id: RideId::new("B".to_string(), ""),
             trip_id: "B".to_string(),
-            time: NaiveTime::from_hms_opt(12, 10, 0).expect("valid hms"),
+            time: ServiceTime::from_hm(12, 10),
             loop_count: 1,
         };
         let target_ride = dummy_ride(
-            StationId::new("04".to_string()),
+            StationId::new("04".to_string(), ""),
             vec![
-                StationId::new("02".to_string()),
-                StationId::new("04".to_string()),
+                StationId::new("02".to_string(), ""),
+                StationId::new("04".to_string(), ""),
             ],
         );
 
         let station_repository = DevelopmentStationRepository::new(|_| vec![]);
         let actual = calc_transfar(
             cur_ride.route.clone(),
-            &NaiveTime::from_hms_opt(12, 0, 0).expect("valid hms"),
+            &ServiceTime::from_hm(12, 0),
             &cur_station,
             &target_departure,
             &target_ride,
@@ -805,12 +873,12 @@ mod test {
             ride_type: "".to_string(),
             type_foreground: "".to_string(),
             type_background: "".to_string(),
-            at: station_repository.from_id(cur_station).unwrap().into(),
+            at: station_repository.find_by_id(cur_station).unwrap().into(),
             to: station_repository
-                .from_id(StationId::new("04".to_string()))
+                .find_by_id(StationId::new("04".to_string(), ""))
                 .unwrap()
                 .into(),
-            career_type: "".to_string(),
+            career_type: CareerType::Bus,
             depart_at: "12:10".to_string(),
             transfar_time: 10,
         };
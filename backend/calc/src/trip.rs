@@ -0,0 +1,118 @@
+use chrono::NaiveDate;
+use models::{
+    id::ID,
+    response::{trip::{ResTrip, ResTripStop}, trips::ResTrips},
+};
+use repositories::traits::station_repository::StationRepository;
+use std::error::Error;
+
+use crate::dataset::Dataset;
+use crate::raptor::network::ScheduledTrip;
+
+/// dateの運行日にtrip_idの便が停車する駅と時刻を、Rideの経路の順に返す
+/// 同じtrip_idが1日に複数回使われている場合 (AIRDO_58など) は、それぞれを別の便として出発が早い順に返す
+/// dateに運行しなければ空のVecを返す
+pub fn get_trips(
+    dataset: &Dataset,
+    trip_id: &str,
+    date: NaiveDate,
+) -> Result<ResTrips, Box<dyn Error + Send + Sync + 'static>> {
+    let network = dataset.network(date)?;
+    let mut scheduled = network.scheduled_trips_of(trip_id);
+    scheduled.sort_by_key(|x| x.stops.iter().find_map(|y| y.time));
+
+    let trips = scheduled
+        .iter()
+        .map(|x| to_res_trip(dataset, x, date))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ResTrips { trips })
+}
+
+fn to_res_trip(
+    dataset: &Dataset,
+    trip: &ScheduledTrip,
+    date: NaiveDate,
+) -> Result<ResTrip, Box<dyn Error + Send + Sync + 'static>> {
+    let ride = dataset
+        .ride(&trip.ride_id)
+        .ok_or(format!("Ride {} not found.", trip.ride_id.get_raw_id()))?;
+    let stops = trip
+        .stops
+        .iter()
+        .map(|x| {
+            Ok(ResTripStop {
+                station: dataset.find_by_id(x.station_id.clone())?.into(),
+                loop_count: x.station_id.get_loop_count(),
+                time: x.time.map(|y| y.wall_clock().format("%H:%M").to_string()),
+                date: x.time.map(|y| y.date_on(date).format("%Y-%m-%d").to_string()),
+                can_board: x.can_board,
+                can_alight: x.can_alight,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync + 'static>>>()?;
+
+    Ok(ResTrip {
+        trip_id: trip.trip_id.clone(),
+        ride_id: trip.ride_id.get_raw_id(),
+        ride_type: ride.ride_type.clone(),
+        aka_type: if ride.aka_type.is_empty() { None } else { Some(ride.aka_type.clone()) },
+        type_foreground: ride.type_foreground.clone(),
+        type_background: ride.type_background.clone(),
+        type_pronounce: ride.type_pronounce.clone(),
+        career_type: ride.career_type,
+        to: dataset.find_by_id(ride.to.clone())?.into(),
+        service_date: date.format("%Y-%m-%d").to_string(),
+        stops,
+    })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::get_trips;
+    use crate::dataset::Dataset;
+    use chrono::NaiveDate;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 4, 25).unwrap()
+    }
+
+    #[test]
+    fn 便の停車駅と時刻を経路の順に返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        let trips = get_trips(&dataset, "HAKODATEBUS_55G-U-0959", date()).unwrap().trips;
+        assert_eq!(1, trips.len());
+
+        let trip = &trips[0];
+        assert_eq!("HAKODATEBUS_55G-U", trip.ride_id);
+        assert_eq!("55G", trip.ride_type);
+        let times: Vec<&String> = trip.stops.iter().filter_map(|x| x.time.as_ref()).collect();
+        assert!(times.len() >= 2);
+        assert!(times.windows(2).all(|x| x[0] <= x[1]));
+        assert!(trip.stops.iter().all(|x| x.time.is_none() || x.date.as_deref() == Some("2025-04-25")));
+    }
+
+    #[test]
+    fn ループする経路は何回目の停車かを返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        let trips = get_trips(&dataset, "HAKODATEBUS_7A-1314", date()).unwrap().trips;
+        assert_eq!(1, trips.len());
+
+        let passes: Vec<i32> = trips[0].stops.iter().filter(|x| x.station.id == "HAKODATEBUS_030001").map(|x| x.loop_count).collect();
+        assert_eq!(vec![1, 2], passes);
+    }
+
+    #[test]
+    fn 同じtrip_idの便が複数あればそれぞれ返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        let trips = get_trips(&dataset, "AIRDO_58", date()).unwrap().trips;
+        assert_eq!(2, trips.len());
+        assert!(trips[0].stops[0].time < trips[1].stops[0].time);
+    }
+
+    #[test]
+    fn 運行しない便は空で返す() {
+        let dataset = Dataset::load("../data").unwrap();
+        assert!(get_trips(&dataset, "HAKODATEBUS_UNKNOWN", date()).unwrap().trips.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use std::{env, error::Error, process::ExitCode};
+use calc::dataset::Dataset;
+use chrono::{Days, FixedOffset, NaiveDate, Utc};
+use gtfs::export::{export, ExportOptions};
+
+const USAGE: &str = "usage: transfar-gtfs-export --agency-url <url> --out <feed.zip|feed directory> \
+    [--data-root <data root>] [--start YYYY-MM-DD] [--end YYYY-MM-DD] [--timezone <tz>]";
+const DEFAULT_DATA_ROOT: &str = "data";
+const DEFAULT_TIMEZONE: &str = "Asia/Tokyo";
+/// --endを省略したときに書き出す日数
+const DEFAULT_EXPORT_DAYS: u64 = 90;
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    data_root: Option<String>,
+    out: Option<String>,
+    agency_url: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    timezone: Option<String>,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut res = Args::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let (key, inline_value) = match arg.split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let target = match key.as_str() {
+            "--data-root" => &mut res.data_root,
+            "--out" => &mut res.out,
+            "--agency-url" => &mut res.agency_url,
+            "--start" => &mut res.start,
+            "--end" => &mut res.end,
+            "--timezone" => &mut res.timezone,
+            _ => return Err(format!("unknown argument: {}", arg)),
+        };
+        let value = inline_value
+            .or_else(|| args.next())
+            .ok_or(format!("{} requires a value", key))?;
+        *target = Some(value);
+    }
+    Ok(res)
+}
+
+fn parse_date(key: &str, raw: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| format!("{} must be formatted as YYYY-MM-DD but got `{}`.", key, raw))
+}
+
+fn run() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let args = parse_args(env::args().skip(1))?;
+    let (Some(out), Some(agency_url)) = (args.out, args.agency_url) else {
+        return Err(USAGE.into());
+    };
+    let start = match &args.start {
+        Some(start) => parse_date("--start", start)?,
+        None => Utc::now().with_timezone(&FixedOffset::east_opt(9 * 60 * 60).expect("valid offset")).date_naive(),
+    };
+    let end = match &args.end {
+        Some(end) => parse_date("--end", end)?,
+        None => start.checked_add_days(Days::new(DEFAULT_EXPORT_DAYS - 1)).ok_or("--start is out of range.")?,
+    };
+    let data_root = args.data_root.unwrap_or(DEFAULT_DATA_ROOT.to_string());
+
+    let dataset = Dataset::load(&data_root)?;
+    let options = ExportOptions {
+        start,
+        end,
+        agency_url,
+        agency_timezone: args.timezone.unwrap_or(DEFAULT_TIMEZONE.to_string()),
+    };
+    let exported = export(&dataset, &options)?;
+    for warning in exported.warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    exported.feed.write(&out)?;
+    println!(
+        "wrote {} ({} stops, {} routes, {} trips, {} stop_times) for {} to {}",
+        out,
+        exported.feed.stops.len(),
+        exported.feed.routes.len(),
+        exported.feed.trips.len(),
+        exported.feed.stop_times.len(),
+        start,
+        end,
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{env, error::Error, process::ExitCode};
+use gtfs::{feed::Feed, import::{import, ImportOptions}};
+
+const USAGE: &str = "usage: transfar-gtfs-import <feed.zip|feed directory> --prefix <PREFIX> [--out <data root>]";
+const DEFAULT_OUT: &str = "data";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    feed_path: Option<String>,
+    prefix: Option<String>,
+    out: Option<String>,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut res = Args::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let (key, inline_value) = match arg.split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let target = match key.as_str() {
+            "--prefix" => &mut res.prefix,
+            "--out" => &mut res.out,
+            _ if !arg.starts_with("--") && res.feed_path.is_none() => {
+                res.feed_path = Some(arg);
+                continue;
+            }
+            _ => return Err(format!("unknown argument: {}", arg)),
+        };
+        let value = inline_value
+            .or_else(|| args.next())
+            .ok_or(format!("{} requires a value", key))?;
+        *target = Some(value);
+    }
+    Ok(res)
+}
+
+fn run() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    let args = parse_args(env::args().skip(1))?;
+    let (Some(feed_path), Some(prefix)) = (args.feed_path, args.prefix) else {
+        return Err(USAGE.into());
+    };
+    let out = args.out.unwrap_or(DEFAULT_OUT.to_string());
+
+    let feed = Feed::open(&feed_path)?;
+    let imported = import(&feed, &ImportOptions { prefix })?;
+    for warning in imported.warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    let written = imported.dataset.write(&out)?;
+    println!(
+        "wrote {} files to {} ({} stations, {} rides, {} timetables, {} calendars, {} departure patterns)",
+        written.len(),
+        out,
+        imported.dataset.stations.len(),
+        imported.dataset.rides.len(),
+        imported.dataset.timetables.len(),
+        imported.dataset.calendars.len(),
+        imported.dataset.patterns.len(),
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use calc::{dataset::Dataset, raptor::network::{Network, ScheduledTrip}};
+use chrono::NaiveDate;
+use models::{id::{RideId, ID}, ride::{CareerType, Ride}, service_time::ServiceTime, station::Station};
+
+use crate::feed::{Agency, CalendarDate, Feed, Route, Stop, StopTime, Translation, Trip, READING_LANGUAGE};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// この期間に運行する便を書き出す
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub agency_url: String,
+    pub agency_timezone: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Exported {
+    pub feed: Feed,
+    /// GTFSで表せずに省いたデータ
+    pub warnings: Vec<String>,
+}
+
+/// 書き出す便の停車情報。運行日が違っても中身が同じなら1つの便にまとめる
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct TripKey {
+    ride_id: String,
+    trip_id: String,
+    /// (停車順, 駅, 時刻, 乗車できるか, 降車できるか)
+    stops: Vec<(usize, String, Option<ServiceTime>, bool, bool)>,
+}
+
+/// データセットをGTFSのフィードに変換する
+///
+/// - 駅をstopにする。joinでつながっている駅は同じ親駅 (location_type 1) にまとめる
+/// - Rideをrouteにする。agencyはIDの先頭 (HAKODATEBUSなど)
+/// - 期間内の各日の運行から便を作り、運行日はcalendar_dates.txtで表す
+pub fn export(dataset: &Dataset, options: &ExportOptions) -> Result<Exported, Box<dyn Error + Send + Sync + 'static>> {
+    if options.end < options.start {
+        return Err("end must not be before start.".into());
+    }
+    let mut res = Exported::default();
+
+    let mut stations: Vec<&Station> = dataset.stations().collect();
+    stations.sort_by_key(|x| x.station_id.get_raw_id());
+    export_stops(&stations, &mut res);
+
+    let mut rides: Vec<(&String, &Ride)> = dataset.rides().collect();
+    rides.sort_by_key(|(raw_id, _)| *raw_id);
+    let mut agencies: BTreeSet<String> = BTreeSet::new();
+    for (raw_id, ride) in rides.iter() {
+        let agency_id = agency_of(raw_id);
+        agencies.insert(agency_id.clone());
+        res.feed.routes.push(Route {
+            route_id: raw_id.to_string(),
+            agency_id: Some(agency_id),
+            route_short_name: Some(ride.ride_type.clone()),
+            route_long_name: Some(ride.aka_type.clone()).filter(|x| !x.is_empty()),
+            route_type: route_type(ride.career_type),
+            route_color: Some(ride.type_background.trim_start_matches('#').to_string()),
+            route_text_color: Some(ride.type_foreground.trim_start_matches('#').to_string()),
+        });
+        if !ride.type_pronounce.is_empty() {
+            res.feed.translations.push(reading("routes", "route_short_name", raw_id, &ride.type_pronounce));
+        }
+    }
+    res.feed.agencies = agencies
+        .into_iter()
+        .map(|x| Agency {
+            agency_id: Some(x.clone()),
+            agency_name: x,
+            agency_url: options.agency_url.clone(),
+            agency_timezone: options.agency_timezone.clone(),
+            agency_lang: Some("ja".to_string()),
+        })
+        .collect();
+
+    // 日ごとの運行を集めて、中身が同じ便の運行日をまとめる
+    let mut dates_by_trip: BTreeMap<TripKey, Vec<NaiveDate>> = BTreeMap::new();
+    let mut skipped: HashSet<String> = HashSet::new();
+    for date in options.start.iter_days().take_while(|x| *x <= options.end) {
+        let network = Network::from_dataset(dataset, date);
+        for trip in network.scheduled_trips() {
+            match trip_key(&trip) {
+                Some(key) => dates_by_trip.entry(key).or_default().push(date),
+                None => {
+                    skipped.insert(trip.trip_id);
+                }
+            }
+        }
+    }
+    let mut skipped: Vec<String> = skipped.into_iter().collect();
+    skipped.sort();
+    for trip_id in skipped {
+        res.warnings.push(format!("trip {}: less than 2 stops with time", trip_id));
+    }
+
+    let mut services: BTreeMap<Vec<NaiveDate>, String> = BTreeMap::new();
+    let mut trip_id_counts: HashMap<String, usize> = HashMap::new();
+    for (key, dates) in dates_by_trip {
+        let next_service_id = format!("S{:04}", services.len() + 1);
+        let service_id = services.entry(dates).or_insert(next_service_id).clone();
+        // 同じtrip_idで中身が違う便 (同じ日の2便目や、ダイヤ改正の前後) には番号をつける
+        let count = trip_id_counts.entry(key.trip_id.clone()).or_default();
+        *count += 1;
+        let trip_id = if *count == 1 { key.trip_id.clone() } else { format!("{}-{}", key.trip_id, count) };
+
+        let headsign = dataset
+            .ride(&RideId::new(key.ride_id.clone(), dataset.data_root_path()))
+            .and_then(|x| dataset.station(&x.to))
+            .map(|x| x.name.clone());
+        res.feed.trips.push(Trip {
+            route_id: key.ride_id.clone(),
+            service_id,
+            trip_id: trip_id.clone(),
+            trip_headsign: headsign,
+            direction_id: None,
+        });
+        for (position, station_id, time, can_board, can_alight) in key.stops {
+            // GTFSと同じく、日付をまたぐ時刻は24時以降のまま書く
+            let time = time.map(|x| format!("{}:00", x));
+            res.feed.stop_times.push(StopTime {
+                trip_id: trip_id.clone(),
+                arrival_time: time.clone(),
+                departure_time: time,
+                stop_id: station_id,
+                stop_sequence: position as u32 + 1,
+                pickup_type: Some(if can_board { 0 } else { 1 }),
+                drop_off_type: Some(if can_alight { 0 } else { 1 }),
+            });
+        }
+    }
+    for (dates, service_id) in services {
+        for date in dates {
+            res.feed.calendar_dates.push(CalendarDate {
+                service_id: service_id.clone(),
+                date: date.format("%Y%m%d").to_string(),
+                exception_type: 1,
+            });
+        }
+    }
+    res.feed.calendar_dates.sort_by(|a, b| (&a.service_id, &a.date).cmp(&(&b.service_id, &b.date)));
+
+    Ok(res)
+}
+
+/// career_typeをGTFSのroute_typeにする (import::career_typeの逆)
+pub fn route_type(career_type: CareerType) -> u16 {
+    match career_type {
+        CareerType::Tram => 0,
+        CareerType::Subway => 1,
+        CareerType::Train | CareerType::LtdExpTrain => 2,
+        CareerType::Ferry => 4,
+        CareerType::Airplane => 1100,
+        CareerType::Bus => 3,
+    }
+}
+
+fn agency_of(raw_id: &str) -> String {
+    raw_id.split('_').next().unwrap_or(raw_id).to_string()
+}
+
+fn reading(table_name: &str, field_name: &str, record_id: &str, translation: &str) -> Translation {
+    Translation {
+        table_name: Some(table_name.to_string()),
+        field_name: Some(field_name.to_string()),
+        language: Some(READING_LANGUAGE.to_string()),
+        record_id: Some(record_id.to_string()),
+        field_value: None,
+        trans_id: None,
+        lang: None,
+        translation: translation.to_string(),
+    }
+}
+
+/// joinでつながっている駅を連結成分ごとにまとめ、2駅以上あれば親駅を作る
+/// 緯度経度は駅のものを書き出し、緯度経度のない駅だけ空にする。親駅はIDが一番小さい駅の緯度経度にする
+fn export_stops(stations: &[&Station], res: &mut Exported) {
+    let index: HashMap<String, usize> = stations.iter().enumerate().map(|(i, x)| (x.station_id.get_raw_id(), i)).collect();
+    let mut adjacency: Vec<Vec<usize>> = vec![vec![]; stations.len()];
+    for (from, station) in stations.iter().enumerate() {
+        for joined in station.join.iter() {
+            if let Some(to) = index.get(&joined.get_raw_id()) {
+                adjacency[from].push(*to);
+                adjacency[*to].push(from);
+            }
+        }
+    }
+    let mut parent_of: Vec<Option<String>> = vec![None; stations.len()];
+    for start in 0..stations.len() {
+        if adjacency[start].is_empty() || parent_of[start].is_some() {
+            continue;
+        }
+        let mut group: Vec<usize> = vec![start];
+        let mut visited: HashSet<usize> = HashSet::from([start]);
+        let mut cursor = 0;
+        while cursor < group.len() {
+            for next in adjacency[group[cursor]].iter() {
+                if visited.insert(*next) {
+                    group.push(*next);
+                }
+            }
+            cursor += 1;
+        }
+        // 駅はIDの順に並んでいるので、startがグループで一番小さいID
+        let parent_id = format!("{}_parent", stations[start].station_id.get_raw_id());
+        res.feed.stops.push(Stop {
+            stop_id: parent_id.clone(),
+            stop_name: stations[start].name.clone(),
+            stop_lat: stations[start].lat,
+            stop_lon: stations[start].lon,
+            location_type: Some(1),
+            parent_station: None,
+        });
+        for member in group {
+            parent_of[member] = Some(parent_id.clone());
+        }
+    }
+
+    for (station, parent) in stations.iter().zip(parent_of) {
+        let raw_id = station.station_id.get_raw_id();
+        res.feed.stops.push(Stop {
+            stop_id: raw_id.clone(),
+            stop_name: station.name.clone(),
+            stop_lat: station.lat,
+            stop_lon: station.lon,
+            location_type: Some(0),
+            parent_station: parent,
+        });
+        if !station.pronounce.is_empty() {
+            res.feed.translations.push(reading("stops", "stop_name", &raw_id, &station.pronounce));
+        }
+    }
+    let missing = stations.iter().filter(|x| x.coordinate().is_none()).count();
+    if missing > 0 {
+        res.warnings.push(format!("{} stops have no coordinates; their stop_lat and stop_lon are left empty.", missing));
+    }
+}
+
+/// 時刻がわかる最初の駅から最後の駅までを便にする。時刻がわかる駅が2つ未満ならNone
+fn trip_key(trip: &ScheduledTrip) -> Option<TripKey> {
+    let timed: Vec<usize> = trip.stops.iter().enumerate().filter(|(_, x)| x.time.is_some()).map(|(i, _)| i).collect();
+    let (Some(first), Some(last)) = (timed.first().cloned(), timed.last().cloned()) else {
+        return None;
+    };
+    if first == last {
+        return None;
+    }
+    let stops = (first..=last)
+        .map(|position| {
+            let stop = &trip.stops[position];
+            (
+                position,
+                stop.station_id.get_raw_id(),
+                stop.time,
+                stop.can_board && position != last,
+                stop.can_alight && position != first,
+            )
+        })
+        .collect();
+    Some(TripKey {
+        ride_id: trip.ride_id.get_raw_id(),
+        trip_id: trip.trip_id.clone(),
+        stops,
+    })
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{export, route_type, ExportOptions};
+    use models::ride::CareerType;
+    use crate::feed::Feed;
+    use calc::dataset::Dataset;
+    use chrono::NaiveDate;
+    use std::fs;
+
+    fn options() -> ExportOptions {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 25).unwrap();
+        ExportOptions {
+            start: date,
+            end: date,
+            agency_url: "https://example.com/".to_string(),
+            agency_timezone: "Asia/Tokyo".to_string(),
+        }
+    }
+
+    #[test]
+    fn career_typeをroute_typeにする() {
+        assert_eq!(3, route_type(CareerType::Bus));
+        assert_eq!(2, route_type(CareerType::LtdExpTrain));
+        assert_eq!(1100, route_type(CareerType::Airplane));
+    }
+
+    #[test]
+    fn リポジトリのデータを書き出せる() {
+        let dataset = Dataset::load("../data").unwrap();
+        let exported = export(&dataset, &options()).unwrap();
+        let feed = exported.feed;
+
+        let route = feed.routes.iter().find(|x| x.route_id == "HAKODATEBUS_55G-D").unwrap();
+        assert_eq!(Some("HAKODATEBUS".to_string()), route.agency_id);
+        assert_eq!(Some("FFD700".to_string()), route.route_text_color);
+        assert_eq!(Some("000000".to_string()), route.route_color);
+        assert!(feed.agencies.iter().any(|x| x.agency_id == Some("JRH".to_string())));
+
+        // 始発は乗車のみ、終点は降車のみ
+        let stop_times: Vec<_> = feed.stop_times.iter().filter(|x| x.trip_id == "HAKODATEBUS_55G-D-1102").collect();
+        assert!(stop_times.len() >= 2);
+        assert_eq!((Some(0), Some(1)), (stop_times[0].pickup_type, stop_times[0].drop_off_type));
+        let last = stop_times.last().unwrap();
+        assert_eq!((Some(1), Some(0)), (last.pickup_type, last.drop_off_type));
+        assert!(stop_times.windows(2).all(|x| x[0].stop_sequence < x[1].stop_sequence));
+
+        // joinでつながった駅は同じ親駅を持つ
+        let station = feed.stops.iter().find(|x| x.stop_id == "AP_RJCH").unwrap();
+        assert_eq!(Some("AP_RJCH_parent".to_string()), station.parent_station);
+        assert!(feed.stops.iter().any(|x| x.stop_id == "AP_RJCH_parent" && x.location_type == Some(1)));
+
+        // 到着時刻がわからない便はGTFSで表せないので省く
+        assert!(exported.warnings.iter().any(|x| x.contains("JRH_HOKUTO_17")));
+        assert!(feed.trips.iter().all(|x| x.trip_id != "JRH_HOKUTO_17"));
+    }
+
+    #[test]
+    fn 書き出したフィードを読み込める() {
+        let dataset = Dataset::load("../data").unwrap();
+        let exported = export(&dataset, &options()).unwrap();
+        let path = std::env::temp_dir().join(format!("transfar-gtfs-export-{}.zip", std::process::id()));
+        exported.feed.write(path.to_str().unwrap()).unwrap();
+
+        let feed = Feed::open(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        let feed = feed.unwrap();
+
+        assert_eq!(exported.feed.stops.len(), feed.stops.len());
+        assert_eq!(exported.feed.trips, feed.trips);
+        assert_eq!(exported.feed.stop_times.len(), feed.stop_times.len());
+        assert_eq!(
+            Some("はこだてくうこう".to_string()),
+            feed.reading("stops", "stop_name", "AP_RJCH", "函館空港")
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::{collections::HashMap, error::Error, fs::{self, File}, io::{Read, Write}, path::Path};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Agency {
+    #[serde(default)]
+    pub agency_id: Option<String>,
+    pub agency_name: String,
+    pub agency_url: String,
+    pub agency_timezone: String,
+    #[serde(default)]
+    pub agency_lang: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Stop {
+    pub stop_id: String,
+    pub stop_name: String,
+    #[serde(default)]
+    pub stop_lat: Option<f64>,
+    #[serde(default)]
+    pub stop_lon: Option<f64>,
+    /// 0か空なら停留所・駅 (のりば)、1なら親駅
+    #[serde(default)]
+    pub location_type: Option<u8>,
+    #[serde(default)]
+    pub parent_station: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Route {
+    pub route_id: String,
+    #[serde(default)]
+    pub agency_id: Option<String>,
+    #[serde(default)]
+    pub route_short_name: Option<String>,
+    #[serde(default)]
+    pub route_long_name: Option<String>,
+    pub route_type: u16,
+    #[serde(default)]
+    pub route_color: Option<String>,
+    #[serde(default)]
+    pub route_text_color: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Trip {
+    pub route_id: String,
+    pub service_id: String,
+    pub trip_id: String,
+    #[serde(default)]
+    pub trip_headsign: Option<String>,
+    #[serde(default)]
+    pub direction_id: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct StopTime {
+    pub trip_id: String,
+    /// HH:MM:SS。24時以降は24以上の時で表す
+    #[serde(default)]
+    pub arrival_time: Option<String>,
+    #[serde(default)]
+    pub departure_time: Option<String>,
+    pub stop_id: String,
+    pub stop_sequence: u32,
+    /// 1なら乗車できない
+    #[serde(default)]
+    pub pickup_type: Option<u8>,
+    /// 1なら降車できない
+    #[serde(default)]
+    pub drop_off_type: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ServiceCalendar {
+    pub service_id: String,
+    pub monday: u8,
+    pub tuesday: u8,
+    pub wednesday: u8,
+    pub thursday: u8,
+    pub friday: u8,
+    pub saturday: u8,
+    pub sunday: u8,
+    /// YYYYMMDD
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CalendarDate {
+    pub service_id: String,
+    pub date: String,
+    /// 1なら運行日に追加、2なら運行日から除外
+    pub exception_type: u8,
+}
+
+/// translations.txt
+/// GTFS-JPの旧形式 (trans_id, lang) と、GTFSの形式 (table_name, field_name, language) のどちらも読む
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Translation {
+    #[serde(default)]
+    pub table_name: Option<String>,
+    #[serde(default)]
+    pub field_name: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub record_id: Option<String>,
+    #[serde(default)]
+    pub field_value: Option<String>,
+    #[serde(default)]
+    pub trans_id: Option<String>,
+    #[serde(default)]
+    pub lang: Option<String>,
+    pub translation: String,
+}
+
+/// 読みがなの言語コード
+pub const READING_LANGUAGE: &str = "ja-Hrkt";
+
+/// translations.txtを書き出すときの形式 (GTFSの形式)
+#[derive(Serialize, Debug)]
+struct TranslationRecord<'a> {
+    table_name: &'a str,
+    field_name: &'a str,
+    language: &'a str,
+    translation: &'a str,
+    record_id: &'a str,
+}
+
+/// GTFSのフィードのうち、変換に使うファイルを読み込んだもの
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Feed {
+    pub agencies: Vec<Agency>,
+    pub stops: Vec<Stop>,
+    pub routes: Vec<Route>,
+    pub trips: Vec<Trip>,
+    pub stop_times: Vec<StopTime>,
+    pub calendar: Vec<ServiceCalendar>,
+    pub calendar_dates: Vec<CalendarDate>,
+    pub translations: Vec<Translation>,
+}
+
+impl Feed {
+    /// zipファイルか、展開済みのディレクトリから読み込む
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let path = Path::new(path);
+        if path.is_dir() {
+            return Self::parse(|name| {
+                let file_path = path.join(name);
+                if file_path.is_file() {
+                    Ok(Some(fs::read_to_string(file_path)?))
+                } else {
+                    Ok(None)
+                }
+            });
+        }
+
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let mut tables: HashMap<String, String> = HashMap::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+            // フォルダごと圧縮されていることがあるので、ファイル名だけで判定する
+            let Some(name) = file.name().rsplit('/').next().map(|x| x.to_string()) else {
+                continue;
+            };
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            tables.insert(name, content);
+        }
+        Self::parse(|name| Ok(tables.get(name).cloned()))
+    }
+
+    /// ファイル名を受け取って中身を返す関数から読み込む。ファイルがなければNoneを返す
+    pub fn parse(
+        read: impl Fn(&str) -> Result<Option<String>, Box<dyn Error + Send + Sync + 'static>>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let required = |name: &str| -> Result<String, Box<dyn Error + Send + Sync + 'static>> {
+            read(name)?.ok_or(format!("{} not found in feed.", name).into())
+        };
+        let optional = |name: &str| read(name).map(|x| x.unwrap_or_default());
+
+        let feed = Self {
+            agencies: parse_table("agency.txt", &optional("agency.txt")?)?,
+            stops: parse_table("stops.txt", &required("stops.txt")?)?,
+            routes: parse_table("routes.txt", &required("routes.txt")?)?,
+            trips: parse_table("trips.txt", &required("trips.txt")?)?,
+            stop_times: parse_table("stop_times.txt", &required("stop_times.txt")?)?,
+            calendar: parse_table("calendar.txt", &optional("calendar.txt")?)?,
+            calendar_dates: parse_table("calendar_dates.txt", &optional("calendar_dates.txt")?)?,
+            translations: parse_table("translations.txt", &optional("translations.txt")?)?,
+        };
+        if feed.calendar.is_empty() && feed.calendar_dates.is_empty() {
+            return Err("either calendar.txt or calendar_dates.txt is required.".into());
+        }
+        Ok(feed)
+    }
+
+    /// 末尾が`.zip`ならzipファイルに、それ以外ならディレクトリに書き出す
+    /// 空のファイル (calendar.txtなど) は書き出さない
+    pub fn write(&self, path: &str) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        let translations = self
+            .translations
+            .iter()
+            .filter_map(|x| {
+                Some(TranslationRecord {
+                    table_name: x.table_name.as_deref()?,
+                    field_name: x.field_name.as_deref()?,
+                    language: x.language.as_deref()?,
+                    translation: &x.translation,
+                    record_id: x.record_id.as_deref()?,
+                })
+            })
+            .collect::<Vec<_>>();
+        let tables = [
+            ("agency.txt", to_csv(&self.agencies)?),
+            ("stops.txt", to_csv(&self.stops)?),
+            ("routes.txt", to_csv(&self.routes)?),
+            ("trips.txt", to_csv(&self.trips)?),
+            ("stop_times.txt", to_csv(&self.stop_times)?),
+            ("calendar.txt", to_csv(&self.calendar)?),
+            ("calendar_dates.txt", to_csv(&self.calendar_dates)?),
+            ("translations.txt", to_csv(&translations)?),
+        ];
+        let tables = tables.into_iter().filter_map(|(name, content)| Some((name, content?)));
+
+        if path.ends_with(".zip") {
+            let mut writer = zip::ZipWriter::new(File::create(path)?);
+            let options = zip::write::SimpleFileOptions::default();
+            for (name, content) in tables {
+                writer.start_file(name, options)?;
+                writer.write_all(content.as_bytes())?;
+            }
+            writer.finish()?;
+        } else {
+            fs::create_dir_all(path)?;
+            for (name, content) in tables {
+                fs::write(Path::new(path).join(name), content)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 読みがな (ja-Hrkt) の翻訳を探す
+    pub fn reading(&self, table_name: &str, field_name: &str, record_id: &str, field_value: &str) -> Option<String> {
+        self.translations
+            .iter()
+            .find(|x| match (&x.table_name, &x.trans_id) {
+                (Some(table), _) => {
+                    table == table_name
+                        && x.field_name.as_deref() == Some(field_name)
+                        && x.language.as_deref() == Some(READING_LANGUAGE)
+                        && (x.record_id.as_deref() == Some(record_id) || x.field_value.as_deref() == Some(field_value))
+                }
+                (None, Some(trans_id)) => trans_id == field_value && x.lang.as_deref() == Some(READING_LANGUAGE),
+                (None, None) => false,
+            })
+            .map(|x| x.translation.clone())
+    }
+}
+
+fn parse_table<T: DeserializeOwned>(
+    name: &str,
+    content: &str,
+) -> Result<Vec<T>, Box<dyn Error + Send + Sync + 'static>> {
+    // GTFS-JPのファイルはBOMつきのことが多い
+    let content = content.trim_start_matches('\u{feff}');
+    if content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes())
+        .deserialize()
+        .enumerate()
+        .map(|(index, x)| x.map_err(|e| format!("{} line {}: {}", name, index + 2, e).into()))
+        .collect()
+}
+
+/// 行がなければNoneを返す
+fn to_csv<T: Serialize>(rows: &[T]) -> Result<Option<String>, Box<dyn Error + Send + Sync + 'static>> {
+    if rows.is_empty() {
+        return Ok(None);
+    }
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows.iter() {
+        writer.serialize(row)?;
+    }
+    let content = writer.into_inner().map_err(|e| e.to_string())?;
+    Ok(Some(String::from_utf8(content)?))
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::Feed;
+    use std::collections::HashMap;
+
+    fn tables(raw: &[(&str, &str)]) -> HashMap<String, String> {
+        raw.iter().map(|(name, content)| (name.to_string(), content.to_string())).collect()
+    }
+
+    #[test]
+    fn BOMつきで省略可能な列がなくても読み込める() {
+        let tables = tables(&[
+            ("stops.txt", "\u{feff}stop_id,stop_name\n01,赤川\n"),
+            ("routes.txt", "route_id,route_short_name,route_type\n55G,55G,3\n"),
+            ("trips.txt", "route_id,service_id,trip_id\n55G,weekday,55G-0756\n"),
+            ("stop_times.txt", "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n55G-0756,07:56:00,07:56:00,01,1\n"),
+            ("calendar_dates.txt", "service_id,date,exception_type\nweekday,20250425,1\n"),
+            ("translations.txt", "trans_id,lang,translation\n赤川,ja-Hrkt,あかがわ\n"),
+        ]);
+        let feed = Feed::parse(|name| Ok(tables.get(name).cloned())).unwrap();
+
+        assert_eq!("01", feed.stops[0].stop_id);
+        assert_eq!(None, feed.stops[0].location_type);
+        assert_eq!(None, feed.stop_times[0].pickup_type);
+        assert!(feed.calendar.is_empty());
+        assert_eq!(Some("あかがわ".to_string()), feed.reading("stops", "stop_name", "01", "赤川"));
+    }
+
+    #[test]
+    fn 必須のファイルがなければエラーになる() {
+        let tables = tables(&[("stops.txt", "stop_id,stop_name\n01,赤川\n")]);
+        assert!(Feed::parse(|name| Ok(tables.get(name).cloned())).is_err());
+    }
+}
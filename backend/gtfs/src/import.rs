@@ -0,0 +1,624 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use models::{
+    calendar::{RawCalendar, RawCalendarException},
+    departure::{DepartureType, RawDeparture, RawDeparturePattern},
+    ride::{CareerType, RawRide},
+    station::RawStation,
+    timetable::{RawTimetable, RawVersion},
+};
+
+use crate::feed::{Feed, Route, StopTime, Trip};
+use crate::raw_dataset::RawDataset;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportOptions {
+    /// IDの先頭につける事業者名 (HAKODATEBUSなど)
+    pub prefix: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub dataset: RawDataset,
+    /// 変換できずに読み飛ばしたデータ
+    pub warnings: Vec<String>,
+}
+
+/// 停車順 (GTFSのstop_id) と、その停車順で走る便のindex
+type StopPattern = (Vec<String>, Vec<usize>);
+
+/// 便がある停留所に停車する情報。stop_timesの1行に相当する
+#[derive(Debug, Clone)]
+struct TripStop {
+    stop_id: String,
+    /// HH:MM
+    time: Option<String>,
+    /// 同じ停留所に何回目に停車したか (1始まり)
+    loop_count: i32,
+    departure_type: Option<DepartureType>,
+}
+
+/// GTFSのフィードをtransfar-naviのデータに変換する
+///
+/// - 停留所 (location_type 0) を駅にする。親駅が同じ停留所どうしはjoinでつなぐ
+/// - route_idと停車順が同じ便を1つのRideにまとめる。同じ停留所に2回以上停車する場合は`~n`をつける
+/// - 駅ごとに、運行するserviceの組み合わせが同じ日を1つの発車パターンにまとめ、
+///   calendar.txtの期間ごとにカレンダーを作る
+pub fn import(feed: &Feed, options: &ImportOptions) -> Result<Imported, Box<dyn Error + Send + Sync + 'static>> {
+    if options.prefix.is_empty() || options.prefix.contains(['_', '~', '/']) {
+        return Err(format!("prefix `{}` must be non-empty and must not contain `_`, `~` or `/`.", options.prefix).into());
+    }
+    let mut res = Imported::default();
+    let id = |gtfs_id: &str| to_id(&options.prefix, gtfs_id);
+
+    res.dataset.stations = import_stations(feed, &id);
+    let station_ids: HashSet<String> = feed.stops.iter().filter(|x| is_stop(x.location_type)).map(|x| x.stop_id.clone()).collect();
+
+    let routes: HashMap<&str, &Route> = feed.routes.iter().map(|x| (x.route_id.as_str(), x)).collect();
+    let mut stop_times: HashMap<&str, Vec<&StopTime>> = HashMap::new();
+    for stop_time in feed.stop_times.iter() {
+        stop_times.entry(stop_time.trip_id.as_str()).or_default().push(stop_time);
+    }
+
+    // 便ごとの停車情報
+    let mut trips: Vec<(&Trip, Vec<TripStop>)> = Vec::new();
+    for trip in feed.trips.iter() {
+        if !routes.contains_key(trip.route_id.as_str()) {
+            res.warnings.push(format!("trip {}: route {} not found", trip.trip_id, trip.route_id));
+            continue;
+        }
+        let Some(trip_stop_times) = stop_times.get_mut(trip.trip_id.as_str()) else {
+            res.warnings.push(format!("trip {}: no stop_times", trip.trip_id));
+            continue;
+        };
+        trip_stop_times.sort_by_key(|x| x.stop_sequence);
+        if let Some(unknown) = trip_stop_times.iter().find(|x| !station_ids.contains(&x.stop_id)) {
+            res.warnings.push(format!("trip {}: stop {} not found", trip.trip_id, unknown.stop_id));
+            continue;
+        }
+        let stops = build_trip_stops(trip, trip_stop_times, &mut res.warnings);
+        if stops.len() < 2 {
+            res.warnings.push(format!("trip {}: less than 2 stops", trip.trip_id));
+            continue;
+        }
+        trips.push((trip, stops));
+    }
+
+    // route_idと停車順が同じ便をまとめてRideにする
+    let mut patterns: HashMap<(&str, Vec<String>), Vec<usize>> = HashMap::new();
+    for (index, (trip, stops)) in trips.iter().enumerate() {
+        let key = (trip.route_id.as_str(), stops.iter().map(|x| x.stop_id.clone()).collect());
+        patterns.entry(key).or_default().push(index);
+    }
+    let mut patterns_by_route: HashMap<&str, Vec<StopPattern>> = HashMap::new();
+    for ((route_id, stops), trip_indices) in patterns {
+        patterns_by_route.entry(route_id).or_default().push((stops, trip_indices));
+    }
+    let mut route_ids: Vec<&str> = patterns_by_route.keys().cloned().collect();
+    route_ids.sort();
+    let mut ride_of_trip: Vec<String> = vec![String::new(); trips.len()];
+    for route_id in route_ids {
+        let route = routes[route_id];
+        let mut route_patterns = patterns_by_route.remove(route_id).unwrap_or_default();
+        // 方向、始発の時刻、停車順の順に並べて番号をつける
+        route_patterns.sort_by_key(|(stops, trip_indices)| {
+            let first_time = trip_indices.iter().filter_map(|x| trips[*x].1[0].time.clone()).min();
+            (trips[trip_indices[0]].0.direction_id, first_time, stops.clone())
+        });
+        let single = route_patterns.len() == 1;
+        for (index, (stops, trip_indices)) in route_patterns.into_iter().enumerate() {
+            let ride_id = if single { id(route_id) } else { format!("{}-{}", id(route_id), index + 1) };
+            res.dataset.rides.push(build_ride(feed, route, &ride_id, &stops, &id));
+            for trip_index in trip_indices {
+                ride_of_trip[trip_index] = ride_id.clone();
+            }
+        }
+    }
+
+    // 駅ごとに発車パターンとカレンダーを作る
+    let service_dates = service_dates(feed, &mut res.warnings)?;
+    let mut departures_by_stop: HashMap<&str, Vec<(&str, RawDeparture)>> = HashMap::new();
+    for (index, (trip, stops)) in trips.iter().enumerate() {
+        for stop in stops.iter() {
+            let Some(time) = &stop.time else {
+                continue;
+            };
+            departures_by_stop.entry(stop.stop_id.as_str()).or_default().push((
+                trip.service_id.as_str(),
+                RawDeparture {
+                    ride_id: ride_of_trip[index].clone(),
+                    trip_id: id(&trip.trip_id),
+                    time: time.clone(),
+                    loop_count: Some(stop.loop_count).filter(|x| *x > 1),
+                    departure_type: stop.departure_type,
+                },
+            ));
+        }
+    }
+    let mut stop_ids: Vec<&str> = departures_by_stop.keys().cloned().collect();
+    stop_ids.sort();
+    for stop_id in stop_ids {
+        let mut departures = departures_by_stop.remove(stop_id).unwrap_or_default();
+        departures.sort_by(|a, b| (&a.1.time, &a.1.trip_id).cmp(&(&b.1.time, &b.1.trip_id)));
+        import_station_calendars(&id(stop_id), &departures, &service_dates, &mut res.dataset);
+    }
+
+    Ok(res)
+}
+
+/// 事業者名をつけてtransfar-naviのIDにする
+/// `~`はループの回数、`/`はパスの区切りとして扱われるので置き換える
+fn to_id(prefix: &str, gtfs_id: &str) -> String {
+    let sanitized: String = gtfs_id
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() || x == '-' || x == '_' { x } else { '-' })
+        .collect();
+    format!("{}_{}", prefix, sanitized)
+}
+
+fn is_stop(location_type: Option<u8>) -> bool {
+    matches!(location_type, None | Some(0))
+}
+
+fn import_stations(feed: &Feed, id: &impl Fn(&str) -> String) -> Vec<RawStation> {
+    let stops: Vec<_> = feed.stops.iter().filter(|x| is_stop(x.location_type)).collect();
+    let mut siblings: HashMap<&str, Vec<&str>> = HashMap::new();
+    for stop in stops.iter() {
+        if let Some(parent) = stop.parent_station.as_deref().filter(|x| !x.is_empty()) {
+            siblings.entry(parent).or_default().push(stop.stop_id.as_str());
+        }
+    }
+
+    stops
+        .iter()
+        .map(|stop| {
+            let join = stop
+                .parent_station
+                .as_deref()
+                .and_then(|x| siblings.get(x))
+                .map(|x| x.iter().filter(|y| **y != stop.stop_id).map(|y| id(y)).collect())
+                .unwrap_or_default();
+            RawStation {
+                station_id: id(&stop.stop_id),
+                name: stop.stop_name.clone(),
+                pronounce: feed.reading("stops", "stop_name", &stop.stop_id, &stop.stop_name).unwrap_or_default(),
+                join,
+                lat: stop.stop_lat,
+                lon: stop.stop_lon,
+            }
+        })
+        .collect()
+}
+
+fn build_trip_stops(trip: &Trip, stop_times: &[&StopTime], warnings: &mut Vec<String>) -> Vec<TripStop> {
+    let mut counts: HashMap<&str, i32> = HashMap::new();
+    let last = stop_times.len().saturating_sub(1);
+    let mut res: Vec<TripStop> = Vec::new();
+    for (position, stop_time) in stop_times.iter().enumerate() {
+        let can_board = position != last && stop_time.pickup_type != Some(1);
+        let can_alight = position != 0 && stop_time.drop_off_type != Some(1);
+        let departure_type = match (can_board, can_alight) {
+            (true, true) => None,
+            (true, false) => Some(DepartureType::Outgoing),
+            (false, true) => Some(DepartureType::Incoming),
+            // 乗り降りできない停車 (通過扱い) は経路にも含めない
+            (false, false) => continue,
+        };
+        let loop_count = counts.entry(stop_time.stop_id.as_str()).or_default();
+        *loop_count += 1;
+
+        // 終点は到着時刻、それ以外は発車時刻を使う
+        let raw_time = if position == last {
+            stop_time.arrival_time.as_ref().or(stop_time.departure_time.as_ref())
+        } else {
+            stop_time.departure_time.as_ref().or(stop_time.arrival_time.as_ref())
+        };
+        let time = match raw_time.map(|x| x.as_str()).filter(|x| !x.is_empty()).map(to_hhmm) {
+            Some(Ok(time)) => Some(time),
+            Some(Err(e)) => {
+                warnings.push(format!("trip {} stop {}: {}", trip.trip_id, stop_time.stop_id, e));
+                None
+            }
+            None => None,
+        };
+        res.push(TripStop {
+            stop_id: stop_time.stop_id.clone(),
+            time,
+            loop_count: *loop_count,
+            departure_type,
+        });
+    }
+    res
+}
+
+/// GTFSのHH:MM:SSをHH:MMにする。日付をまたぐ時刻は24時以降のまま残す
+fn to_hhmm(raw: &str) -> Result<String, String> {
+    let mut parts = raw.split(':');
+    let (Some(hour), Some(minute)) = (parts.next(), parts.next()) else {
+        return Err(format!("invalid time `{}`", raw));
+    };
+    let (Ok(hour), Ok(minute)) = (hour.parse::<u32>(), minute.parse::<u32>()) else {
+        return Err(format!("invalid time `{}`", raw));
+    };
+    if minute >= 60 {
+        return Err(format!("invalid time `{}`", raw));
+    }
+    if hour >= 48 {
+        return Err(format!("time `{}` is more than a day after the service day", raw));
+    }
+    Ok(format!("{:02}:{:02}", hour, minute))
+}
+
+fn build_ride(feed: &Feed, route: &Route, ride_id: &str, stops: &[String], id: &impl Fn(&str) -> String) -> RawRide {
+    let non_empty = |x: &Option<String>| x.clone().filter(|x| !x.is_empty());
+    let ride_type = non_empty(&route.route_short_name)
+        .or(non_empty(&route.route_long_name))
+        .unwrap_or(route.route_id.clone());
+
+    // 2回以上停車する停留所は、何回目の停車かを`~n`で表す
+    let mut totals: HashMap<&str, i32> = HashMap::new();
+    for stop in stops.iter() {
+        *totals.entry(stop.as_str()).or_default() += 1;
+    }
+    let mut counts: HashMap<&str, i32> = HashMap::new();
+    let route_stops = stops
+        .iter()
+        .map(|stop| {
+            let count = counts.entry(stop.as_str()).or_default();
+            *count += 1;
+            if totals[stop.as_str()] > 1 { format!("{}~{}", id(stop), count) } else { id(stop) }
+        })
+        .collect();
+
+    RawRide {
+        ride_id: ride_id.to_string(),
+        ride_type: ride_type.clone(),
+        aka_type: "".to_string(),
+        type_foreground: format!("#{}", non_empty(&route.route_text_color).unwrap_or("000000".to_string())),
+        type_background: format!("#{}", non_empty(&route.route_color).unwrap_or("FFFFFF".to_string())),
+        type_pronounce: feed.reading("routes", "route_short_name", &route.route_id, &ride_type).unwrap_or_default(),
+        to: id(stops.last().map(|x| x.as_str()).unwrap_or_default()),
+        career_type: career_type(route.route_type),
+        route: route_stops,
+    }
+}
+
+/// GTFSのroute_typeをcareer_typeにする
+pub fn career_type(route_type: u16) -> CareerType {
+    match route_type {
+        0 => CareerType::Tram,
+        1 => CareerType::Subway,
+        2 | 100..=117 => CareerType::Train,
+        4 | 1000..=1099 => CareerType::Ferry,
+        1100..=1199 => CareerType::Airplane,
+        _ => CareerType::Bus,
+    }
+}
+
+fn parse_date(raw: &str) -> Result<NaiveDate, Box<dyn Error + Send + Sync + 'static>> {
+    NaiveDate::parse_from_str(raw, "%Y%m%d").map_err(|e| format!("invalid date `{}`: {}", raw, e).into())
+}
+
+/// 運行日ごとの、運行するservice_idの一覧
+/// calendar.txtの期間の区切り (その日から新しい期間が始まる日) も持つ
+struct ServiceDates {
+    services: HashMap<NaiveDate, BTreeSet<String>>,
+    last: NaiveDate,
+    period_starts: BTreeSet<NaiveDate>,
+}
+
+fn service_dates(feed: &Feed, warnings: &mut Vec<String>) -> Result<ServiceDates, Box<dyn Error + Send + Sync + 'static>> {
+    let mut services: HashMap<NaiveDate, BTreeSet<String>> = HashMap::new();
+    let mut period_starts: BTreeSet<NaiveDate> = BTreeSet::new();
+    for calendar in feed.calendar.iter() {
+        let start = parse_date(&calendar.start_date)?;
+        let end = parse_date(&calendar.end_date)?;
+        if end < start {
+            warnings.push(format!("service {}: end_date is before start_date", calendar.service_id));
+            continue;
+        }
+        period_starts.insert(start);
+        if let Some(next) = end.checked_add_days(Days::new(1)) {
+            period_starts.insert(next);
+        }
+        let flags = [
+            calendar.monday, calendar.tuesday, calendar.wednesday, calendar.thursday,
+            calendar.friday, calendar.saturday, calendar.sunday,
+        ];
+        for date in start.iter_days().take_while(|x| *x <= end) {
+            if flags[date.weekday().num_days_from_monday() as usize] == 1 {
+                services.entry(date).or_default().insert(calendar.service_id.clone());
+            }
+        }
+    }
+    let mut dates: BTreeSet<NaiveDate> = services.keys().cloned().collect();
+    for calendar_date in feed.calendar_dates.iter() {
+        let date = parse_date(&calendar_date.date)?;
+        dates.insert(date);
+        let entry = services.entry(date).or_default();
+        match calendar_date.exception_type {
+            1 => {
+                entry.insert(calendar_date.service_id.clone());
+            }
+            2 => {
+                entry.remove(&calendar_date.service_id);
+            }
+            other => warnings.push(format!("service {}: unknown exception_type {}", calendar_date.service_id, other)),
+        }
+    }
+
+    let (Some(first), Some(last)) = (dates.first().cloned(), dates.last().cloned()) else {
+        return Err("feed has no service dates.".into());
+    };
+    period_starts.insert(first);
+    Ok(ServiceDates {
+        services,
+        last,
+        period_starts: period_starts.into_iter().filter(|x| *x >= first && *x <= last).collect(),
+    })
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun,
+];
+
+/// 期間内のカレンダー。曜日ごとに一番多い組み合わせを既定にして、それ以外の日を例外にする
+#[derive(Debug, Clone, PartialEq)]
+struct Period {
+    weekdays: [usize; 7],
+    exceptions: Vec<(NaiveDate, usize)>,
+    first: NaiveDate,
+    last: NaiveDate,
+}
+
+fn import_station_calendars(
+    station_id: &str,
+    departures: &[(&str, RawDeparture)],
+    service_dates: &ServiceDates,
+    dataset: &mut RawDataset,
+) {
+    let station_services: BTreeSet<&str> = departures.iter().map(|(service_id, _)| *service_id).collect();
+    // 運行するserviceの組み合わせ -> 発車パターンの番号
+    let mut combinations: Vec<BTreeSet<&str>> = Vec::new();
+    let mut combination_of = |date: NaiveDate| -> usize {
+        let combination: BTreeSet<&str> = service_dates
+            .services
+            .get(&date)
+            .map(|x| x.iter().map(|y| y.as_str()).filter(|y| station_services.contains(y)).collect())
+            .unwrap_or_default();
+        match combinations.iter().position(|x| *x == combination) {
+            Some(index) => index,
+            None => {
+                combinations.push(combination);
+                combinations.len() - 1
+            }
+        }
+    };
+
+    let starts: Vec<NaiveDate> = service_dates.period_starts.iter().cloned().collect();
+    let mut periods: Vec<Period> = Vec::new();
+    for (index, start) in starts.iter().enumerate() {
+        let last = starts
+            .get(index + 1)
+            .and_then(|x| x.pred_opt())
+            .unwrap_or(service_dates.last);
+        let dates: Vec<(NaiveDate, usize)> = start
+            .iter_days()
+            .take_while(|x| *x <= last)
+            .map(|x| (x, combination_of(x)))
+            .collect();
+
+        let mut weekdays = [0; 7];
+        for (weekday_index, weekday) in WEEKDAYS.iter().enumerate() {
+            // 一番多いもの。同じ数なら先に出てきたもの
+            let mut counts: Vec<(usize, usize)> = Vec::new();
+            for (_, combination) in dates.iter().filter(|(date, _)| date.weekday() == *weekday) {
+                match counts.iter_mut().find(|(x, _)| x == combination) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((*combination, 1)),
+                }
+            }
+            let max = counts.iter().map(|(_, count)| *count).max().unwrap_or(0);
+            weekdays[weekday_index] = counts.iter().find(|(_, count)| *count == max).map(|(x, _)| *x).unwrap_or(0);
+        }
+        let exceptions: Vec<(NaiveDate, usize)> = dates
+            .into_iter()
+            .filter(|(date, combination)| weekdays[date.weekday().num_days_from_monday() as usize] != *combination)
+            .collect();
+
+        // 曜日ごとの既定が同じなら、1つ前の期間とまとめる
+        match periods.last_mut() {
+            Some(previous) if previous.weekdays == weekdays => {
+                previous.exceptions.extend(exceptions);
+                previous.last = last;
+            }
+            _ => periods.push(Period { weekdays, exceptions, first: *start, last }),
+        }
+    }
+    let pattern_id = |index: usize| format!("{}_{:04}", station_id, index + 1);
+    for (index, combination) in combinations.iter().enumerate() {
+        dataset.patterns.push(RawDeparturePattern {
+            pattern_id: pattern_id(index),
+            departures: departures
+                .iter()
+                .filter(|(service_id, _)| combination.contains(service_id))
+                .map(|(_, departure)| departure.clone())
+                .collect(),
+        });
+    }
+
+    let mut versions: Vec<RawVersion> = Vec::new();
+    for (index, period) in periods.iter().enumerate() {
+        let calendar_id = format!("{}_{:04}", station_id, index + 1);
+        let weekday = |x: usize| pattern_id(period.weekdays[x]);
+        dataset.calendars.push(RawCalendar {
+            calendar_id: calendar_id.clone(),
+            monday: weekday(0),
+            tuesday: weekday(1),
+            wednesday: weekday(2),
+            thursday: weekday(3),
+            friday: weekday(4),
+            saturday: weekday(5),
+            sunday: weekday(6),
+            holiday: None,
+            annual: vec![],
+            range_exception: vec![],
+            recurring_exception: vec![],
+            exception: period
+                .exceptions
+                .iter()
+                .map(|(date, combination)| RawCalendarException {
+                    date: date.format("%Y/%m/%d").to_string(),
+                    pattern_id: pattern_id(*combination),
+                })
+                .collect(),
+        });
+        versions.push(RawVersion {
+            calendar_id,
+            // 最初の期間より前はフィードに運行日がないので、フィードの始まりから有効にする
+            // それ以降の期間は前の期間の翌日から始まるので省略する
+            valid_from: (index == 0).then(|| period.first.format("%Y/%m/%d").to_string()),
+            valid_until: period.last.format("%Y/%m/%d").to_string(),
+        });
+    }
+    dataset.timetables.push(RawTimetable {
+        station_id: station_id.to_string(),
+        versions,
+    });
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::{import, ImportOptions};
+    use crate::feed::Feed;
+    use calc::dataset::Dataset;
+    use chrono::NaiveDate;
+    use models::{departure::DepartureType, id::{StationId, ID}, ride::CareerType};
+    use std::{collections::HashMap, fs};
+
+    fn feed() -> Feed {
+        let tables: HashMap<&str, &str> = HashMap::from([
+            ("stops.txt", "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                01,函館駅前,,,0,P\n02,函館駅前,,,0,P\n03,赤川,41.8297,140.7384,0,\n04,亀田支所前,,,0,\nP,函館駅前,,,1,\n"),
+            ("routes.txt", "route_id,route_short_name,route_type,route_color,route_text_color\n\
+                7A,7A,3,000000,FFD700\nSH,シャトル,3,,\n"),
+            ("trips.txt", "route_id,service_id,trip_id,direction_id\n\
+                7A,weekday,7A-0700,0\n7A,weekend,7A-0900,0\nSH,weekday,SH-0800,0\nSH,weekday,SH-2350,0\n"),
+            ("stop_times.txt", "trip_id,arrival_time,departure_time,stop_id,stop_sequence,pickup_type,drop_off_type\n\
+                7A-0700,07:00:00,07:00:00,03,1,,\n7A-0700,07:10:00,07:10:00,04,2,,\n\
+                7A-0700,07:20:00,07:20:00,01,3,,\n7A-0700,07:30:00,07:30:00,04,4,,\n7A-0700,07:40:00,07:40:00,03,5,,\n\
+                7A-0900,09:00:00,09:00:00,03,1,,\n7A-0900,09:10:00,09:10:00,04,2,,\n\
+                7A-0900,09:20:00,09:20:00,01,3,,\n7A-0900,09:30:00,09:30:00,04,4,,\n7A-0900,09:40:00,09:40:00,03,5,,\n\
+                SH-0800,08:00:00,08:00:00,02,1,,\nSH-0800,08:05:00,08:05:00,04,2,1,0\nSH-0800,08:15:00,08:15:00,03,3,,\n\
+                SH-2350,23:50:00,23:50:00,02,1,,\nSH-2350,24:05:00,24:05:00,03,2,,\n"),
+            ("calendar.txt", "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\n\
+                weekday,1,1,1,1,1,0,0,20250401,20250430\nweekend,0,0,0,0,0,1,1,20250401,20250430\n"),
+            ("calendar_dates.txt", "service_id,date,exception_type\nweekday,20250429,2\nweekend,20250429,1\n"),
+            ("translations.txt", "table_name,field_name,language,translation,record_id,field_value\n\
+                stops,stop_name,ja-Hrkt,あかがわ,03,\n"),
+        ]);
+        Feed::parse(|name| Ok(tables.get(name).map(|x| x.to_string()))).unwrap()
+    }
+
+    fn options() -> ImportOptions {
+        ImportOptions { prefix: "TEST".to_string() }
+    }
+
+    #[test]
+    fn 同じ停留所に2回停車する系統には周回数をつける() {
+        let imported = import(&feed(), &options()).unwrap();
+        let ride = imported.dataset.rides.iter().find(|x| x.ride_id == "TEST_7A").unwrap();
+        assert_eq!(vec!["TEST_03~1", "TEST_04~1", "TEST_01", "TEST_04~2", "TEST_03~2"], ride.route);
+        assert_eq!("TEST_03", ride.to);
+        assert_eq!("#FFD700", ride.type_foreground);
+        assert_eq!("#000000", ride.type_background);
+        assert_eq!(CareerType::Bus, ride.career_type);
+
+        let pattern = imported.dataset.patterns.iter().find(|x| x.pattern_id == "TEST_04_0001").unwrap();
+        let loop_counts: Vec<(String, Option<i32>)> = pattern.departures.iter().map(|x| (x.time.clone(), x.loop_count)).collect();
+        assert_eq!(
+            vec![("07:10".to_string(), None), ("07:30".to_string(), Some(2)), ("08:05".to_string(), None)],
+            loop_counts
+        );
+        // pickup_type=1の停車は降車専用
+        assert_eq!(Some(DepartureType::Incoming), pattern.departures[2].departure_type);
+    }
+
+    #[test]
+    fn 親駅が同じ停留所をjoinでつなぐ() {
+        let imported = import(&feed(), &options()).unwrap();
+        let stations = imported.dataset.stations;
+        assert_eq!(4, stations.len());
+        let station = stations.iter().find(|x| x.station_id == "TEST_01").unwrap();
+        assert_eq!(vec!["TEST_02"], station.join);
+        let station = stations.iter().find(|x| x.station_id == "TEST_03").unwrap();
+        assert_eq!("あかがわ", station.pronounce);
+        assert_eq!((Some(41.8297), Some(140.7384)), (station.lat, station.lon));
+        let station = stations.iter().find(|x| x.station_id == "TEST_04").unwrap();
+        assert_eq!((None, None), (station.lat, station.lon));
+    }
+
+    #[test]
+    fn 始発は乗車専用_終点は降車専用になる() {
+        let imported = import(&feed(), &options()).unwrap();
+        let pattern = imported.dataset.patterns.iter().find(|x| x.pattern_id == "TEST_03_0001").unwrap();
+        let types: Vec<(String, Option<DepartureType>)> = pattern.departures.iter().map(|x| (x.time.clone(), x.departure_type)).collect();
+        assert_eq!(
+            vec![
+                ("07:00".to_string(), Some(DepartureType::Outgoing)),
+                ("07:40".to_string(), Some(DepartureType::Incoming)),
+                ("08:15".to_string(), Some(DepartureType::Incoming)),
+                ("24:05".to_string(), Some(DepartureType::Incoming)),
+            ],
+            types
+        );
+    }
+
+    #[test]
+    fn 曜日ごとの既定と例外の日をカレンダーにする() {
+        let imported = import(&feed(), &options()).unwrap();
+        let calendar = imported.dataset.calendars.iter().find(|x| x.calendar_id == "TEST_03_0001").unwrap();
+        assert_eq!("TEST_03_0001", calendar.monday);
+        assert_eq!("TEST_03_0002", calendar.saturday);
+        assert_eq!(1, calendar.exception.len());
+        assert_eq!("2025/04/29", calendar.exception[0].date);
+        assert_eq!("TEST_03_0002", calendar.exception[0].pattern_id);
+
+        let timetable = imported.dataset.timetables.iter().find(|x| x.station_id == "TEST_03").unwrap();
+        assert_eq!(1, timetable.versions.len());
+        assert_eq!(Some("2025/04/01".to_string()), timetable.versions[0].valid_from);
+        assert_eq!("2025/04/30", timetable.versions[0].valid_until);
+    }
+
+    #[test]
+    fn 日付をまたぐ時刻は24時以降のまま取り込む() {
+        let imported = import(&feed(), &options()).unwrap();
+        assert!(imported.warnings.iter().all(|x| !x.contains("24:05:00")));
+        assert!(imported.dataset.patterns.iter().flat_map(|x| x.departures.iter()).any(|x| x.time == "24:05"));
+    }
+
+    #[test]
+    fn 書き出したデータをそのまま読み込める() {
+        let root = std::env::temp_dir().join(format!("transfar-gtfs-import-{}", std::process::id()));
+        let root_path = root.to_str().unwrap();
+        let imported = import(&feed(), &options()).unwrap();
+        imported.dataset.write(root_path).unwrap();
+
+        let dataset = Dataset::load(root_path);
+        fs::remove_dir_all(&root).unwrap();
+        let dataset = dataset.unwrap();
+
+        let holiday = NaiveDate::from_ymd_opt(2025, 4, 29).unwrap();
+        let station_id = StationId::new("TEST_03".to_string(), root_path);
+        let trip_ids: Vec<String> = dataset.pattern_on(&station_id, holiday).unwrap().departures.iter().map(|x| x.trip_id.clone()).collect();
+        assert_eq!(vec!["TEST_7A-0900", "TEST_7A-0900"], trip_ids);
+        assert!(dataset.is_same_station(&StationId::new("TEST_02".to_string(), root_path), &StationId::new("TEST_01".to_string(), root_path)));
+    }
+
+    #[test]
+    fn prefixに区切り文字を含められない() {
+        assert!(import(&feed(), &ImportOptions { prefix: "HAKODATE_BUS".to_string() }).is_err());
+    }
+}
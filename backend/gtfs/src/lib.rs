@@ -0,0 +1,4 @@
+pub mod export;
+pub mod feed;
+pub mod import;
+pub mod raw_dataset;
@@ -0,0 +1,64 @@
+use std::{error::Error, fs, path::{Path, PathBuf}};
+use models::{
+    calendar::RawCalendar, departure::RawDeparturePattern, ride::RawRide, station::RawStation,
+    timetable::RawTimetable,
+};
+use serde::Serialize;
+
+/// データのルート以下に書き出す各ファイルの中身
+#[derive(Debug, Default)]
+pub struct RawDataset {
+    pub stations: Vec<RawStation>,
+    pub rides: Vec<RawRide>,
+    pub timetables: Vec<RawTimetable>,
+    pub calendars: Vec<RawCalendar>,
+    pub patterns: Vec<RawDeparturePattern>,
+}
+
+impl RawDataset {
+    /// data_root_path以下にファイルを書き出し、書き出したファイルのパスを返す
+    /// 同じIDのファイルがすでにあれば上書きする
+    pub fn write(&self, data_root_path: &str) -> Result<Vec<PathBuf>, Box<dyn Error + Send + Sync + 'static>> {
+        let root = Path::new(data_root_path);
+        let mut written: Vec<PathBuf> = Vec::new();
+        for x in self.stations.iter() {
+            written.push(write_json(root, "station", &x.station_id, x)?);
+        }
+        for x in self.rides.iter() {
+            written.push(write_json(root, "ride", &x.ride_id, x)?);
+        }
+        for x in self.timetables.iter() {
+            written.push(write_json(root, "timetable", &x.station_id, x)?);
+        }
+        for x in self.calendars.iter() {
+            written.push(write_json(root, "calendar", &x.calendar_id, x)?);
+        }
+        for x in self.patterns.iter() {
+            written.push(write_json(root, "departure-pattern", &x.pattern_id, x)?);
+        }
+        Ok(written)
+    }
+}
+
+/// ID::build_pathと同じく、IDを"_"で区切ったものをディレクトリとして扱う
+fn write_json<T: Serialize>(
+    root: &Path,
+    data_type_path: &str,
+    raw_id: &str,
+    value: &T,
+) -> Result<PathBuf, Box<dyn Error + Send + Sync + 'static>> {
+    let mut path = root.join(data_type_path);
+    path.extend(raw_id.split('_'));
+    path.set_extension("json");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut content: Vec<u8> = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut content, formatter);
+    value.serialize(&mut serializer)?;
+    content.push(b'\n');
+    fs::write(&path, content)?;
+    Ok(path)
+}
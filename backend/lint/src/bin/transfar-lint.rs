@@ -0,0 +1,68 @@
+use std::{env, error::Error, process::ExitCode};
+use chrono::{FixedOffset, NaiveDate, Utc};
+use lint::{checks::lint, report::Severity};
+
+const USAGE: &str = "usage: transfar-lint [--data-root <data root>] [--today YYYY-MM-DD] [--deny-warnings]";
+const DEFAULT_DATA_ROOT: &str = "data";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    data_root: Option<String>,
+    today: Option<String>,
+    deny_warnings: bool,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut res = Args::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        if arg == "--deny-warnings" {
+            res.deny_warnings = true;
+            continue;
+        }
+        let (key, inline_value) = match arg.split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let target = match key.as_str() {
+            "--data-root" => &mut res.data_root,
+            "--today" => &mut res.today,
+            _ => return Err(format!("unknown argument: {}\n{}", arg, USAGE)),
+        };
+        let value = inline_value
+            .or_else(|| args.next())
+            .ok_or(format!("{} requires a value", key))?;
+        *target = Some(value);
+    }
+    Ok(res)
+}
+
+fn run() -> Result<bool, Box<dyn Error + Send + Sync + 'static>> {
+    let args = parse_args(env::args().skip(1))?;
+    let today = match &args.today {
+        Some(today) => NaiveDate::parse_from_str(today, "%Y-%m-%d")
+            .map_err(|_| format!("--today must be formatted as YYYY-MM-DD but got `{}`.", today))?,
+        None => Utc::now().with_timezone(&FixedOffset::east_opt(9 * 60 * 60).expect("valid offset")).date_naive(),
+    };
+    let data_root = args.data_root.unwrap_or(DEFAULT_DATA_ROOT.to_string());
+
+    let report = lint(&data_root, today);
+    for problem in report.problems.iter() {
+        println!("{}", problem);
+    }
+    let errors = report.count(Severity::Error);
+    let warnings = report.count(Severity::Warning);
+    println!("{} errors, {} warnings in {} (as of {})", errors, warnings, data_root, today);
+    Ok(errors == 0 && (!args.deny_warnings || warnings == 0))
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,653 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use calc::{dataset::Dataset, raptor::network::Network};
+use chrono::{Days, NaiveDate};
+use models::{
+    calendar::{AnnualPeriod, RangeException, RecurringException},
+    id::ID,
+    service_time::ServiceTime,
+};
+
+use crate::raw::RawData;
+use crate::report::{Report, Severity};
+
+/// 発車時刻の並びを確認する日数 (すべての曜日を含むように1週間)
+const SCHEDULE_CHECK_DAYS: u64 = 7;
+
+/// データのルート以下をすべて確認する。todayは期限切れの判定と、時刻の並びを確認する日の基準に使う
+pub fn lint(data_root_path: &str, today: NaiveDate) -> Report {
+    let mut report = Report::default();
+    let data = RawData::load(data_root_path, &mut report);
+
+    check_stations(&data, &mut report);
+    check_rides(&data, &mut report);
+    check_timetables(&data, today, &mut report);
+    check_calendars(&data, &mut report);
+    check_patterns(&data, &mut report);
+    check_transfar_rules(&data, &mut report);
+
+    // 参照が壊れているとNetworkを作れないので、時刻の並びはほかにエラーがないときだけ確認する
+    if report.count(Severity::Error) == 0 {
+        match Dataset::load(data_root_path) {
+            Ok(dataset) => check_trip_times(&dataset, &data, today, &mut report),
+            Err(e) => {
+                for error in e.errors {
+                    report.error(data_root_path, error);
+                }
+            }
+        }
+    } else {
+        report.warning(data_root_path, "skipped checking the order of departure times because of the errors above");
+    }
+
+    report.sort();
+    report
+}
+
+/// `~n`を取り除いたIDとnを返す。nがなければNone
+fn split_loop(raw: &str) -> (&str, Option<&str>) {
+    match raw.split_once('~') {
+        Some((raw_id, loop_count)) => (raw_id, Some(loop_count)),
+        None => (raw, None),
+    }
+}
+
+fn check_stations(data: &RawData, report: &mut Report) {
+    for (raw_id, file) in data.stations.iter() {
+        if file.value.station_id != *raw_id {
+            report.error(&file.path, format!("station_id `{}` does not match the file path (`{}`)", file.value.station_id, raw_id));
+        }
+        for joined in file.value.join.iter() {
+            if joined == raw_id {
+                report.warning(&file.path, "join contains the station itself");
+            } else if !data.stations.contains_key(joined) {
+                report.error(&file.path, format!("join: station {} not found", joined));
+            }
+        }
+        match (file.value.lat, file.value.lon) {
+            (Some(lat), Some(lon)) => {
+                if !(-90.0..=90.0).contains(&lat) {
+                    report.error(&file.path, format!("lat {} is out of range", lat));
+                }
+                if !(-180.0..=180.0).contains(&lon) {
+                    report.error(&file.path, format!("lon {} is out of range", lon));
+                }
+            }
+            (None, None) => {}
+            _ => report.error(&file.path, "lat and lon must be given together"),
+        }
+    }
+}
+
+fn check_rides(data: &RawData, report: &mut Report) {
+    for (raw_id, file) in data.rides.iter() {
+        let ride = &file.value;
+        if ride.ride_id != *raw_id {
+            report.error(&file.path, format!("ride_id `{}` does not match the file path (`{}`)", ride.ride_id, raw_id));
+        }
+        if ride.route.is_empty() {
+            report.error(&file.path, "route is empty");
+        }
+        if !data.stations.contains_key(&ride.to) {
+            report.error(&file.path, format!("to: station {} not found", ride.to));
+        }
+
+        let mut occurrences: BTreeMap<&str, Vec<Option<&str>>> = BTreeMap::new();
+        for stop in ride.route.iter() {
+            let (station_id, loop_count) = split_loop(stop);
+            if !data.stations.contains_key(station_id) {
+                report.error(&file.path, format!("route: station {} not found", station_id));
+            }
+            if let Some(loop_count) = loop_count && loop_count.parse::<i32>().is_err() {
+                report.error(&file.path, format!("route: `{}` has an invalid loop count", stop));
+            }
+            occurrences.entry(station_id).or_default().push(loop_count);
+        }
+        // 2回以上停車する駅は`~1`から順に番号をつける
+        for (station_id, loop_counts) in occurrences {
+            let expected: Vec<String> = (1..=loop_counts.len()).map(|x| x.to_string()).collect();
+            let actual: Vec<String> = loop_counts.iter().map(|x| x.unwrap_or("").to_string()).collect();
+            if loop_counts.len() > 1 && actual != expected {
+                report.error(
+                    &file.path,
+                    format!(
+                        "route: station {} appears {} times; write them as `{}~1` to `{}~{}` in order",
+                        station_id, loop_counts.len(), station_id, station_id, loop_counts.len()
+                    ),
+                );
+            } else if loop_counts.len() == 1 && !matches!(loop_counts[0], None | Some("1")) {
+                report.warning(&file.path, format!("route: station {} appears once but has `~{}`", station_id, loop_counts[0].unwrap_or("")));
+            }
+        }
+    }
+}
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw, "%Y/%m/%d").ok()
+}
+
+fn check_timetables(data: &RawData, today: NaiveDate, report: &mut Report) {
+    for (raw_id, file) in data.timetables.iter() {
+        let timetable = &file.value;
+        if timetable.station_id != *raw_id {
+            report.error(&file.path, format!("station_id `{}` does not match the file path (`{}`)", timetable.station_id, raw_id));
+        }
+        if !data.stations.contains_key(raw_id) {
+            report.error(&file.path, format!("station {} not found", raw_id));
+        }
+        if timetable.versions.is_empty() {
+            report.error(&file.path, "versions is empty");
+        }
+
+        let mut previous: Option<NaiveDate> = None;
+        let mut has_valid_version = false;
+        for version in timetable.versions.iter() {
+            if !data.calendars.contains_key(&version.calendar_id) {
+                report.error(&file.path, format!("calendar {} not found", version.calendar_id));
+            }
+            let Some(valid_until) = parse_date(&version.valid_until) else {
+                report.error(&file.path, format!("valid_until `{}` must be formatted as YYYY/MM/DD", version.valid_until));
+                continue;
+            };
+            // 最初に見つかった有効なバージョンが使われるので、古い順に並んでいないと後ろのバージョンが使われない
+            if let Some(previous) = previous && valid_until <= previous {
+                report.error(&file.path, format!("versions must be sorted by valid_until ({} is not after {})", valid_until, previous));
+            }
+            if let Some(raw_valid_from) = &version.valid_from {
+                match parse_date(raw_valid_from) {
+                    Some(valid_from) if valid_from > valid_until => {
+                        report.error(&file.path, format!("calendar {}: valid_from {} is after valid_until {}", version.calendar_id, valid_from, valid_until));
+                    }
+                    Some(valid_from) => {
+                        if let Some(previous) = previous && previous < valid_until {
+                            if valid_from <= previous {
+                                report.error(&file.path, format!("calendar {}: valid_from {} overlaps the previous version (until {})", version.calendar_id, valid_from, previous));
+                            } else if valid_from > previous + Days::new(1) {
+                                report.warning(&file.path, format!("no calendar is valid from {} to {}", previous + Days::new(1), valid_from - Days::new(1)));
+                            }
+                        }
+                    }
+                    None => report.error(&file.path, format!("valid_from `{}` must be formatted as YYYY/MM/DD", raw_valid_from)),
+                }
+            }
+            previous = Some(valid_until);
+            if valid_until >= today {
+                has_valid_version = true;
+            } else {
+                report.warning(&file.path, format!("calendar {} expired on {}", version.calendar_id, valid_until));
+            }
+        }
+        if !timetable.versions.is_empty() && !has_valid_version {
+            report.error(&file.path, format!("all versions expired; the station has no departures on {} or later", today));
+        }
+    }
+}
+
+fn check_calendars(data: &RawData, report: &mut Report) {
+    let used: BTreeSet<&String> = data
+        .timetables
+        .values()
+        .flat_map(|x| x.value.versions.iter().map(|y| &y.calendar_id))
+        .collect();
+    for (raw_id, file) in data.calendars.iter() {
+        let calendar = &file.value;
+        if calendar.calendar_id != *raw_id {
+            report.error(&file.path, format!("calendar_id `{}` does not match the file path (`{}`)", calendar.calendar_id, raw_id));
+        }
+        if !used.contains(raw_id) {
+            report.warning(&file.path, "not used by any timetable");
+        }
+        let weekdays = [
+            &calendar.monday, &calendar.tuesday, &calendar.wednesday, &calendar.thursday,
+            &calendar.friday, &calendar.saturday, &calendar.sunday,
+        ]
+        .into_iter()
+        .chain(calendar.holiday.iter());
+        for pattern_id in weekdays {
+            if !data.patterns.contains_key(pattern_id) {
+                report.error(&file.path, format!("departure-pattern {} not found", pattern_id));
+            }
+        }
+
+        for period in calendar.annual.iter() {
+            if AnnualPeriod::from_raw(period).is_err() {
+                report.error(&file.path, format!("annual: `{}`-`{}` must be formatted as MM/DD", period.from, period.until));
+            }
+        }
+
+        let mut dates: BTreeSet<NaiveDate> = BTreeSet::new();
+        for exception in calendar.exception.iter() {
+            if !data.patterns.contains_key(&exception.pattern_id) {
+                report.error(&file.path, format!("exception: departure-pattern {} not found", exception.pattern_id));
+            }
+            match parse_date(&exception.date) {
+                Some(date) => {
+                    if !dates.insert(date) {
+                        report.warning(&file.path, format!("exception: {} appears more than once; only the first one is used", date));
+                    }
+                }
+                None => report.error(&file.path, format!("exception: date `{}` must be formatted as YYYY/MM/DD", exception.date)),
+            }
+        }
+
+        let mut ranges: Vec<RangeException> = vec![];
+        for raw in calendar.range_exception.iter() {
+            if !data.patterns.contains_key(&raw.pattern_id) {
+                report.error(&file.path, format!("range_exception: departure-pattern {} not found", raw.pattern_id));
+            }
+            match RangeException::from_raw(raw, "") {
+                Ok(range) => {
+                    // 重なる期間では先に書いたものが使われる
+                    if let Some(previous) = ranges.iter().find(|x| ranges_overlap(x, &range)) {
+                        report.warning(
+                            &file.path,
+                            format!("range_exception: {}-{} overlaps {}-{}; only the first one is used", range.from, range.until, previous.from, previous.until),
+                        );
+                    }
+                    ranges.push(range);
+                }
+                Err(e) => report.error(&file.path, format!("range_exception: `{}`-`{}` is invalid: {}", raw.from, raw.until, e)),
+            }
+        }
+
+        for raw in calendar.recurring_exception.iter() {
+            if !data.patterns.contains_key(&raw.pattern_id) {
+                report.error(&file.path, format!("recurring_exception: departure-pattern {} not found", raw.pattern_id));
+            }
+            if let Err(e) = RecurringException::from_raw(raw, "") {
+                report.error(&file.path, e.to_string());
+            }
+        }
+    }
+}
+
+/// 期間と曜日の両方が重なるか
+fn ranges_overlap(a: &RangeException, b: &RangeException) -> bool {
+    let days_overlap = match (&a.days, &b.days) {
+        (Some(a), Some(b)) => a.iter().any(|x| b.contains(x)),
+        _ => true,
+    };
+    a.from <= b.until && b.from <= a.until && days_overlap
+}
+
+/// 発車パターンのID -> そのパターンを使う駅
+fn stations_by_pattern(data: &RawData) -> HashMap<&str, BTreeSet<&str>> {
+    let mut res: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for (station_id, timetable) in data.timetables.iter() {
+        for version in timetable.value.versions.iter() {
+            let Some(calendar) = data.calendars.get(&version.calendar_id) else {
+                continue;
+            };
+            let calendar = &calendar.value;
+            let pattern_ids = [
+                &calendar.monday, &calendar.tuesday, &calendar.wednesday, &calendar.thursday,
+                &calendar.friday, &calendar.saturday, &calendar.sunday,
+            ]
+            .into_iter()
+            .chain(calendar.holiday.iter())
+            .chain(calendar.exception.iter().map(|x| &x.pattern_id))
+            .chain(calendar.range_exception.iter().map(|x| &x.pattern_id))
+            .chain(calendar.recurring_exception.iter().map(|x| &x.pattern_id));
+            for pattern_id in pattern_ids {
+                res.entry(pattern_id.as_str()).or_default().insert(station_id.as_str());
+            }
+        }
+    }
+    res
+}
+
+fn check_patterns(data: &RawData, report: &mut Report) {
+    let stations_by_pattern = stations_by_pattern(data);
+    for (raw_id, file) in data.patterns.iter() {
+        let pattern = &file.value;
+        if pattern.pattern_id != *raw_id {
+            report.error(&file.path, format!("pattern_id `{}` does not match the file path (`{}`)", pattern.pattern_id, raw_id));
+        }
+        let stations = stations_by_pattern.get(raw_id.as_str());
+        if stations.is_none() {
+            report.warning(&file.path, "not used by any calendar");
+        }
+
+        for departure in pattern.departures.iter() {
+            let trip = &departure.trip_id;
+            if ServiceTime::parse(&departure.time).is_err() {
+                report.error(&file.path, format!("trip {}: time `{}` must be formatted as HH:MM", trip, departure.time));
+            }
+            let loop_count = departure.loop_count.unwrap_or(1);
+            if loop_count < 1 {
+                report.error(&file.path, format!("trip {}: loop_count must be 1 or more", trip));
+            }
+            let Some(ride) = data.rides.get(&departure.ride_id) else {
+                report.error(&file.path, format!("trip {}: ride {} not found", trip, departure.ride_id));
+                continue;
+            };
+
+            // 駅がRideの経路に含まれていて、loop_countに対応する`~n`があるか
+            for station_id in stations.into_iter().flatten() {
+                let loop_counts: Vec<Option<&str>> = ride
+                    .value
+                    .route
+                    .iter()
+                    .map(|x| split_loop(x))
+                    .filter(|(x, _)| x == station_id)
+                    .map(|(_, x)| x)
+                    .collect();
+                let expected = loop_count.to_string();
+                let found = match loop_counts.as_slice() {
+                    [] => {
+                        report.error(&file.path, format!("trip {}: station {} is not on the route of ride {}", trip, station_id, departure.ride_id));
+                        continue;
+                    }
+                    [single] => loop_count == 1 || *single == Some(expected.as_str()),
+                    multiple => multiple.contains(&Some(expected.as_str())),
+                };
+                if !found {
+                    report.error(
+                        &file.path,
+                        format!("trip {}: loop_count {} does not match the route of ride {} (no `{}~{}`)", trip, loop_count, departure.ride_id, station_id, loop_count),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn check_transfar_rules(data: &RawData, report: &mut Report) {
+    let Some(file) = &data.transfar_rules else {
+        return;
+    };
+    let rules = &file.value;
+    let stations = rules
+        .stations
+        .iter()
+        .map(|x| &x.station_id)
+        .chain(rules.station_pairs.iter().flat_map(|x| [&x.from_station_id, &x.to_station_id]));
+    for station_id in stations {
+        if !data.stations.contains_key(station_id) {
+            report.error(&file.path, format!("station {} not found", station_id));
+        }
+    }
+    let times = rules
+        .default
+        .iter()
+        .chain(rules.modes.iter().map(|x| &x.time))
+        .chain(rules.stations.iter().map(|x| &x.time))
+        .chain(rules.station_pairs.iter().map(|x| &x.time));
+    for time in times {
+        if let (Some(min), Some(max)) = (time.min, time.max) && min > max {
+            report.error(&file.path, format!("min ({}) is greater than max ({})", min, max));
+        }
+    }
+    if let Some(walking) = &rules.walking {
+        if walking.radius_meters.is_some_and(|x| x < 0.0) {
+            report.error(&file.path, "walking.radius_meters must not be negative");
+        }
+        if walking.meters_per_minute.is_some_and(|x| x <= 0.0) {
+            report.error(&file.path, "walking.meters_per_minute must be positive");
+        }
+    }
+}
+
+/// 確認する日。todayから1週間と、それ以降の特別ダイヤの日、valid_fromで新しいカレンダーが始まる日
+/// range_exceptionとrecurring_exceptionは、それぞれ最初に使われる日だけを確認する
+fn schedule_check_dates(data: &RawData, today: NaiveDate) -> BTreeSet<NaiveDate> {
+    let mut res: BTreeSet<NaiveDate> = today.iter_days().take(SCHEDULE_CHECK_DAYS as usize).collect();
+    let last = data
+        .timetables
+        .values()
+        .flat_map(|x| x.value.versions.iter().filter_map(|y| parse_date(&y.valid_until)))
+        .max()
+        .unwrap_or(today);
+    res.extend(
+        data.timetables
+            .values()
+            .flat_map(|x| x.value.versions.iter().filter_map(|y| y.valid_from.as_deref().and_then(parse_date)))
+            .filter(|x| *x >= today && *x <= last),
+    );
+    for calendar in data.calendars.values() {
+        res.extend(
+            calendar
+                .value
+                .exception
+                .iter()
+                .filter_map(|x| parse_date(&x.date))
+                .filter(|x| *x >= today && *x <= last),
+        );
+        let dates = || today.iter_days().take_while(|x| *x <= last);
+        for range in calendar.value.range_exception.iter().filter_map(|x| RangeException::from_raw(x, "").ok()) {
+            res.extend(dates().skip_while(|x| *x < range.from).find(|x| range.contains(*x)));
+        }
+        for recurring in calendar.value.recurring_exception.iter().filter_map(|x| RecurringException::from_raw(x, "").ok()) {
+            res.extend(dates().find(|x| recurring.matches(*x)));
+        }
+    }
+    res
+}
+
+/// 同じ便の時刻が経路の順に並んでいるか確認する
+fn check_trip_times(dataset: &Dataset, data: &RawData, today: NaiveDate, report: &mut Report) {
+    for date in schedule_check_dates(data, today) {
+        let network = Network::from_dataset(dataset, date);
+        for trip in network.scheduled_trips() {
+            let mut previous: Option<(String, ServiceTime)> = None;
+            for stop in trip.stops.iter() {
+                let Some(time) = stop.time else {
+                    continue;
+                };
+                let station_id = stop.station_id.get_raw_id();
+                if let Some((previous_station, previous_time)) = &previous && time < *previous_time {
+                    let path = dataset
+                        .pattern_on(&stop.station_id, date)
+                        .map(|x| x.pattern_id.build_path())
+                        .unwrap_or(station_id.clone());
+                    report.error(
+                        path,
+                        format!(
+                            "trip {}: departs {} at {} but {} at {}, earlier than the previous stop on ride {}",
+                            trip.trip_id,
+                            previous_station,
+                            previous_time,
+                            station_id,
+                            time,
+                            trip.ride_id.get_raw_id(),
+                        ),
+                    );
+                }
+                previous = Some((station_id, time));
+            }
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+#[cfg(test)]
+mod test {
+    use super::lint;
+    use crate::report::Severity;
+    use chrono::NaiveDate;
+    use std::{fs, path::Path};
+
+    const STATIONS: [&str; 3] = ["01", "02", "03"];
+
+    /// 3駅を順に走るバスが1便あるデータを作る
+    fn write_dataset(root: &Path) {
+        for station in STATIONS {
+            write(root, &format!("station/T/{}.json", station), &format!(
+                r#"{{"station_id": "T_{}", "name": "{}", "join": [], "pronounce": ""}}"#, station, station
+            ));
+            write(root, &format!("timetable/T/{}.json", station), &format!(
+                r#"{{"station_id": "T_{0}", "versions": [{{"calendar_id": "T_{0}_0001", "valid_until": "2025/10/01"}}]}}"#, station
+            ));
+            write(root, &format!("calendar/T/{}/0001.json", station), &format!(
+                r#"{{"calendar_id": "T_{0}_0001", "monday": "T_{0}_0001", "tuesday": "T_{0}_0001", "wednesday": "T_{0}_0001",
+                    "thursday": "T_{0}_0001", "friday": "T_{0}_0001", "saturday": "T_{0}_0001", "sunday": "T_{0}_0001", "exception": []}}"#, station
+            ));
+        }
+        write(root, "ride/T/R.json", r##"{"ride_id": "T_R", "type": "R", "aka_type": "", "type_foreground": "#000000",
+            "type_background": "#FFFFFF", "type_pronounce": "", "to": "T_03", "career_type": "BUS", "route": ["T_01", "T_02", "T_03"]}"##);
+        write_departure(root, "01", "08:00");
+        write_departure(root, "02", "08:10");
+        write_departure(root, "03", "08:20");
+    }
+
+    fn write_departure(root: &Path, station: &str, time: &str) {
+        write(root, &format!("departure-pattern/T/{}/0001.json", station), &format!(
+            r#"{{"pattern_id": "T_{}_0001", "departures": [{{"ride_id": "T_R", "trip_id": "T_R_1", "time": "{}"}}]}}"#, station, time
+        ));
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn lint_with(name: &str, modify: impl Fn(&Path)) -> Vec<String> {
+        let root = std::env::temp_dir().join(format!("transfar-lint-{}-{}", name, std::process::id()));
+        write_dataset(&root);
+        modify(&root);
+        let report = lint(root.to_str().unwrap(), NaiveDate::from_ymd_opt(2025, 4, 25).unwrap());
+        fs::remove_dir_all(&root).unwrap();
+        report
+            .problems
+            .iter()
+            .filter(|x| x.severity == Severity::Error)
+            .map(|x| x.to_string().replace(root.to_str().unwrap(), ""))
+            .collect()
+    }
+
+    #[test]
+    fn 問題がなければエラーを報告しない() {
+        assert_eq!(Vec::<String>::new(), lint_with("valid", |_| {}));
+    }
+
+    #[test]
+    fn 存在しないファイルへの参照を報告する() {
+        let errors = lint_with("reference", |root| {
+            write(root, "departure-pattern/T/02/0001.json",
+                r#"{"pattern_id": "T_02_0001", "departures": [{"ride_id": "T_X", "trip_id": "T_R_1", "time": "08:10"}]}"#);
+            write(root, "station/T/03.json", r#"{"station_id": "T_03", "name": "03", "join": ["T_99"], "pronounce": ""}"#);
+        });
+        assert_eq!(
+            vec![
+                "error: /departure-pattern/T/02/0001.json: trip T_R_1: ride T_X not found".to_string(),
+                "error: /station/T/03.json: join: station T_99 not found".to_string(),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn 緯度経度の片方だけや範囲外の値を報告する() {
+        let errors = lint_with("coordinate", |root| {
+            write(root, "station/T/01.json", r#"{"station_id": "T_01", "name": "01", "join": [], "pronounce": "", "lat": 41.77}"#);
+            write(root, "station/T/02.json", r#"{"station_id": "T_02", "name": "02", "join": [], "pronounce": "", "lat": 141.77, "lon": 140.72}"#);
+        });
+        assert_eq!(
+            vec![
+                "error: /station/T/01.json: lat and lon must be given together".to_string(),
+                "error: /station/T/02.json: lat 141.77 is out of range".to_string(),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn 時刻が経路の順に並んでいない便を報告する() {
+        let errors = lint_with("backwards", |root| write_departure(root, "03", "08:05"));
+        assert_eq!(1, errors.len());
+        assert!(errors[0].starts_with("error: /departure-pattern/T/03/0001.json: trip T_R_1: departs T_02 at 08:10 but T_03 at 08:05"));
+    }
+
+    #[test]
+    fn 周回数が経路と合わない発車を報告する() {
+        let errors = lint_with("loop", |root| {
+            write(root, "departure-pattern/T/02/0001.json",
+                r#"{"pattern_id": "T_02_0001", "departures": [{"ride_id": "T_R", "trip_id": "T_R_1", "time": "08:10", "loop_count": 2}]}"#);
+        });
+        assert_eq!(
+            vec!["error: /departure-pattern/T/02/0001.json: trip T_R_1: loop_count 2 does not match the route of ride T_R (no `T_02~2`)".to_string()],
+            errors
+        );
+    }
+
+    #[test]
+    fn 同じ駅に2回停車するのに周回数がない経路を報告する() {
+        let errors = lint_with("route", |root| {
+            write(root, "ride/T/R.json", r##"{"ride_id": "T_R", "type": "R", "aka_type": "", "type_foreground": "#000000",
+                "type_background": "#FFFFFF", "type_pronounce": "", "to": "T_03", "career_type": "BUS", "route": ["T_01", "T_02", "T_03", "T_02"]}"##);
+        });
+        assert_eq!(
+            vec![
+                "error: /departure-pattern/T/02/0001.json: trip T_R_1: loop_count 1 does not match the route of ride T_R (no `T_02~1`)".to_string(),
+                "error: /ride/T/R.json: route: station T_02 appears 2 times; write them as `T_02~1` to `T_02~2` in order".to_string(),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn 期間と毎月の例外の日も時刻の並びを確認する() {
+        let errors = lint_with("range", |root| {
+            write(root, "calendar/T/03/0001.json", r#"{"calendar_id": "T_03_0001", "monday": "T_03_0001", "tuesday": "T_03_0001",
+                "wednesday": "T_03_0001", "thursday": "T_03_0001", "friday": "T_03_0001", "saturday": "T_03_0001", "sunday": "T_03_0001",
+                "exception": [],
+                "range_exception": [{"from": "2025/08/01", "until": "2025/08/31", "days": ["saturday"], "pattern_id": "T_03_0002"}],
+                "recurring_exception": [{"weekday": "holiday", "nth": [1], "pattern_id": "T_03_0001"}]}"#);
+            write(root, "departure-pattern/T/03/0002.json",
+                r#"{"pattern_id": "T_03_0002", "departures": [{"ride_id": "T_R", "trip_id": "T_R_1", "time": "08:05"}]}"#);
+        });
+        assert_eq!(
+            vec!["error: /calendar/T/03/0001.json: recurring_exception: weekday must not be holiday".to_string()],
+            errors
+        );
+
+        let errors = lint_with("range-order", |root| {
+            write(root, "calendar/T/03/0001.json", r#"{"calendar_id": "T_03_0001", "monday": "T_03_0001", "tuesday": "T_03_0001",
+                "wednesday": "T_03_0001", "thursday": "T_03_0001", "friday": "T_03_0001", "saturday": "T_03_0001", "sunday": "T_03_0001",
+                "exception": [],
+                "range_exception": [{"from": "2025/08/01", "until": "2025/08/31", "days": ["saturday"], "pattern_id": "T_03_0002"}]}"#);
+            write(root, "departure-pattern/T/03/0002.json",
+                r#"{"pattern_id": "T_03_0002", "departures": [{"ride_id": "T_R", "trip_id": "T_R_1", "time": "08:05"}]}"#);
+        });
+        // 期間の最初の土曜日 (2025/08/02) の時刻を確認する
+        assert_eq!(1, errors.len());
+        assert!(errors[0].starts_with("error: /departure-pattern/T/03/0002.json: trip T_R_1: departs T_02 at 08:10 but T_03 at 08:05"));
+    }
+
+    #[test]
+    fn 期限切れのカレンダーしかない駅を報告する() {
+        let root = std::env::temp_dir().join(format!("transfar-lint-expired-{}", std::process::id()));
+        write_dataset(&root);
+        let report = lint(root.to_str().unwrap(), NaiveDate::from_ymd_opt(2025, 10, 2).unwrap());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(3, report.count(Severity::Error));
+        assert!(report.problems.iter().any(|x| x.message == "calendar T_01_0001 expired on 2025-10-01"));
+        assert!(report.problems.iter().any(|x| x.message == "all versions expired; the station has no departures on 2025-10-02 or later"));
+    }
+
+    #[test]
+    fn valid_fromが前のバージョンと重なるものを報告する() {
+        let errors = lint_with("valid-from", |root| {
+            write(root, "timetable/T/01.json", r#"{"station_id": "T_01", "versions": [
+                {"calendar_id": "T_01_0001", "valid_until": "2025/10/01"},
+                {"calendar_id": "T_01_0001", "valid_from": "2025/09/01", "valid_until": "2025/12/31"}]}"#);
+            write(root, "timetable/T/02.json", r#"{"station_id": "T_02", "versions": [
+                {"calendar_id": "T_02_0001", "valid_from": "2025/10/02", "valid_until": "2025/10/01"}]}"#);
+        });
+        assert_eq!(
+            vec![
+                "error: /timetable/T/01.json: calendar T_01_0001: valid_from 2025-09-01 overlaps the previous version (until 2025-10-01)".to_string(),
+                "error: /timetable/T/02.json: calendar T_02_0001: valid_from 2025-10-02 is after valid_until 2025-10-01".to_string(),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn 同梱のデータにエラーがない() {
+        let report = lint("../data", NaiveDate::from_ymd_opt(2025, 4, 25).unwrap());
+        let errors: Vec<String> = report.problems.iter().filter(|x| x.severity == Severity::Error).map(|x| x.to_string()).collect();
+        assert_eq!(Vec::<String>::new(), errors);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
+use calc::dataset::list_ids;
+use models::{
+    calendar::RawCalendar, departure::RawDeparturePattern, ride::RawRide, station::RawStation,
+    timetable::RawTimetable, transfar_rule::{RawTransfarRules, TRANSFAR_RULES_FILE},
+};
+use serde::de::DeserializeOwned;
+
+use crate::report::Report;
+
+/// Rawの型で読み込んだファイル
+#[derive(Debug, Clone)]
+pub struct RawFile<T> {
+    pub path: String,
+    pub value: T,
+}
+
+/// データのルート以下のファイルを、変換せずにそのまま読み込んだもの。キーはファイルのパスから決まるID
+#[derive(Debug, Default)]
+pub struct RawData {
+    pub stations: BTreeMap<String, RawFile<RawStation>>,
+    pub rides: BTreeMap<String, RawFile<RawRide>>,
+    pub timetables: BTreeMap<String, RawFile<RawTimetable>>,
+    pub calendars: BTreeMap<String, RawFile<RawCalendar>>,
+    pub patterns: BTreeMap<String, RawFile<RawDeparturePattern>>,
+    /// ファイルがなければNone
+    pub transfar_rules: Option<RawFile<RawTransfarRules>>,
+}
+
+impl RawData {
+    /// 読み込めなかったファイルはreportに追加して読み飛ばす
+    pub fn load(data_root_path: &str, report: &mut Report) -> Self {
+        Self {
+            stations: load_type(data_root_path, "station", report),
+            rides: load_type(data_root_path, "ride", report),
+            timetables: load_type(data_root_path, "timetable", report),
+            calendars: load_type(data_root_path, "calendar", report),
+            patterns: load_type(data_root_path, "departure-pattern", report),
+            transfar_rules: load_transfar_rules(data_root_path, report),
+        }
+    }
+}
+
+fn load_transfar_rules(data_root_path: &str, report: &mut Report) -> Option<RawFile<RawTransfarRules>> {
+    let path = Path::new(data_root_path).join(TRANSFAR_RULES_FILE);
+    if !path.exists() {
+        return None;
+    }
+    let path = path.display().to_string();
+    match parse(&path) {
+        Ok(value) => Some(RawFile { path, value }),
+        Err(e) => {
+            report.error(path, format!("failed to parse: {}", e));
+            None
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let f = File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader::<_, T>(BufReader::new(f)).map_err(|e| e.to_string())
+}
+
+/// ID::build_pathと同じ規則でファイルのパスを作る
+pub fn path_of(data_root_path: &str, data_type_path: &str, raw_id: &str) -> String {
+    let mut path = Path::new(data_root_path).join(data_type_path);
+    path.extend(raw_id.split('_'));
+    path.set_extension("json");
+    path.display().to_string()
+}
+
+fn load_type<T: DeserializeOwned>(
+    data_root_path: &str,
+    data_type_path: &str,
+    report: &mut Report,
+) -> BTreeMap<String, RawFile<T>> {
+    let mut errors: Vec<String> = Vec::new();
+    let raw_ids = list_ids(data_root_path, data_type_path, &mut errors);
+    for error in errors {
+        report.error(Path::new(data_root_path).join(data_type_path).display().to_string(), error);
+    }
+
+    let mut res: BTreeMap<String, RawFile<T>> = BTreeMap::new();
+    for raw_id in raw_ids {
+        let path = path_of(data_root_path, data_type_path, &raw_id);
+        match parse::<T>(&path) {
+            Ok(value) => {
+                res.insert(raw_id, RawFile { path, value });
+            }
+            Err(e) => report.error(path, format!("failed to parse: {}", e)),
+        }
+    }
+    res
+}
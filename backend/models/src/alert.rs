@@ -0,0 +1,131 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// 運行情報の種類。JSONでは`"NO_SERVICE"`のように書く
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlertEffect {
+    /// 対象の便は運休、駅は閉鎖。発車と乗り換えから除く
+    NoService,
+    /// お知らせ。対象の発車と乗り換えに文面をつける
+    #[default]
+    Notice,
+}
+
+/// 運行情報の対象。指定したものがすべて一致する発車に当てはまる
+/// IDはどれもデータのID (`HAKODATEBUS_050004`など)
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct AlertTarget {
+    /// 発車する駅
+    pub station_id: Option<String>,
+    /// 系統。GTFSから取り込んだ系統の`-n`がついたものにも一致する
+    pub ride_id: Option<String>,
+    pub trip_id: Option<String>,
+}
+
+impl AlertTarget {
+    pub fn is_empty(&self) -> bool {
+        self.station_id.is_none() && self.ride_id.is_none() && self.trip_id.is_none()
+    }
+
+    pub fn matches(&self, station_id: &str, ride_id: &str, trip_id: &str) -> bool {
+        self.station_id.as_ref().is_none_or(|x| x == station_id)
+            && self.ride_id.as_ref().is_none_or(|x| is_same_route(x, ride_id))
+            && self.trip_id.as_ref().is_none_or(|x| x == trip_id)
+    }
+}
+
+/// GTFSのroute_idから取り込んだ系統は、停車順ごとに`<route_id>-<n>`に分かれる
+fn is_same_route(target: &str, ride_id: &str) -> bool {
+    target == ride_id
+        || ride_id
+            .strip_prefix(target)
+            .and_then(|x| x.strip_prefix('-'))
+            .is_some_and(|x| !x.is_empty() && x.chars().all(|y| y.is_ascii_digit()))
+}
+
+/// 運休や閉鎖、お知らせなどの運行情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub alert_id: String,
+    pub effect: AlertEffect,
+    /// どれか1つに一致すれば当てはまる
+    pub targets: Vec<AlertTarget>,
+    /// 運行日の範囲 (両端を含む)。Noneなら制限しない
+    pub from: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub header: String,
+    pub description: Option<String>,
+}
+
+/// 管理用エンドポイントで受け取る運行情報。日付は`YYYY-MM-DD`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RawAlert {
+    #[serde(default)]
+    pub alert_id: Option<String>,
+    #[serde(default)]
+    pub effect: AlertEffect,
+    pub targets: Vec<AlertTarget>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub until: Option<String>,
+    pub header: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl Alert {
+    /// 対象がない、日付が読めないなどの場合はエラーにする。alert_idがなければdefault_idを使う
+    pub fn from_raw(raw: RawAlert, default_id: &str) -> Result<Self, String> {
+        if raw.targets.is_empty() || raw.targets.iter().any(|x| x.is_empty()) {
+            return Err("each alert target must have `station_id`, `ride_id` or `trip_id`.".to_string());
+        }
+        if raw.header.trim().is_empty() {
+            return Err("`header` must not be empty.".to_string());
+        }
+        let parse = |key: &str, raw: Option<String>| {
+            raw.map(|x| {
+                NaiveDate::parse_from_str(&x, "%Y-%m-%d")
+                    .map_err(|_| format!("`{}` must be formatted as YYYY-MM-DD but got `{}`.", key, x))
+            })
+            .transpose()
+        };
+        let from = parse("from", raw.from)?;
+        let until = parse("until", raw.until)?;
+        if let (Some(from), Some(until)) = (from, until) && from > until {
+            return Err("`from` must not be after `until`.".to_string());
+        }
+
+        Ok(Self {
+            alert_id: raw.alert_id.filter(|x| !x.is_empty()).unwrap_or(default_id.to_string()),
+            effect: raw.effect,
+            targets: raw.targets,
+            from,
+            until,
+            header: raw.header,
+            description: raw.description,
+        })
+    }
+
+    pub fn to_raw(&self) -> RawAlert {
+        RawAlert {
+            alert_id: Some(self.alert_id.clone()),
+            effect: self.effect,
+            targets: self.targets.clone(),
+            from: self.from.map(|x| x.format("%Y-%m-%d").to_string()),
+            until: self.until.map(|x| x.format("%Y-%m-%d").to_string()),
+            header: self.header.clone(),
+            description: self.description.clone(),
+        }
+    }
+
+    pub fn is_active_on(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|x| x <= date) && self.until.is_none_or(|x| date <= x)
+    }
+
+    /// service_dateの運行日のtrip_idの便 (ride_idの系統) がstation_idを出る発車に当てはまればtrue
+    pub fn applies_to(&self, station_id: &str, ride_id: &str, trip_id: &str, service_date: NaiveDate) -> bool {
+        self.is_active_on(service_date) && self.targets.iter().any(|x| x.matches(station_id, ride_id, trip_id))
+    }
+}
@@ -1,7 +1,8 @@
 use std::{error::Error, fs::File, io::BufReader};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use chrono::{Datelike, NaiveDate, Weekday};
+use crate::holiday::is_holiday;
 use crate::id::{CalendarId, DeparturePatternId, ID};
 
 // fixme: 曜日とかexceptionのpattern_idの型をstringじゃなくてちゃんとしたwrapperにしたい。
@@ -16,34 +17,58 @@ pub struct Calendar {
     pub friday: DeparturePatternId,
     pub saturday: DeparturePatternId,
     pub sunday: DeparturePatternId,
-    pub exception: Vec<CalendarException>
+    /// 祝日 (振替休日、国民の休日を含む) のパターン。Noneなら祝日も曜日のパターンを使う
+    pub holiday: Option<DeparturePatternId>,
+    /// 毎年決まった期間のダイヤ (年末年始など)
+    pub annual: Vec<AnnualPeriod>,
+    pub exception: Vec<CalendarException>,
+    /// 期間を指定した例外 (夏季のみ運行など)
+    pub range_exception: Vec<RangeException>,
+    /// 毎月n回目の曜日の例外 (第2日曜日など)
+    pub recurring_exception: Vec<RecurringException>,
 }
 
 impl Calendar {
     #[allow(dead_code)]
-    pub(crate) fn from_raw(raw: RawCalendar) -> Result<Self, chrono::ParseError> {
+    pub(crate) fn from_raw(raw: RawCalendar, data_root_path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
         let exceptions: Result<Vec<CalendarException>, chrono::ParseError> = raw.exception
                 .iter()
-                .map(|x| CalendarException::from_raw(x))
+                .map(|x| CalendarException::from_raw(x, data_root_path))
                 .collect();
+        let annual = raw.annual
+                .iter()
+                .map(AnnualPeriod::from_raw)
+                .collect::<Result<Vec<AnnualPeriod>, chrono::ParseError>>()?;
+        let range_exception = raw.range_exception
+                .iter()
+                .map(|x| RangeException::from_raw(x, data_root_path))
+                .collect::<Result<Vec<RangeException>, _>>()?;
+        let recurring_exception = raw.recurring_exception
+                .iter()
+                .map(|x| RecurringException::from_raw(x, data_root_path))
+                .collect::<Result<Vec<RecurringException>, _>>()?;
         
         match exceptions {
             Ok(exception) => {
                 let res = Self {
                     calendar_id: raw.calendar_id,
-                    monday: DeparturePatternId::new(raw.monday),
-                    tuesday: DeparturePatternId::new(raw.tuesday),
-                    wednesday: DeparturePatternId::new(raw.wednesday),
-                    thursday: DeparturePatternId::new(raw.thursday),
-                    friday: DeparturePatternId::new(raw.friday),
-                    saturday: DeparturePatternId::new(raw.saturday),
-                    sunday: DeparturePatternId::new(raw.sunday),
-                    exception: exception
+                    monday: DeparturePatternId::new(raw.monday, data_root_path),
+                    tuesday: DeparturePatternId::new(raw.tuesday, data_root_path),
+                    wednesday: DeparturePatternId::new(raw.wednesday, data_root_path),
+                    thursday: DeparturePatternId::new(raw.thursday, data_root_path),
+                    friday: DeparturePatternId::new(raw.friday, data_root_path),
+                    saturday: DeparturePatternId::new(raw.saturday, data_root_path),
+                    sunday: DeparturePatternId::new(raw.sunday, data_root_path),
+                    holiday: raw.holiday.map(|x| DeparturePatternId::new(x, data_root_path)),
+                    annual,
+                    exception,
+                    range_exception,
+                    recurring_exception,
                 };
                 Ok(res)
             }
             Err(e) => {
-                Err(e)
+                Err(e.into())
             }
         }
     }
@@ -55,17 +80,66 @@ impl Calendar {
         let reader = BufReader::new(f);
         let raw: RawCalendar = serde_json::from_reader(reader)?;
 
-        Ok(Self::from_raw(raw)?)
+        Self::from_raw(raw, &calendar_id.get_root_path())
     }
 
     #[allow(dead_code)]
     pub fn get_pattern_id(&self, date: NaiveDate) -> DeparturePatternId {
-        let exception = self.exception.iter().find(|x| x.date == date);
-        if exception.is_some() {
-            return exception.unwrap().pattern_id.clone()
+        self.resolve_pattern(date).0
+    }
+
+    /// dateの発車パターンと、それがどの規則で決まったかを返す
+    /// 日付を書いたもの (exception、range_exception) を繰り返しのもの (annual、recurring_exception、祝日、曜日) より優先する
+    /// 同じ種類の中では先に書いたものを使う
+    pub fn resolve_pattern(&self, date: NaiveDate) -> (DeparturePatternId, PatternSource) {
+        if let Some(exception) = self.exception.iter().find(|x| x.date == date) {
+            return (exception.pattern_id.clone(), PatternSource::Exception)
         }
-        
-        let weekday = date.weekday();
+        if let Some(range) = self.range_exception.iter().find(|x| x.contains(date)) {
+            return (range.pattern_id.clone(), PatternSource::Range)
+        }
+        if let Some(period) = self.annual.iter().find(|x| x.contains(date)) {
+            return (self.day_kind_pattern_id(period.same_as), PatternSource::Annual)
+        }
+        if let Some(recurring) = self.recurring_exception.iter().find(|x| x.matches(date)) {
+            return (recurring.pattern_id.clone(), PatternSource::Recurring)
+        }
+        if let Some(holiday) = &self.holiday && is_holiday(date) {
+            return (holiday.clone(), PatternSource::Holiday)
+        }
+
+        (self.weekday_pattern_id(date.weekday()), PatternSource::Weekday)
+    }
+
+    /// 使う可能性のある発車パターンのIDをすべて返す
+    pub fn pattern_ids(&self) -> Vec<&DeparturePatternId> {
+        [
+            &self.monday, &self.tuesday, &self.wednesday, &self.thursday,
+            &self.friday, &self.saturday, &self.sunday,
+        ]
+        .into_iter()
+        .chain(self.holiday.iter())
+        .chain(self.exception.iter().map(|x| &x.pattern_id))
+        .chain(self.range_exception.iter().map(|x| &x.pattern_id))
+        .chain(self.recurring_exception.iter().map(|x| &x.pattern_id))
+        .collect()
+    }
+
+    /// 祝日のパターンがなければ日曜日のパターンを使う
+    fn day_kind_pattern_id(&self, kind: DayKind) -> DeparturePatternId {
+        match kind {
+            DayKind::Holiday => self.holiday.clone().unwrap_or(self.sunday.clone()),
+            DayKind::Monday => self.monday.clone(),
+            DayKind::Tuesday => self.tuesday.clone(),
+            DayKind::Wednesday => self.wednesday.clone(),
+            DayKind::Thursday => self.thursday.clone(),
+            DayKind::Friday => self.friday.clone(),
+            DayKind::Saturday => self.saturday.clone(),
+            DayKind::Sunday => self.sunday.clone(),
+        }
+    }
+
+    fn weekday_pattern_id(&self, weekday: Weekday) -> DeparturePatternId {
         match weekday {
             Weekday::Mon => self.monday.clone(),
             Weekday::Tue => self.tuesday.clone(),
@@ -78,8 +152,110 @@ impl Calendar {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-pub(crate) struct RawCalendar {
+/// 発車パターンを決めた規則。JSONでは`"weekday"`のように小文字で書く
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternSource {
+    /// 曜日ごとの既定のパターン
+    Weekday,
+    /// exceptionに書かれた日のパターン
+    Exception,
+    /// annualに書かれた期間 (年末年始など) のパターン
+    Annual,
+    /// 祝日のパターン
+    Holiday,
+    /// range_exceptionに書かれた期間のパターン
+    Range,
+    /// recurring_exceptionに書かれた曜日のパターン
+    Recurring,
+}
+
+/// annualの期間に使うパターン。JSONでは`"sunday"`、`"holiday"`のように小文字で書く
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DayKind {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+    Holiday,
+}
+
+impl DayKind {
+    /// dateの曜日。祝日ならholiday
+    pub fn of(date: NaiveDate) -> Self {
+        if is_holiday(date) {
+            return Self::Holiday;
+        }
+        match date.weekday() {
+            Weekday::Mon => Self::Monday,
+            Weekday::Tue => Self::Tuesday,
+            Weekday::Wed => Self::Wednesday,
+            Weekday::Thu => Self::Thursday,
+            Weekday::Fri => Self::Friday,
+            Weekday::Sat => Self::Saturday,
+            Weekday::Sun => Self::Sunday,
+        }
+    }
+
+    /// holidayはNone
+    pub fn weekday(&self) -> Option<Weekday> {
+        match self {
+            Self::Monday => Some(Weekday::Mon),
+            Self::Tuesday => Some(Weekday::Tue),
+            Self::Wednesday => Some(Weekday::Wed),
+            Self::Thursday => Some(Weekday::Thu),
+            Self::Friday => Some(Weekday::Fri),
+            Self::Saturday => Some(Weekday::Sat),
+            Self::Sunday => Some(Weekday::Sun),
+            Self::Holiday => None,
+        }
+    }
+}
+
+/// 毎年決まった期間。fromよりuntilが前なら年をまたぐ (12/29から01/03など)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnnualPeriod {
+    /// (月, 日)
+    pub from: (u32, u32),
+    pub until: (u32, u32),
+    pub same_as: DayKind,
+}
+
+impl AnnualPeriod {
+    pub fn from_raw(raw: &RawAnnualPeriod) -> Result<Self, chrono::ParseError> {
+        // 2月29日も書けるように、うるう年の日付として読む
+        let parse = |x: &str| NaiveDate::parse_from_str(&format!("2000/{}", x), "%Y/%m/%d").map(|y| (y.month(), y.day()));
+        Ok(Self {
+            from: parse(&raw.from)?,
+            until: parse(&raw.until)?,
+            same_as: raw.same_as,
+        })
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let day = (date.month(), date.day());
+        if self.from <= self.until {
+            self.from <= day && day <= self.until
+        } else {
+            self.from <= day || day <= self.until
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct RawAnnualPeriod {
+    /// `MM/DD`
+    pub from: String,
+    pub until: String,
+    pub same_as: DayKind,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct RawCalendar {
     pub calendar_id: String,
     pub monday: String,
     pub tuesday: String,
@@ -88,10 +264,18 @@ pub(crate) struct RawCalendar {
     pub friday: String,
     pub saturday: String,
     pub sunday: String,
-    pub exception: Vec<RawCalendarException>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holiday: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annual: Vec<RawAnnualPeriod>,
+    pub exception: Vec<RawCalendarException>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub range_exception: Vec<RawRangeException>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recurring_exception: Vec<RawRecurringException>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct RawCalendarException {
     pub date: String,
     pub pattern_id: String
@@ -105,11 +289,93 @@ pub struct CalendarException {
 
 impl CalendarException {
     #[allow(dead_code)]
-    pub fn from_raw(raw: &RawCalendarException) -> Result<Self, chrono::ParseError> {
+    pub fn from_raw(raw: &RawCalendarException, data_root_path: &str) -> Result<Self, chrono::ParseError> {
         let date = NaiveDate::parse_from_str(&raw.date, "%Y/%m/%d")?;
         Ok(CalendarException {
             date,
-            pattern_id: DeparturePatternId::new(raw.pattern_id.clone()),
+            pattern_id: DeparturePatternId::new(raw.pattern_id.clone(), data_root_path),
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct RawRangeException {
+    pub from: String,
+    pub until: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<Vec<DayKind>>,
+    pub pattern_id: String,
+}
+
+/// fromからuntilまで (両端を含む) の例外。daysを指定すると、その曜日 (祝日は`holiday`) の日だけに使う
+#[derive(Debug, PartialEq, Clone)]
+pub struct RangeException {
+    pub from: NaiveDate,
+    pub until: NaiveDate,
+    pub days: Option<Vec<DayKind>>,
+    pub pattern_id: DeparturePatternId,
+}
+
+impl RangeException {
+    pub fn from_raw(raw: &RawRangeException, data_root_path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let from = NaiveDate::parse_from_str(&raw.from, "%Y/%m/%d")?;
+        let until = NaiveDate::parse_from_str(&raw.until, "%Y/%m/%d")?;
+        if from > until {
+            return Err(format!("range_exception: {} is after {}", raw.from, raw.until).into());
+        }
+        Ok(Self {
+            from,
+            until,
+            days: raw.days.clone(),
+            pattern_id: DeparturePatternId::new(raw.pattern_id.clone(), data_root_path),
         })
     }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.until && self.days.as_ref().is_none_or(|x| x.contains(&DayKind::of(date)))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct RawRecurringException {
+    pub weekday: DayKind,
+    pub nth: Vec<i32>,
+    pub pattern_id: String,
+}
+
+/// 毎月nth回目のweekdayの例外。nthが負なら月末から数える (-1は最後)
+/// 祝日かどうかは見ないので、weekdayに`holiday`は使えない
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecurringException {
+    pub weekday: Weekday,
+    pub nth: Vec<i32>,
+    pub pattern_id: DeparturePatternId,
+}
+
+impl RecurringException {
+    pub fn from_raw(raw: &RawRecurringException, data_root_path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let weekday = raw.weekday.weekday().ok_or("recurring_exception: weekday must not be holiday")?;
+        if raw.nth.is_empty() || raw.nth.iter().any(|x| *x == 0 || x.abs() > 5) {
+            return Err(format!("recurring_exception: nth must be 1 to 5 or -1 to -5 but got {:?}", raw.nth).into());
+        }
+        Ok(Self {
+            weekday,
+            nth: raw.nth.clone(),
+            pattern_id: DeparturePatternId::new(raw.pattern_id.clone(), data_root_path),
+        })
+    }
+
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        if date.weekday() != self.weekday {
+            return false;
+        }
+        let from_start = (date.day() as i32 - 1) / 7 + 1;
+        let days_in_month = date
+            .with_day(1)
+            .and_then(|x| x.checked_add_months(chrono::Months::new(1)))
+            .and_then(|x| x.pred_opt())
+            .map_or(31, |x| x.day() as i32);
+        let from_end = -((days_in_month - date.day() as i32) / 7 + 1);
+        self.nth.iter().any(|x| *x == from_start || *x == from_end)
+    }
 }
@@ -1,9 +1,8 @@
 use std::{error::Error, fs::File, io::BufReader};
 
-use serde::Deserialize;
-use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
 
-use crate::id::{DeparturePatternId, RideId, ID};
+use crate::{id::{DeparturePatternId, RideId, ID}, service_time::ServiceTime};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct DeparturePattern {
@@ -13,14 +12,14 @@ pub struct DeparturePattern {
 
 impl DeparturePattern {
     #[allow(dead_code)]
-    pub(crate) fn from_raw(raw: RawDeparturePattern) -> Result<Self, chrono::ParseError> {
+    pub(crate) fn from_raw(raw: RawDeparturePattern, data_root_path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
         let departures = raw.departures
             .into_iter()
-            .map(Departure::from_raw)
+            .map(|x| Departure::from_raw(x, data_root_path))
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(DeparturePattern {
-            pattern_id: DeparturePatternId::new(raw.pattern_id),
+            pattern_id: DeparturePatternId::new(raw.pattern_id, data_root_path),
             departures,
         })
     }
@@ -32,7 +31,7 @@ impl DeparturePattern {
         let reader = BufReader::new(f);
         let raw: RawDeparturePattern = serde_json::from_reader(reader)?;
         
-        Ok(DeparturePattern::from_raw(raw)?)
+        DeparturePattern::from_raw(raw, &id.get_root_path())
     }
 }
 
@@ -41,37 +40,64 @@ impl DeparturePattern {
 pub struct Departure {
     pub ride_id: RideId,
     pub trip_id: String,
-    pub time: NaiveTime,
+    /// 運行日の時刻。日付をまたぐ便は24時以降になる
+    pub time: ServiceTime,
     pub loop_count: i32,
-    pub departure_type: String,
+    pub departure_type: DepartureType,
+}
+
+/// その駅で乗れるか、降りられるか
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DepartureType {
+    /// 乗車も降車もできる
+    #[default]
+    Both,
+    /// 降車のみ (終点など)
+    Incoming,
+    /// 乗車のみ (始発など)
+    Outgoing,
+}
+
+impl DepartureType {
+    pub fn can_board(&self) -> bool {
+        *self != Self::Incoming
+    }
+
+    pub fn can_alight(&self) -> bool {
+        *self != Self::Outgoing
+    }
 }
 
 impl Departure {
-    pub(crate) fn from_raw(raw: RawDeparture) -> Result<Self, chrono::ParseError> {
-        let time = NaiveTime::parse_from_str(&raw.time, "%H:%M")?;
+    pub(crate) fn from_raw(raw: RawDeparture, data_root_path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let time = ServiceTime::parse(&raw.time)?;
         let loop_count = raw.loop_count.to_owned().unwrap_or(1);
-        let departure_type = raw.departure_type.to_owned().unwrap_or("both".to_string());
+        let departure_type = raw.departure_type.unwrap_or_default();
         Ok(Departure {
-            ride_id: RideId::new(raw.ride_id),
+            ride_id: RideId::new(raw.ride_id, data_root_path),
             trip_id: raw.trip_id,
-            time: time,
-            loop_count: loop_count,
-            departure_type: departure_type
+            time,
+            loop_count,
+            departure_type
         })
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-pub(crate) struct RawDeparturePattern {
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct RawDeparturePattern {
     pub pattern_id: String,
     pub departures: Vec<RawDeparture>
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
-pub(crate) struct RawDeparture {
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+pub struct RawDeparture {
     pub ride_id: String,
     pub trip_id: String,
+    /// HH:MM。日付をまたぐ便は`24:35`のように書く
     pub time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub loop_count: Option<i32>,
-    pub departure_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub departure_type: Option<DepartureType>,
 }
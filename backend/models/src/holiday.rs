@@ -0,0 +1,100 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+
+/// 祝日の表が正しい最初の年。これより前の日は祝日として扱わない
+pub const FIRST_SUPPORTED_YEAR: i32 = 2000;
+/// 春分の日と秋分の日の計算式が使える最後の年
+pub const LAST_SUPPORTED_YEAR: i32 = 2099;
+
+/// 国民の祝日、振替休日、国民の休日 (前後が祝日の日) ならtrue
+pub fn is_holiday(date: NaiveDate) -> bool {
+    holiday_name(date).is_some()
+}
+
+/// 祝日なら名前を返す。振替休日と国民の休日も含める
+pub fn holiday_name(date: NaiveDate) -> Option<&'static str> {
+    if let Some(name) = national_holiday(date) {
+        return Some(name);
+    }
+    // 2007年の改正の前後で、振替休日と国民の休日の決まりが違う
+    let revised = date.year() >= 2007;
+    // 振替休日: 日曜日の祝日から続く祝日の次の日。改正前は日曜日の祝日の次の日だけ
+    let mut previous = date.pred_opt()?;
+    while national_holiday(previous).is_some() {
+        if previous.weekday() == Weekday::Sun {
+            return Some("振替休日");
+        }
+        if !revised {
+            break;
+        }
+        previous = previous.pred_opt()?;
+    }
+    // 国民の休日: 前の日と次の日が祝日の日 (シルバーウィークなど)。改正前は日曜日を除く
+    if (revised || date.weekday() != Weekday::Sun)
+        && national_holiday(date.pred_opt()?).is_some()
+        && national_holiday(date.succ_opt()?).is_some() {
+        return Some("国民の休日");
+    }
+    None
+}
+
+/// 国民の祝日に関する法律で日付が決まっている祝日
+fn national_holiday(date: NaiveDate) -> Option<&'static str> {
+    let year = date.year();
+    if !(FIRST_SUPPORTED_YEAR..=LAST_SUPPORTED_YEAR).contains(&year) {
+        return None;
+    }
+    let (month, day) = (date.month(), date.day());
+    // 2019年の即位と、2020年、2021年のオリンピックの特例
+    match (year, month, day) {
+        (2019, 5, 1) => return Some("天皇の即位の日"),
+        (2019, 10, 22) => return Some("即位礼正殿の儀の行われる日"),
+        (2020, 7, 23) | (2021, 7, 22) => return Some("海の日"),
+        (2020, 7, 24) | (2021, 7, 23) => return Some("スポーツの日"),
+        (2020, 8, 10) | (2021, 8, 8) => return Some("山の日"),
+        _ => {}
+    }
+    let moved = matches!(year, 2020 | 2021);
+    let name = match month {
+        1 if day == 1 => "元日",
+        1 if is_nth_monday(date, 2) => "成人の日",
+        2 if day == 11 => "建国記念の日",
+        2 if day == 23 && year >= 2020 => "天皇誕生日",
+        3 if day == vernal_equinox_day(year) => "春分の日",
+        4 if day == 29 => if year >= 2007 { "昭和の日" } else { "みどりの日" },
+        5 if day == 3 => "憲法記念日",
+        5 if day == 4 && year >= 2007 => "みどりの日",
+        5 if day == 5 => "こどもの日",
+        7 if !moved && year >= 2003 && is_nth_monday(date, 3) => "海の日",
+        7 if year < 2003 && day == 20 => "海の日",
+        8 if !moved && year >= 2016 && day == 11 => "山の日",
+        9 if year >= 2003 && is_nth_monday(date, 3) => "敬老の日",
+        9 if year < 2003 && day == 15 => "敬老の日",
+        9 if day == autumnal_equinox_day(year) => "秋分の日",
+        10 if !moved && is_nth_monday(date, 2) => if year >= 2020 { "スポーツの日" } else { "体育の日" },
+        11 if day == 3 => "文化の日",
+        11 if day == 23 => "勤労感謝の日",
+        12 if day == 23 && year <= 2018 => "天皇誕生日",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// その月のn回目の月曜日ならtrue
+fn is_nth_monday(date: NaiveDate, n: u32) -> bool {
+    date.weekday() == Weekday::Mon && (date.day() - 1) / 7 + 1 == n
+}
+
+/// 春分の日 (3月)。国立天文台の暦要項と一致する近似式
+fn vernal_equinox_day(year: i32) -> u32 {
+    equinox_day(20.8431, year)
+}
+
+/// 秋分の日 (9月)
+fn autumnal_equinox_day(year: i32) -> u32 {
+    equinox_day(23.2488, year)
+}
+
+fn equinox_day(base: f64, year: i32) -> u32 {
+    let elapsed = (year - 1980) as f64;
+    (base + 0.242194 * elapsed - (elapsed / 4.0).floor()).floor() as u32
+}
@@ -1,10 +1,7 @@
-use crate::station::Station;
-
-pub const ROOT_PATH: &str = "/home/yourein/Codes/transfar-navi/backend/data";
-
 #[allow(dead_code)]
 pub trait ID {
-    fn new(raw_id: String) -> Self;
+    /// `data_root_path`はデータセットのルートディレクトリ (`station`や`ride`を含むディレクトリ)
+    fn new(raw_id: String, data_root_path: &str) -> Self;
     fn get_id_path_list(&self) -> Vec<String>;
     fn get_root_path(&self) -> String;
     fn get_data_type_path(&self) -> &'static str;
@@ -13,6 +10,11 @@ pub trait ID {
     fn build_path(&self) -> String {
         self.get_root_path() + "/" + self.get_data_type_path() + "/" + &self.get_id_path_list().join("/") + ".json"
     }
+
+    /// 自分と同じデータセットに属する別のIDを作る
+    fn with_same_root<T: ID>(&self, raw_id: String) -> T where Self: Sized {
+        T::new(raw_id, &self.get_root_path())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,8 +24,8 @@ pub struct DeparturePatternId {
 }
 
 impl ID for DeparturePatternId {
-    fn new(raw_id: String) -> Self {
-        DeparturePatternId { id: raw_id, data_root_path: ROOT_PATH.to_string() }
+    fn new(raw_id: String, data_root_path: &str) -> Self {
+        DeparturePatternId { id: raw_id, data_root_path: data_root_path.to_string() }
     }
 
     fn get_id_path_list(&self) -> Vec<String> {
@@ -50,8 +52,8 @@ pub struct RideId {
 }
 
 impl ID for RideId {
-    fn new(id: String) -> Self {
-        RideId { id, data_root_path: ROOT_PATH.to_string() }
+    fn new(id: String, data_root_path: &str) -> Self {
+        RideId { id, data_root_path: data_root_path.to_string() }
     }
 
     fn get_id_path_list(&self) -> Vec<String> {
@@ -78,8 +80,8 @@ pub struct CalendarId {
 }
 
 impl ID for CalendarId {
-    fn new(id: String) -> Self {
-        CalendarId { id, data_root_path: ROOT_PATH.to_string() }
+    fn new(id: String, data_root_path: &str) -> Self {
+        CalendarId { id, data_root_path: data_root_path.to_string() }
     }
 
     fn get_id_path_list(&self) -> Vec<String> {
@@ -107,7 +109,7 @@ pub struct StationId {
 
 impl StationId {
     pub fn to_timetable_id(&self) -> TimeTableId {
-        TimeTableId::new(self.get_raw_id().clone())
+        self.with_same_root(self.get_raw_id())
     }
 
     fn split_additional_info(&self) -> Vec<String> {
@@ -117,32 +119,17 @@ impl StationId {
     pub fn get_loop_count(&self) -> i32 {
         let id_data = self.split_additional_info();
         if id_data.len() >= 2 {
-            id_data[1].parse::<i32>().unwrap_or_else(|_| 1)
+            id_data[1].parse::<i32>().unwrap_or(1)
         }
         else {
             1
         }
     }
-
-    #[allow(deprecated)]
-    pub fn is_same_station(&self, other: &StationId) -> bool {
-        let actually_same = self.get_raw_id() == other.get_raw_id();
-        if actually_same {
-            // すでに同じことが確定しているならearly returnする
-            return true;
-        }
-        match Station::from_id(other.clone()) {
-            Ok(other_station) => {
-                other_station.join.iter().find(|x| x.get_raw_id() == self.get_raw_id()).is_some()
-            },
-            Err(_) => false
-        }
-    }
 }
 
 impl ID for StationId {
-    fn new(id: String) -> Self {
-        StationId { id, data_root_path: ROOT_PATH.to_string() }
+    fn new(id: String, data_root_path: &str) -> Self {
+        StationId { id, data_root_path: data_root_path.to_string() }
     }
 
     fn get_id_path_list(&self) -> Vec<String> {
@@ -169,8 +156,8 @@ pub struct TimeTableId {
 }
 
 impl ID for TimeTableId {
-    fn new(id: String) -> Self {
-        TimeTableId { id, data_root_path: ROOT_PATH.to_string() }
+    fn new(id: String, data_root_path: &str) -> Self {
+        TimeTableId { id, data_root_path: data_root_path.to_string() }
     }
 
     fn get_id_path_list(&self) -> Vec<String> {
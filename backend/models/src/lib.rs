@@ -4,25 +4,35 @@ pub mod calendar;
 pub mod id;
 pub mod timetable;
 pub mod transfar;
+pub mod transfar_rule;
 pub mod response;
 pub mod ride;
+pub mod service_time;
+pub mod alert;
+pub mod holiday;
 
 #[cfg(test)]
 #[allow(non_snake_case)]
 mod tests {
-    use crate::id::{CalendarId, DeparturePatternId, RideId, StationId, TimeTableId, ID, ROOT_PATH};
-    use crate::ride::{RawRide, Ride};
-    use crate::station::{RawStation, Station};
-    use crate::departure::{Departure, DeparturePattern, RawDeparture, RawDeparturePattern};
-    use crate::calendar::{Calendar, CalendarException, RawCalendar, RawCalendarException};
+    use crate::alert::{Alert, AlertEffect, AlertTarget, RawAlert};
+    use crate::id::{CalendarId, DeparturePatternId, RideId, StationId, TimeTableId, ID};
+    use crate::ride::{CareerType, RawRide, Ride};
+    use crate::service_time::ServiceTime;
+    use crate::station::{distance_meters, RawStation, Station};
+    use crate::departure::{Departure, DeparturePattern, DepartureType, RawDeparture, RawDeparturePattern};
+    use crate::calendar::{Calendar, CalendarException, PatternSource, RawCalendar, RawCalendarException};
+    use crate::holiday::{holiday_name, is_holiday};
     use crate::timetable::{CalendarVersion, RawTimetable, TimeTable};
+    use crate::transfar_rule::{RawTransfarRules, TransfarKind, TransfarLimit, TransfarRules};
     use chrono::{NaiveDate, NaiveTime};
     use std::fs::File;
     use std::io::BufReader;
 
+    const TEST_ROOT_PATH: &str = "/hoge/fuga";
+
     #[test]
     fn parse_raw_station() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/station/test/test_station.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/station/test/test_station.json")).unwrap();
         let reader = BufReader::new(f);
 
         let actual: RawStation = serde_json::from_reader(reader).unwrap();
@@ -32,7 +42,9 @@ mod tests {
             pronounce: "てすと".to_string(),
             join: [
                 "test_station_02".to_string()
-            ].to_vec()
+            ].to_vec(),
+            lat: None,
+            lon: None,
         };
         assert_eq!(actual, expected);
     }
@@ -41,24 +53,34 @@ mod tests {
     #[allow(deprecated)]
     #[test]
     fn parse_station() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/station/test/test_station.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/station/test/test_station.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawStation = serde_json::from_reader(reader).unwrap();
-        let actual = Station::from_raw(raw);
+        let actual = Station::from_raw(raw, TEST_ROOT_PATH);
         let expected = Station {
-            station_id: StationId::new("test_station".to_string()),
+            station_id: StationId::new("test_station".to_string(), TEST_ROOT_PATH),
             name: "TestStation".to_string(),
             pronounce: "てすと".to_string(),
             join: [
-                StationId::new("test_station_02".to_string())
-            ].to_vec()
+                StationId::new("test_station_02".to_string(), TEST_ROOT_PATH)
+            ].to_vec(),
+            lat: None,
+            lon: None,
         };
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn 駅の間の距離をメートルで計算できる() {
+        // 緯度1/1000度は約111m
+        let actual = distance_meters((41.770, 140.720), (41.771, 140.720));
+        assert!((actual - 111.2).abs() < 0.1, "{}", actual);
+        assert_eq!(0.0, distance_meters((41.770, 140.720), (41.770, 140.720)));
+    }
+
     #[test]
     fn parse_raw_departure_pattern() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/departure-pattern/0001.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/departure-pattern/0001.json")).unwrap();
         let reader = BufReader::new(f);
 
         let actual: RawDeparturePattern = serde_json::from_reader(reader).unwrap();
@@ -70,7 +92,7 @@ mod tests {
                     trip_id: "trip_id".to_string(),
                     time: "00:00".to_string(),
                     loop_count: None,
-                    departure_type: Some("both".to_string())
+                    departure_type: None
                 }
             ].to_vec()
         };
@@ -80,14 +102,14 @@ mod tests {
 
     #[test]
     fn parse_exception_date() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/exception_date.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/exception_date.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawCalendarException = serde_json::from_reader(reader).unwrap();
-        let actual = CalendarException::from_raw(&raw).unwrap();
+        let actual = CalendarException::from_raw(&raw, TEST_ROOT_PATH).unwrap();
 
         let expected = CalendarException {
-            date: NaiveDate::from_ymd_opt(2025, 04, 22).expect("Valid Date"),
-            pattern_id: DeparturePatternId::new("1".to_string())
+            date: NaiveDate::from_ymd_opt(2025, 4, 22).expect("Valid Date"),
+            pattern_id: DeparturePatternId::new("1".to_string(), TEST_ROOT_PATH)
         };
 
         assert_eq!(expected, actual)
@@ -95,24 +117,28 @@ mod tests {
 
     #[test]
     fn parse_calendar() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/test_calendar.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/test_calendar.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawCalendar = serde_json::from_reader(reader).unwrap();
-        let actual = Calendar::from_raw(raw).unwrap();
+        let actual = Calendar::from_raw(raw, TEST_ROOT_PATH).unwrap();
 
         let expected = Calendar {
             calendar_id: "test_calendar".to_string(),
-            monday: DeparturePatternId::new("0001".to_string()),
-            tuesday: DeparturePatternId::new("0002".to_string()),
-            wednesday: DeparturePatternId::new("0003".to_string()),
-            thursday: DeparturePatternId::new("0004".to_string()),
-            friday: DeparturePatternId::new("0005".to_string()),
-            saturday: DeparturePatternId::new("0006".to_string()),
-            sunday: DeparturePatternId::new("0007".to_string()),
+            monday: DeparturePatternId::new("0001".to_string(), TEST_ROOT_PATH),
+            tuesday: DeparturePatternId::new("0002".to_string(), TEST_ROOT_PATH),
+            wednesday: DeparturePatternId::new("0003".to_string(), TEST_ROOT_PATH),
+            thursday: DeparturePatternId::new("0004".to_string(), TEST_ROOT_PATH),
+            friday: DeparturePatternId::new("0005".to_string(), TEST_ROOT_PATH),
+            saturday: DeparturePatternId::new("0006".to_string(), TEST_ROOT_PATH),
+            sunday: DeparturePatternId::new("0007".to_string(), TEST_ROOT_PATH),
+            holiday: None,
+            annual: vec![],
+            range_exception: vec![],
+            recurring_exception: vec![],
             exception: [
                 CalendarException {
-                    date: NaiveDate::from_ymd_opt(2025, 04, 22).unwrap(),
-                    pattern_id: DeparturePatternId::new("0008".to_string()),
+                    date: NaiveDate::from_ymd_opt(2025, 4, 22).unwrap(),
+                    pattern_id: DeparturePatternId::new("0008".to_string(), TEST_ROOT_PATH),
                 }
             ].to_vec()
 
@@ -125,48 +151,170 @@ mod tests {
 
     #[test]
     fn can_get_right_pattern_id() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/test_calendar.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/test_calendar.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawCalendar = serde_json::from_reader(reader).unwrap();
-        let calendar = Calendar::from_raw(raw).unwrap();
+        let calendar = Calendar::from_raw(raw, TEST_ROOT_PATH).unwrap();
 
-        let date1 = NaiveDate::from_ymd_opt(2025, 04, 22).expect("Valid Date");
-        let expected1 = DeparturePatternId::new("0008".to_string());
+        let date1 = NaiveDate::from_ymd_opt(2025, 4, 22).expect("Valid Date");
+        let expected1 = DeparturePatternId::new("0008".to_string(), TEST_ROOT_PATH);
         let actual1 = calendar.get_pattern_id(date1);
         assert_eq!(expected1, actual1);
 
-        let date2 = NaiveDate::from_ymd_opt(2025, 04, 23).expect("Valid"); // 2025/04/23 is Wednesday
-        let expected2 = DeparturePatternId::new("0003".to_string());
+        let date2 = NaiveDate::from_ymd_opt(2025, 4, 23).expect("Valid"); // 2025/04/23 is Wednesday
+        let expected2 = DeparturePatternId::new("0003".to_string(), TEST_ROOT_PATH);
         let actual2 = calendar.get_pattern_id(date2);
         assert_eq!(expected2, actual2);
     }
 
+    #[test]
+    fn 発車パターンがどの規則で決まったかわかる() {
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/test_calendar.json")).unwrap();
+        let reader = BufReader::new(f);
+        let raw: RawCalendar = serde_json::from_reader(reader).unwrap();
+        let calendar = Calendar::from_raw(raw, TEST_ROOT_PATH).unwrap();
+
+        let exception = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        assert_eq!(PatternSource::Exception, calendar.resolve_pattern(exception).1);
+        let wednesday = NaiveDate::from_ymd_opt(2025, 4, 23).unwrap();
+        assert_eq!(PatternSource::Weekday, calendar.resolve_pattern(wednesday).1);
+        assert_eq!("\"exception\"", serde_json::to_string(&PatternSource::Exception).unwrap());
+    }
+
+    #[test]
+    fn 振替休日と国民の休日を含む祝日がわかる() {
+        let date = |y: i32, m: u32, d: u32| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let holidays_2025: Vec<NaiveDate> = date(2025, 1, 1).iter_days().take_while(|x| *x < date(2026, 1, 1)).filter(|x| is_holiday(*x)).collect();
+        let expected = [
+            (1, 1), (1, 13), (2, 11), (2, 23), (2, 24), (3, 20), (4, 29), (5, 3), (5, 4), (5, 5), (5, 6),
+            (7, 21), (8, 11), (9, 15), (9, 23), (10, 13), (11, 3), (11, 23), (11, 24),
+        ];
+        assert_eq!(expected.iter().map(|(m, d)| date(2025, *m, *d)).collect::<Vec<_>>(), holidays_2025);
+        assert_eq!(Some("振替休日"), holiday_name(date(2025, 5, 6)));
+
+        // シルバーウィーク
+        assert_eq!(Some("敬老の日"), holiday_name(date(2026, 9, 21)));
+        assert_eq!(Some("国民の休日"), holiday_name(date(2026, 9, 22)));
+        assert_eq!(Some("秋分の日"), holiday_name(date(2026, 9, 23)));
+        // 2019年の即位と2020年のオリンピックの特例
+        assert!(is_holiday(date(2019, 4, 30)) && is_holiday(date(2019, 5, 1)) && is_holiday(date(2019, 5, 2)));
+        assert!(!is_holiday(date(2019, 12, 23)));
+        assert_eq!(Some("スポーツの日"), holiday_name(date(2020, 7, 24)));
+        assert!(!is_holiday(date(2020, 10, 12)));
+        // 2007年の改正前は、振替休日は日曜日の祝日の次の日だけで、日曜日は国民の休日にならない
+        assert_eq!(Some("振替休日"), holiday_name(date(2006, 1, 2)));
+        assert_eq!(Some("国民の休日"), holiday_name(date(2006, 5, 4)));
+        assert!(!is_holiday(date(2003, 5, 4)));
+        assert!(!is_holiday(date(2003, 5, 6)));
+    }
+
+    #[test]
+    fn 年末年始と祝日のパターンをexceptionの次に優先する() {
+        let raw: RawCalendar = serde_json::from_str(r#"{
+            "calendar_id": "test_calendar",
+            "monday": "0001", "tuesday": "0001", "wednesday": "0001", "thursday": "0001", "friday": "0001",
+            "saturday": "0002", "sunday": "0003", "holiday": "0004",
+            "annual": [{"from": "12/29", "until": "01/03", "same_as": "sunday"}, {"from": "08/13", "until": "08/15", "same_as": "holiday"}],
+            "exception": [{"date": "2025/12/31", "pattern_id": "0005"}]
+        }"#).unwrap();
+        let calendar = Calendar::from_raw(raw, TEST_ROOT_PATH).unwrap();
+        let resolve = |m: u32, d: u32| {
+            let (pattern_id, source) = calendar.resolve_pattern(NaiveDate::from_ymd_opt(if m == 1 { 2026 } else { 2025 }, m, d).unwrap());
+            (pattern_id.get_raw_id(), source)
+        };
+        assert_eq!(("0003".to_string(), PatternSource::Annual), resolve(12, 29));
+        assert_eq!(("0005".to_string(), PatternSource::Exception), resolve(12, 31));
+        // 元日も年末年始の期間を優先する
+        assert_eq!(("0003".to_string(), PatternSource::Annual), resolve(1, 1));
+        assert_eq!(("0001".to_string(), PatternSource::Weekday), resolve(1, 5));
+        assert_eq!(("0004".to_string(), PatternSource::Annual), resolve(8, 13));
+        assert_eq!(("0004".to_string(), PatternSource::Holiday), resolve(11, 24));
+        assert_eq!("\"holiday\"", serde_json::to_string(&PatternSource::Holiday).unwrap());
+
+        let invalid: RawCalendar = serde_json::from_str(r#"{
+            "calendar_id": "test_calendar",
+            "monday": "0001", "tuesday": "0001", "wednesday": "0001", "thursday": "0001", "friday": "0001",
+            "saturday": "0002", "sunday": "0003",
+            "annual": [{"from": "12/32", "until": "01/03", "same_as": "sunday"}],
+            "exception": []
+        }"#).unwrap();
+        assert!(Calendar::from_raw(invalid, TEST_ROOT_PATH).is_err());
+    }
+
+    #[test]
+    fn 期間と毎月n回目の曜日の例外を使う() {
+        let raw: RawCalendar = serde_json::from_str(r#"{
+            "calendar_id": "test_calendar",
+            "monday": "0001", "tuesday": "0001", "wednesday": "0001", "thursday": "0001", "friday": "0001",
+            "saturday": "0002", "sunday": "0003", "holiday": "0004",
+            "annual": [{"from": "08/13", "until": "08/15", "same_as": "sunday"}],
+            "exception": [{"date": "2025/08/02", "pattern_id": "0005"}],
+            "range_exception": [{"from": "2025/07/19", "until": "2025/08/31", "days": ["saturday", "sunday", "holiday"], "pattern_id": "0006"}],
+            "recurring_exception": [{"weekday": "sunday", "nth": [2, -1], "pattern_id": "0007"}]
+        }"#).unwrap();
+        let calendar = Calendar::from_raw(raw, TEST_ROOT_PATH).unwrap();
+        let resolve = |m: u32, d: u32| {
+            let (pattern_id, source) = calendar.resolve_pattern(NaiveDate::from_ymd_opt(2025, m, d).unwrap());
+            (pattern_id.get_raw_id(), source)
+        };
+        // 期間内でもdaysに含まれない日は使わない
+        assert_eq!(("0006".to_string(), PatternSource::Range), resolve(7, 19));
+        assert_eq!(("0001".to_string(), PatternSource::Weekday), resolve(7, 22));
+        // 海の日 (2025/07/21) はholidayとして扱う
+        assert_eq!(("0006".to_string(), PatternSource::Range), resolve(7, 21));
+        // 日付を書いたものは、期間や繰り返しより優先する
+        assert_eq!(("0005".to_string(), PatternSource::Exception), resolve(8, 2));
+        assert_eq!(("0006".to_string(), PatternSource::Range), resolve(8, 10));
+        assert_eq!(("0006".to_string(), PatternSource::Range), resolve(8, 31));
+        // 第2日曜日と最後の日曜日
+        assert_eq!(("0007".to_string(), PatternSource::Recurring), resolve(9, 14));
+        assert_eq!(("0007".to_string(), PatternSource::Recurring), resolve(9, 28));
+        assert_eq!(("0003".to_string(), PatternSource::Weekday), resolve(9, 21));
+        // 毎年の期間は繰り返しより優先する
+        assert_eq!(("0003".to_string(), PatternSource::Annual), resolve(8, 13));
+        assert_eq!(11, calendar.pattern_ids().len());
+
+        let invalid = [
+            r#""range_exception": [{"from": "2025/09/01", "until": "2025/08/31", "pattern_id": "0006"}]"#,
+            r#""range_exception": [{"from": "2025/9/1", "until": "2025/08/31x", "pattern_id": "0006"}]"#,
+            r#""recurring_exception": [{"weekday": "holiday", "nth": [1], "pattern_id": "0007"}]"#,
+            r#""recurring_exception": [{"weekday": "monday", "nth": [6], "pattern_id": "0007"}]"#,
+            r#""recurring_exception": [{"weekday": "monday", "nth": [], "pattern_id": "0007"}]"#,
+        ];
+        for field in invalid {
+            let raw: RawCalendar = serde_json::from_str(&format!(r#"{{
+                "calendar_id": "test_calendar",
+                "monday": "0001", "tuesday": "0001", "wednesday": "0001", "thursday": "0001", "friday": "0001",
+                "saturday": "0002", "sunday": "0003", "exception": [], {}
+            }}"#, field)).unwrap();
+            assert!(Calendar::from_raw(raw, TEST_ROOT_PATH).is_err(), "{}", field);
+        }
+    }
+
     #[test]
     fn build_departure_pattern_path() {
-        let id = DeparturePatternId::new(
-            "TESTAGENCY_TEST-RIDE_0001".to_string()
-        );
+        let id = DeparturePatternId::new("TESTAGENCY_TEST-RIDE_0001".to_string(), TEST_ROOT_PATH);
 
         let actual = id.build_path();
-        let expected = ROOT_PATH.to_string() + "/departure-pattern/TESTAGENCY/TEST-RIDE/0001.json";
+        let expected = TEST_ROOT_PATH.to_string() + "/departure-pattern/TESTAGENCY/TEST-RIDE/0001.json";
         assert_eq!(expected, actual);
     }
 
     #[test]
     fn parse_departure_pattern() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/departure-pattern/0001.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/departure-pattern/0001.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawDeparturePattern = serde_json::from_reader(reader).unwrap();
-        let actual = DeparturePattern::from_raw(raw).unwrap();
+        let actual = DeparturePattern::from_raw(raw, TEST_ROOT_PATH).unwrap();
         let expected = DeparturePattern {
-            pattern_id: DeparturePatternId::new("test".to_string()),
+            pattern_id: DeparturePatternId::new("test".to_string(), TEST_ROOT_PATH),
             departures: [
                 Departure {
-                    ride_id: RideId::new("ride_id".to_string()),
+                    ride_id: RideId::new("ride_id".to_string(), TEST_ROOT_PATH),
                     trip_id: "trip_id".to_string(),
-                    time: NaiveTime::from_hms_opt(00, 00, 00).expect("Valid Time"),
+                    time: ServiceTime::from_hm(0, 0),
                     loop_count: 1,
-                    departure_type: "Both".to_string()
+                    departure_type: DepartureType::Both
                 }
             ].to_vec()
         };
@@ -176,25 +324,28 @@ mod tests {
 
     #[test]
     fn parse_time_table() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/timetable/test-timetable.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/timetable/test-timetable.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawTimetable = serde_json::from_reader(reader).unwrap();
-        let actual = TimeTable::from_raw(raw).unwrap();
+        let actual = TimeTable::from_raw(raw, TEST_ROOT_PATH).unwrap();
 
         let expected = TimeTable {
             station_id: "test_station".to_string(),
             versions: [
                 CalendarVersion {
-                    calendar_id: CalendarId::new("test_calendar_1".to_string()),
-                    valid_until: NaiveDate::from_ymd_opt(2025, 04, 01).expect("valid date")
+                    calendar_id: CalendarId::new("test_calendar_1".to_string(), TEST_ROOT_PATH),
+                    valid_from: None,
+                    valid_until: NaiveDate::from_ymd_opt(2025, 4, 1).expect("valid date")
                 },
                 CalendarVersion {
-                    calendar_id: CalendarId::new("test_calendar_2".to_string()),
-                    valid_until: NaiveDate::from_ymd_opt(2025, 04, 20).expect("valid date")
+                    calendar_id: CalendarId::new("test_calendar_2".to_string(), TEST_ROOT_PATH),
+                    valid_from: None,
+                    valid_until: NaiveDate::from_ymd_opt(2025, 4, 20).expect("valid date")
                 },
                 CalendarVersion {
-                    calendar_id: CalendarId::new("test_calendar_3".to_string()),
-                    valid_until: NaiveDate::from_ymd_opt(2025, 04, 25).expect("valid date")
+                    calendar_id: CalendarId::new("test_calendar_3".to_string(), TEST_ROOT_PATH),
+                    valid_from: None,
+                    valid_until: NaiveDate::from_ymd_opt(2025, 4, 25).expect("valid date")
                 }
             ].to_vec()
         };
@@ -204,35 +355,90 @@ mod tests {
 
     #[test]
     fn can_get_first_valid_date() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/timetable/test-timetable.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/timetable/test-timetable.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawTimetable = serde_json::from_reader(reader).unwrap();
-        let timetable = TimeTable::from_raw(raw).unwrap();
+        let timetable = TimeTable::from_raw(raw, TEST_ROOT_PATH).unwrap();
 
-        let actual1 = timetable.get_valid_calendar(NaiveDate::from_ymd_opt(2025, 04, 15).expect("Valid"));
+        let actual1 = timetable.get_valid_calendar(NaiveDate::from_ymd_opt(2025, 4, 15).expect("Valid"));
         let expected1 = Some(
             CalendarVersion {
-                calendar_id: CalendarId::new("test_calendar_2".to_string()),
-                valid_until: NaiveDate::from_ymd_opt(2025, 04, 20).expect("valid date")
+                calendar_id: CalendarId::new("test_calendar_2".to_string(), TEST_ROOT_PATH),
+                valid_from: None,
+                valid_until: NaiveDate::from_ymd_opt(2025, 4, 20).expect("valid date")
             }
         );
-        let actual2 = timetable.get_valid_calendar(NaiveDate::from_ymd_opt(2025, 04, 25).expect("Valid"));
+        let actual2 = timetable.get_valid_calendar(NaiveDate::from_ymd_opt(2025, 4, 25).expect("Valid"));
         let expected2 = Some(CalendarVersion {
-            calendar_id: CalendarId::new("test_calendar_3".to_string()),
-            valid_until: NaiveDate::from_ymd_opt(2025, 04, 25).expect("valid date")
+            calendar_id: CalendarId::new("test_calendar_3".to_string(), TEST_ROOT_PATH),
+            valid_from: None,
+            valid_until: NaiveDate::from_ymd_opt(2025, 4, 25).expect("valid date")
         });
-        let actual3 = timetable.get_valid_calendar(NaiveDate::from_ymd_opt(2025, 05, 01).expect("valid"));
+        let actual3 = timetable.get_valid_calendar(NaiveDate::from_ymd_opt(2025, 5, 1).expect("valid"));
         let expected3: Option<CalendarVersion> = None;
 
         assert_eq!([actual1, actual2, actual3], [expected1, expected2, expected3]);
     }
 
+    #[test]
+    fn valid_fromで始まるバージョンを並べて次の改正を返す() {
+        let raw: RawTimetable = serde_json::from_str(r#"{
+            "station_id": "test_station",
+            "versions": [
+                {"calendar_id": "test_calendar_3", "valid_from": "2025/12/01", "valid_until": "2026/03/31"},
+                {"calendar_id": "test_calendar_1", "valid_until": "2025/09/30"},
+                {"calendar_id": "test_calendar_2", "valid_until": "2025/11/15"}
+            ]
+        }"#).unwrap();
+        let timetable = TimeTable::from_raw(raw, TEST_ROOT_PATH).unwrap();
+        let calendar_id = |date: NaiveDate| timetable.get_valid_calendar(date).map(|x| x.calendar_id.get_raw_id());
+        let date = |m: u32, d: u32| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+
+        // valid_untilの順に並べ替える
+        assert_eq!(Some("test_calendar_1".to_string()), calendar_id(date(4, 25)));
+        assert_eq!(Some("test_calendar_2".to_string()), calendar_id(date(10, 1)));
+        // valid_fromより前は有効なバージョンがない
+        assert_eq!(None, calendar_id(date(11, 20)));
+        assert_eq!(Some("test_calendar_3".to_string()), calendar_id(date(12, 1)));
+
+        let upcoming: Vec<(NaiveDate, String)> = timetable
+            .upcoming_versions(date(9, 1))
+            .into_iter()
+            .map(|(starts, version)| (starts, version.calendar_id.get_raw_id()))
+            .collect();
+        assert_eq!(vec![(date(10, 1), "test_calendar_2".to_string()), (date(12, 1), "test_calendar_3".to_string())], upcoming);
+        assert!(timetable.upcoming_versions(date(12, 1)).is_empty());
+
+        // valid_fromがないバージョンは、前のバージョンより前の日には使わない
+        let raw: RawTimetable = serde_json::from_str(r#"{
+            "station_id": "test_station",
+            "versions": [
+                {"calendar_id": "test_calendar_1", "valid_from": "2025/06/01", "valid_until": "2025/11/30"},
+                {"calendar_id": "test_calendar_2", "valid_until": "2026/03/31"}
+            ]
+        }"#).unwrap();
+        let timetable = TimeTable::from_raw(raw, TEST_ROOT_PATH).unwrap();
+        assert_eq!(None, timetable.get_valid_calendar(date(1, 1)));
+        assert_eq!(Some("test_calendar_1".to_string()), timetable.get_valid_calendar(date(6, 1)).map(|x| x.calendar_id.get_raw_id()));
+        assert_eq!(Some("test_calendar_2".to_string()), timetable.get_valid_calendar(date(12, 1)).map(|x| x.calendar_id.get_raw_id()));
+
+        let overlapping = [
+            r#"[{"calendar_id": "a", "valid_until": "2025/09/30"}, {"calendar_id": "b", "valid_from": "2025/09/30", "valid_until": "2025/12/31"}]"#,
+            r#"[{"calendar_id": "a", "valid_until": "2025/09/30"}, {"calendar_id": "b", "valid_until": "2025/09/30"}]"#,
+            r#"[{"calendar_id": "a", "valid_from": "2025/10/01", "valid_until": "2025/09/30"}]"#,
+        ];
+        for versions in overlapping {
+            let raw: RawTimetable = serde_json::from_str(&format!(r#"{{"station_id": "test_station", "versions": {}}}"#, versions)).unwrap();
+            assert!(TimeTable::from_raw(raw, TEST_ROOT_PATH).is_err(), "{}", versions);
+        }
+    }
+
     #[test]
     fn can_parse_ride() {
-        let f = File::open("/home/yourein/Codes/transfar-navi/backend/models/test_data/55G-D.json").unwrap();
+        let f = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/55G-D.json")).unwrap();
         let reader = BufReader::new(f);
         let raw: RawRide = serde_json::from_reader(reader).unwrap();
-        let actual = Ride::from_raw(raw);
+        let actual = Ride::from_raw(raw, TEST_ROOT_PATH);
 
         let expected = Ride {
             ride_type: "55G".to_string(),
@@ -240,13 +446,13 @@ mod tests {
             type_foreground: "#FFD700".to_string(),
             type_background: "#000000".to_string(),
             type_pronounce: "ごじゅうご じーけいとう".to_string(),
-            to: StationId::new("HAKODATEBUS_050019".to_string()),
-            career_type: "BUS".to_string(),
+            to: StationId::new("HAKODATEBUS_050019".to_string(), TEST_ROOT_PATH),
+            career_type: CareerType::Bus,
             route: vec![
-                StationId::new("HAKODATEBUS_050004".to_string()),
-                StationId::new("HAKODATEBUS_050005".to_string()),
-                StationId::new("HAKODATEBUS_050016".to_string()),
-                StationId::new("HAKODATEBUS_050019".to_string())
+                StationId::new("HAKODATEBUS_050004".to_string(), TEST_ROOT_PATH),
+                StationId::new("HAKODATEBUS_050005".to_string(), TEST_ROOT_PATH),
+                StationId::new("HAKODATEBUS_050016".to_string(), TEST_ROOT_PATH),
+                StationId::new("HAKODATEBUS_050019".to_string(), TEST_ROOT_PATH)
             ]
         };
 
@@ -331,13 +537,115 @@ mod tests {
         let actual_no_loop = id_no_loop.to_timetable_id();
         let expected_loop = TimeTableId {
             id: "TEST".to_string(),
-            data_root_path: "/home/yourein/Codes/transfar-navi/backend/data".to_string(),
+            data_root_path: "/hoge/fuga".to_string(),
         };
         let expected_no_loop = TimeTableId {
             id: "TEST".to_string(),
-            data_root_path: "/home/yourein/Codes/transfar-navi/backend/data".to_string(),
+            data_root_path: "/hoge/fuga".to_string(),
         };
         assert_eq!(actual_loop, expected_loop);
         assert_eq!(actual_no_loop, expected_no_loop);
     }
+
+    #[test]
+    fn career_typeとdeparture_typeはデータと同じ文字列でシリアライズされる() {
+        let career_types = [CareerType::Bus, CareerType::LtdExpTrain, CareerType::Airplane];
+        assert_eq!(r#"["BUS","LTDEXPTRAIN","AIRPLANE"]"#, serde_json::to_string(&career_types).unwrap());
+        assert_eq!("LTDEXPTRAIN", CareerType::LtdExpTrain.to_string());
+
+        let departure_types: Vec<DepartureType> = serde_json::from_str(r#"["both", "incoming", "outgoing"]"#).unwrap();
+        assert_eq!(vec![DepartureType::Both, DepartureType::Incoming, DepartureType::Outgoing], departure_types);
+        assert!(!DepartureType::Incoming.can_board() && DepartureType::Incoming.can_alight());
+        assert!(DepartureType::Outgoing.can_board() && !DepartureType::Outgoing.can_alight());
+    }
+
+    #[test]
+    fn 乗り換えルールはより細かい条件のものが書かれている値だけ上書きする() {
+        let raw: RawTransfarRules = serde_json::from_str(r#"{
+            "default": {"min": 0},
+            "modes": [{"from_mode": "BUS", "to_mode": "LTDEXPTRAIN", "min": 10, "max": 120}],
+            "stations": [{"station_id": "AP", "to_agency": "ANA", "min": 40}],
+            "station_pairs": [{"from_station_id": "BUS", "to_station_id": "JR", "min": 8}]
+        }"#).unwrap();
+        let rules = TransfarRules::from_raw(raw);
+        let id = |raw_id: &str| StationId::new(raw_id.to_string(), TEST_ROOT_PATH);
+        let ride = |raw_id: &str| RideId::new(raw_id.to_string(), TEST_ROOT_PATH);
+        let (bus, jr, ap) = (id("BUS"), id("JR"), id("AP"));
+        let (hokuto, ana, jal) = (ride("JRH_HOKUTO"), ride("ANA_554"), ride("JAL_584"));
+
+        let to_train = TransfarKind { from_station: &bus, to_station: &jr, from_mode: CareerType::Bus, to_mode: CareerType::LtdExpTrain, to_ride: &hokuto };
+        assert_eq!(TransfarLimit { min: 8, max: Some(120) }, rules.limit(&to_train));
+        let elsewhere = TransfarKind { from_station: &jr, ..to_train };
+        assert_eq!(TransfarLimit { min: 10, max: Some(120) }, rules.limit(&elsewhere));
+
+        let to_ana = TransfarKind { from_station: &ap, to_station: &ap, from_mode: CareerType::Bus, to_mode: CareerType::Airplane, to_ride: &ana };
+        assert!(!rules.is_acceptable(&to_ana, 39));
+        assert!(rules.is_acceptable(&to_ana, 40));
+        assert!(rules.is_acceptable(&TransfarKind { to_ride: &jal, ..to_ana }, 0));
+    }
+
+    #[test]
+    fn 日付をまたぐ時刻を運行日の24時以降として読める() {
+        let actual = ServiceTime::parse("25:10").unwrap();
+        assert_eq!(25 * 3600 + 10 * 60, actual.seconds());
+        assert_eq!(1, actual.days());
+        assert_eq!(NaiveTime::from_hms_opt(1, 10, 0).unwrap(), actual.wall_clock());
+        assert_eq!("25:10", actual.to_string());
+        assert_eq!(Some(ServiceTime::from_hm(1, 10)), actual.on_next_day());
+        assert_eq!(80, actual.minutes_since(ServiceTime::from_hm(23, 50)));
+        let service_date = NaiveDate::from_ymd_opt(2025, 4, 30).unwrap();
+        assert_eq!(NaiveDate::from_ymd_opt(2025, 5, 1).unwrap(), actual.date_on(service_date));
+
+        assert_eq!(ServiceTime::from_hm(8, 45), ServiceTime::parse("8:45").unwrap());
+        assert!(ServiceTime::parse("8:5").is_err());
+        assert!(ServiceTime::parse("08:60").is_err());
+        assert!(ServiceTime::parse("48:00").is_err());
+
+        assert_eq!(ServiceTime::from_hm(24, 5), ServiceTime::from_hm(23, 50).delayed(15 * 60));
+        assert_eq!(ServiceTime::from_hm(7, 58), ServiceTime::from_hm(8, 0).delayed(-120));
+        assert_eq!(ServiceTime::from_hm(0, 0), ServiceTime::from_hm(0, 1).delayed(-120));
+    }
+
+    #[test]
+    fn 運行情報は期間内で対象のどれかに一致する発車に当てはまる() {
+        let raw: RawAlert = serde_json::from_str(r#"{
+            "effect": "NO_SERVICE",
+            "targets": [{"trip_id": "HAKODATEBUS_55G-D-0756"}, {"station_id": "HAKODATEBUS_050004", "ride_id": "HAKODATEBUS_55G"}],
+            "from": "2025-04-25",
+            "until": "2025-04-26",
+            "header": "工事のため運休"
+        }"#).unwrap();
+        let alert = Alert::from_raw(raw, "admin-1").unwrap();
+        assert_eq!("admin-1", alert.alert_id);
+        assert_eq!(AlertEffect::NoService, alert.effect);
+
+        let date = NaiveDate::from_ymd_opt(2025, 4, 25).unwrap();
+        assert!(alert.applies_to("HAKODATEBUS_030023", "HAKODATEBUS_55A", "HAKODATEBUS_55G-D-0756", date));
+        // GTFSから取り込んだ系統は`-n`がついていても一致する
+        assert!(alert.applies_to("HAKODATEBUS_050004", "HAKODATEBUS_55G-2", "HAKODATEBUS_55G-D-1102", date));
+        assert!(!alert.applies_to("HAKODATEBUS_050004", "HAKODATEBUS_55G-D", "HAKODATEBUS_55G-D-1102", date));
+        assert!(!alert.applies_to("HAKODATEBUS_030023", "HAKODATEBUS_55G", "HAKODATEBUS_55G-D-1102", date));
+        assert!(!alert.applies_to("HAKODATEBUS_030023", "HAKODATEBUS_55A", "HAKODATEBUS_55G-D-0756", NaiveDate::from_ymd_opt(2025, 4, 27).unwrap()));
+        assert_eq!(Some("2025-04-26".to_string()), alert.to_raw().until);
+    }
+
+    #[test]
+    fn 対象や文面がない運行情報は読めない() {
+        let raw = |targets: Vec<AlertTarget>, header: &str, from: Option<&str>| RawAlert {
+            alert_id: None,
+            effect: AlertEffect::Notice,
+            targets,
+            from: from.map(|x| x.to_string()),
+            until: Some("2025-04-25".to_string()),
+            header: header.to_string(),
+            description: None,
+        };
+        let trip = || vec![AlertTarget { trip_id: Some("HAKODATEBUS_55G-D-0756".to_string()), ..Default::default() }];
+        assert!(Alert::from_raw(raw(trip(), "迂回", Some("2025-04-25")), "admin-1").is_ok());
+        assert!(Alert::from_raw(raw(vec![], "迂回", None), "admin-1").is_err());
+        assert!(Alert::from_raw(raw(vec![AlertTarget::default()], "迂回", None), "admin-1").is_err());
+        assert!(Alert::from_raw(raw(trip(), " ", None), "admin-1").is_err());
+        assert!(Alert::from_raw(raw(trip(), "迂回", Some("2025/04/25")), "admin-1").is_err());
+        assert!(Alert::from_raw(raw(trip(), "迂回", Some("2025-04-26")), "admin-1").is_err());
+    }
 }
@@ -0,0 +1,19 @@
+use serde::Serialize;
+use crate::alert::Alert;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResAlert {
+    pub alert_id: String,
+    pub header: String,
+    pub description: Option<String>,
+}
+
+impl From<&Alert> for ResAlert {
+    fn from(alert: &Alert) -> Self {
+        Self {
+            alert_id: alert.alert_id.clone(),
+            header: alert.header.clone(),
+            description: alert.description.clone(),
+        }
+    }
+}
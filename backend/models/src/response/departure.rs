@@ -1,6 +1,7 @@
 use serde::Serialize;
+use crate::ride::CareerType;
 
-use super::{station::ResStation, transfar::ResTransfar};
+use super::{alert::ResAlert, station::ResStation, transfar::ResTransfar};
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct ResDeparture {
@@ -10,7 +11,12 @@ pub struct ResDeparture {
     pub type_background: String,
     pub type_pronounce: String,
     pub to: ResStation,
-    pub career_type: String,
-    pub depart_at: String,
+    pub career_type: CareerType,
+    pub depart_at: String, // hh:mm, リアルタイムの遅れがわかる便は遅れを反映した時刻
+    pub scheduled_depart_at: String, // hh:mm, 時刻表の時刻
+    pub delay_minutes: Option<i64>, // リアルタイムの遅れ (早発は負)。情報がない便はNone
+    pub realtime: bool, // リアルタイムの情報がある便ならtrue
+    pub service_date: String, // yyyy-mm-dd, 前日の運行日の便が日付をまたいで出る場合は前日
+    pub alerts: Vec<ResAlert>, // この発車に当てはまるお知らせ
     pub transfars: Vec<Vec<ResTransfar>>
 }
\ No newline at end of file
@@ -4,5 +4,6 @@ use super::departure::ResDeparture;
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct ResDepartures {
-    pub departures: Vec<ResDeparture>
+    pub departures: Vec<ResDeparture>,
+    pub next_cursor: Option<String>, // 続きの発車があれば、次のページを取得するためのcursor
 }
\ No newline at end of file
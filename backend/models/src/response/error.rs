@@ -0,0 +1,11 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// すべてのエンドポイントで共通のエラーレスポンス
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResError {
+    pub code: String,
+    pub message: String,
+    /// エラーごとの追加情報。なければnull
+    pub details: Option<Value>,
+}
@@ -0,0 +1,41 @@
+use serde::Serialize;
+use crate::ride::CareerType;
+
+use super::alert::ResAlert;
+use super::station::ResStation;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResJourney {
+    pub depart_at: String, // hh:mm
+    pub depart_date: String, // yyyy-mm-dd, 日付をまたいで出る場合は翌日
+    pub arrive_at: Option<String>, // hh:mm, 到着駅に時刻表がない場合はNone
+    pub arrive_date: Option<String>, // yyyy-mm-dd, 日付をまたいで着く場合は翌日
+    pub transfar_count: usize,
+    pub legs: Vec<ResJourneyLeg>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ResLegType {
+    Ride,
+    /// 近くの駅へ歩いて移る
+    Walk,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResJourneyLeg {
+    pub leg_type: ResLegType,
+    pub ride_type: String,
+    pub type_foreground: String,
+    pub type_background: String,
+    pub at: ResStation,
+    pub to: ResStation,
+    pub career_type: Option<CareerType>, // 歩く区間ではNone
+    pub depart_at: String, // hh:mm
+    pub arrive_at: Option<String>, // hh:mm
+    pub duration: Option<i64>, // 分, 到着時刻がわからない場合はNone
+    pub transfar_time: i64,
+    pub delay_minutes: Option<i64>, // 乗る駅を出るときのリアルタイムの遅れ。情報がない便と歩く区間はNone
+    pub realtime: bool,
+    pub alerts: Vec<ResAlert>, // 乗る駅と降りる駅のお知らせ。歩く区間では空
+}
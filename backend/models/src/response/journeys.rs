@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use super::journey::ResJourney;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResJourneys {
+    pub journeys: Vec<ResJourney>
+}
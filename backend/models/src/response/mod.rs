@@ -2,4 +2,15 @@ pub mod station;
 pub mod stations;
 pub mod transfar;
 pub mod departure;
-pub mod departures;
\ No newline at end of file
+pub mod departures;
+pub mod error;
+pub mod journey;
+pub mod journeys;
+pub mod reload;
+pub mod station_timetable;
+pub mod trip;
+pub mod trips;
+pub mod nearby_stations;
+pub mod realtime;
+pub mod alert;
+pub mod revisions;
\ No newline at end of file
@@ -0,0 +1,14 @@
+use serde::Serialize;
+use super::station::ResStation;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResNearbyStations {
+    pub stations: Vec<ResNearbyStation>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResNearbyStation {
+    pub station: ResStation,
+    pub distance_meters: u32, // 指定した地点からの大円距離
+    pub has_timetable: bool,
+}
@@ -0,0 +1,7 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResRealtime {
+    pub trips: usize,
+    pub timestamp: Option<String>,
+}
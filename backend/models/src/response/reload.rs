@@ -0,0 +1,7 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResReload {
+    pub stations: usize,
+    pub rides: usize,
+}
@@ -0,0 +1,19 @@
+use serde::Serialize;
+
+use super::station::ResStation;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResRevisions {
+    pub station: ResStation,
+    pub date: String, // yyyy-mm-dd
+    pub current: Option<ResRevision>, // dateに有効なカレンダー。なければNone
+    pub upcoming: Vec<ResRevision>, // dateより後に始まるカレンダー。始まる順
+}
+
+/// 時刻表のカレンダーのバージョン
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResRevision {
+    pub calendar_id: String,
+    pub valid_from: Option<String>, // yyyy-mm-dd, いつから有効かわからない場合はNone
+    pub valid_until: String, // yyyy-mm-dd
+}
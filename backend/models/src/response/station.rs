@@ -7,6 +7,10 @@ pub struct ResStation {
     pub id: String,
     pub name: String,
     pub pronounce: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lat: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lon: Option<f64>,
 }
 
 impl From<Station> for ResStation {
@@ -14,7 +18,9 @@ impl From<Station> for ResStation {
         Self {
             id: station.station_id.get_raw_id(),
             name: station.name,
-            pronounce: station.pronounce
+            pronounce: station.pronounce,
+            lat: station.lat,
+            lon: station.lon,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+use serde::Serialize;
+use crate::{calendar::PatternSource, ride::CareerType};
+
+use super::station::ResStation;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResStationTimetable {
+    pub station: ResStation,
+    pub date: String, // yyyy-mm-dd
+    pub patterns: Vec<ResResolvedPattern>, // 駅とjoinでつながった駅ごとの発車パターン
+    pub hours: Vec<ResTimetableHour>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResResolvedPattern {
+    pub station: ResStation,
+    pub calendar_id: String,
+    pub pattern_id: String,
+    pub source: PatternSource,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResTimetableHour {
+    pub hour: u32, // 0-23, 日付をまたいだ発車は最後に0時台として並ぶ
+    pub groups: Vec<ResTimetableGroup>,
+}
+
+/// 同じ駅から同じ種別、同じ行き先へ出る便
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResTimetableGroup {
+    pub ride_type: String,
+    pub aka_type: Option<String>,
+    pub type_foreground: String,
+    pub type_background: String,
+    pub type_pronounce: String,
+    pub career_type: CareerType,
+    pub at: ResStation,
+    pub to: ResStation,
+    pub departures: Vec<ResTimetableDeparture>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResTimetableDeparture {
+    pub depart_at: String, // hh:mm
+    pub trip_id: String,
+}
@@ -1,5 +1,6 @@
 use serde::Serialize;
-use super::station::ResStation;
+use crate::ride::CareerType;
+use super::{alert::ResAlert, station::ResStation};
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct ResTransfar {
@@ -8,7 +9,10 @@ pub struct ResTransfar {
     pub type_background: String,
     pub at: ResStation,
     pub to: ResStation,
-    pub career_type: String,
-    pub depart_at: String, // hh:mm
+    pub career_type: CareerType,
+    pub depart_at: String, // hh:mm, リアルタイムの遅れがわかる便は遅れを反映した時刻
+    pub delay_minutes: Option<i64>, // リアルタイムの遅れ。情報がない便はNone
+    pub realtime: bool,
+    pub alerts: Vec<ResAlert>, // 乗り継ぐ便に当てはまるお知らせ
     pub transfar_time: i64,
 }
\ No newline at end of file
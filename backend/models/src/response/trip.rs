@@ -0,0 +1,29 @@
+use serde::Serialize;
+use crate::ride::CareerType;
+
+use super::station::ResStation;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResTrip {
+    pub trip_id: String,
+    pub ride_id: String,
+    pub ride_type: String,
+    pub aka_type: Option<String>,
+    pub type_foreground: String,
+    pub type_background: String,
+    pub type_pronounce: String,
+    pub career_type: CareerType,
+    pub to: ResStation,
+    pub service_date: String, // yyyy-mm-dd
+    pub stops: Vec<ResTripStop>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResTripStop {
+    pub station: ResStation,
+    pub loop_count: i32, // 同じ駅に何回目に停車するか (Rideのrouteの`~n`)
+    pub time: Option<String>, // hh:mm, 駅に時刻表がない場合はNone
+    pub date: Option<String>, // yyyy-mm-dd, 日付をまたいだ停車は翌日
+    pub can_board: bool,
+    pub can_alight: bool,
+}
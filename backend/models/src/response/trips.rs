@@ -0,0 +1,8 @@
+use serde::Serialize;
+
+use super::trip::ResTrip;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResTrips {
+    pub trips: Vec<ResTrip>
+}
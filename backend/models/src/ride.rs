@@ -1,6 +1,7 @@
 use std::{fs::File, io::BufReader};
 use std::error::Error;
-use serde::Deserialize;
+use std::fmt::Display;
+use serde::{Deserialize, Serialize};
 use crate::id::{RideId, StationId, ID};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -11,43 +12,77 @@ pub struct Ride {
     pub type_background: String,
     pub type_pronounce: String,
     pub to: StationId,
-    pub career_type: String,
+    pub career_type: CareerType,
     pub route: Vec<StationId>
 }
 
+/// 乗り物の種類。JSONでは`"BUS"`のように大文字で書く
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CareerType {
+    Bus,
+    Tram,
+    Subway,
+    Train,
+    /// 特急列車
+    LtdExpTrain,
+    Ferry,
+    Airplane,
+}
+
+impl CareerType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bus => "BUS",
+            Self::Tram => "TRAM",
+            Self::Subway => "SUBWAY",
+            Self::Train => "TRAIN",
+            Self::LtdExpTrain => "LTDEXPTRAIN",
+            Self::Ferry => "FERRY",
+            Self::Airplane => "AIRPLANE",
+        }
+    }
+}
+
+impl Display for CareerType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 #[allow(dead_code)]
 impl Ride {
-    pub fn from_raw(raw: RawRide) -> Self {
+    pub fn from_raw(raw: RawRide, data_root_path: &str) -> Self {
         Self {
             ride_type: raw.ride_type,
             aka_type: raw.aka_type,
             type_foreground: raw.type_foreground,
             type_background: raw.type_background,
             type_pronounce: raw.type_pronounce,
-            to: StationId::new(raw.to.to_string()),
+            to: StationId::new(raw.to.to_string(), data_root_path),
             career_type: raw.career_type,
-            route: raw.route.iter().map(|x| StationId::new(x.clone())).collect()
+            route: raw.route.iter().map(|x| StationId::new(x.clone(), data_root_path)).collect()
         }
     }
 
-    pub fn from_id(id: String) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
-        let f = File::open(RideId::new(id).build_path())?;
+    pub fn from_id(id: RideId) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let f = File::open(id.build_path())?;
         let reader = BufReader::new(f);
         let raw: RawRide = serde_json::from_reader(reader)?;
-        Ok(Self::from_raw(raw))
+        Ok(Self::from_raw(raw, &id.get_root_path()))
     }
 }
 
 #[allow(dead_code)]
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RawRide {
-    pub(crate) ride_id: String,
-    #[serde(rename(deserialize = "type"))] pub ride_type: String,
+    pub ride_id: String,
+    #[serde(rename = "type")] pub ride_type: String,
     pub aka_type: String,
     pub type_foreground: String,
     pub type_background: String,
     pub type_pronounce: String,
     pub to: String,
-    pub career_type: String,
+    pub career_type: CareerType,
     pub route: Vec<String>,
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::{error::Error, fmt::Display};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+
+/// 1日の秒数
+pub const SECONDS_PER_DAY: u32 = 86400;
+
+/// 運行日 (サービス日) の0時からの経過秒数。GTFSと同じく、日付をまたぐ便は`24:35`のように24時以降で表す
+/// GTFSでは「運行日の正午の12時間前」からだが、日本には夏時間がないので0時と同じになる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ServiceTime(u32);
+
+impl ServiceTime {
+    pub fn from_seconds(seconds: u32) -> Self {
+        Self(seconds)
+    }
+
+    pub fn from_hm(hour: u32, minute: u32) -> Self {
+        Self(hour * 3600 + minute * 60)
+    }
+
+    /// `HH:MM`を読む。HHは24以上 (翌日の深夜) でもよく、これまでのデータと同じく`8:45`のような1桁も読む
+    pub fn parse(raw: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let invalid = || format!("invalid time `{}`, expected HH:MM", raw);
+        let (hour, minute) = raw.split_once(':').ok_or_else(invalid)?;
+        if hour.is_empty() || hour.len() > 2 || minute.len() != 2 {
+            return Err(invalid().into());
+        }
+        let (Ok(hour), Ok(minute)) = (hour.parse::<u32>(), minute.parse::<u32>()) else {
+            return Err(invalid().into());
+        };
+        if minute >= 60 || hour >= 48 {
+            return Err(invalid().into());
+        }
+        Ok(Self::from_hm(hour, minute))
+    }
+
+    pub fn seconds(&self) -> u32 {
+        self.0
+    }
+
+    /// 運行日の何日後の時刻か。24時以降なら1
+    pub fn days(&self) -> u32 {
+        self.0 / SECONDS_PER_DAY
+    }
+
+    /// 時計の上での時刻 (24時以降は翌日の時刻)
+    pub fn wall_clock(&self) -> NaiveTime {
+        NaiveTime::from_num_seconds_from_midnight_opt(self.0 % SECONDS_PER_DAY, 0).expect("valid seconds")
+    }
+
+    /// service_dateの運行日のこの時刻が、時計の上で何日か
+    pub fn date_on(&self, service_date: NaiveDate) -> NaiveDate {
+        self.datetime_on(service_date).date()
+    }
+
+    /// service_dateの運行日のこの時刻の、時計の上での日時
+    pub fn datetime_on(&self, service_date: NaiveDate) -> NaiveDateTime {
+        service_date.and_time(NaiveTime::MIN) + TimeDelta::seconds(self.0 as i64)
+    }
+
+    /// earlierからの経過分数
+    pub fn minutes_since(&self, earlier: ServiceTime) -> i64 {
+        (self.0 as i64 - earlier.0 as i64) / 60
+    }
+
+    /// 遅れ (秒。早く着く場合は負) を足した時刻。運行日の0時より前にはしない
+    pub fn delayed(&self, delay_seconds: i32) -> Self {
+        Self((self.0 as i64 + delay_seconds as i64).max(0) as u32)
+    }
+
+    /// 翌日の運行日から見た時刻。24時より前ならNone
+    pub fn on_next_day(&self) -> Option<Self> {
+        self.0.checked_sub(SECONDS_PER_DAY).map(Self)
+    }
+
+    /// 前日の運行日から見た時刻
+    pub fn on_previous_day(&self) -> Self {
+        Self(self.0 + SECONDS_PER_DAY)
+    }
+}
+
+impl From<NaiveTime> for ServiceTime {
+    fn from(time: NaiveTime) -> Self {
+        Self(time.num_seconds_from_midnight())
+    }
+}
+
+/// データと同じ`HH:MM` (24時以降もそのまま) で表示する
+impl Display for ServiceTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 3600, self.0 % 3600 / 60)
+    }
+}
@@ -1,5 +1,5 @@
 use std::{error::Error, fs::File, io::BufReader, path::Path};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use crate::id::{StationId, ID};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -7,23 +7,34 @@ pub struct Station {
     pub station_id: StationId,
     pub name: String,
     pub pronounce: String,
-    pub join: Vec<StationId>
+    pub join: Vec<StationId>,
+    /// 緯度 (度)。緯度経度がわからない駅はNone
+    pub lat: Option<f64>,
+    /// 経度 (度)
+    pub lon: Option<f64>,
 }
 
 impl Station {
-    #[deprecated(note="use StationRepository::from_raw instead")]
+    #[deprecated(note="use StationRepository::build_from_raw instead")]
     #[allow(dead_code)]
-    pub fn from_raw(raw: RawStation) -> Self {
+    pub fn from_raw(raw: RawStation, data_root_path: &str) -> Self {
         Self {
-            station_id: StationId::new(raw.station_id.clone()),
+            station_id: StationId::new(raw.station_id.clone(), data_root_path),
             name: raw.name,
             pronounce: raw.pronounce,
-            join: raw.join.iter().map(|x| StationId::new(x.clone())).collect()
+            join: raw.join.iter().map(|x| StationId::new(x.clone(), data_root_path)).collect(),
+            lat: raw.lat,
+            lon: raw.lon,
         }
     }
 
+    /// 緯度と経度の両方がわかる場合だけ (緯度, 経度) を返す
+    pub fn coordinate(&self) -> Option<(f64, f64)> {
+        self.lat.zip(self.lon)
+    }
+
     #[allow(deprecated)]
-    #[deprecated(note="use StationRepository::from_id instead")]
+    #[deprecated(note="use StationRepository::find_by_id instead")]
     #[allow(dead_code)]
     pub fn from_id(id: StationId) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
         let path = id.build_path();
@@ -31,7 +42,7 @@ impl Station {
         let reader = BufReader::new(f);
         let raw: RawStation = serde_json::from_reader(reader)?;
 
-        Ok(Station::from_raw(raw))
+        Ok(Station::from_raw(raw, &id.get_root_path()))
     }
 
     #[deprecated(note="use StationRepository::check_is_valid instead")]
@@ -44,10 +55,25 @@ impl Station {
 
 
 #[allow(dead_code)]
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct RawStation {
     pub station_id: String,
     pub name: String,
     pub pronounce: String,
-    pub join: Vec<String>
+    pub join: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lat: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lon: Option<f64>,
+}
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// 2点 (緯度, 経度) 間の大円距離 (メートル)
+pub fn distance_meters(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (from_lat, from_lon) = (from.0.to_radians(), from.1.to_radians());
+    let (to_lat, to_lon) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((to_lat - from_lat) / 2.0).sin().powi(2)
+        + from_lat.cos() * to_lat.cos() * ((to_lon - from_lon) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
 }
@@ -1,7 +1,7 @@
 use std::{error::Error, fs::File, io::BufReader};
 
-use serde::Deserialize;
-use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use chrono::{Days, NaiveDate};
 use crate::id::{CalendarId, StationId, ID};
 
 #[derive(Debug, PartialEq)]
@@ -11,13 +11,27 @@ pub struct TimeTable {
 }
 
 impl TimeTable {
+    /// versionsをvalid_untilの順に並べ、期間が重なるものがあればエラーにする
     #[allow(dead_code)]
-    pub(crate) fn from_raw(raw: RawTimetable) -> Result<Self, chrono::ParseError> {
-        let versions = raw
+    pub(crate) fn from_raw(raw: RawTimetable, data_root_path: &str) -> Result<Self, Box<dyn Error + Sync + Send + 'static>> {
+        let mut versions = raw
             .versions
             .into_iter()
-            .map(CalendarVersion::from_raw)
+            .map(|x| CalendarVersion::from_raw(x, data_root_path))
             .collect::<Result<Vec<_>, _>>()?;
+        versions.sort_by_key(|x| x.valid_until);
+        for (previous, version) in versions.iter().zip(versions.iter().skip(1)) {
+            // valid_fromがなければ前のバージョンの翌日から始まる。valid_untilが同じものは後ろが使われない
+            let starts = version.valid_from.unwrap_or(previous.valid_until + Days::new(1));
+            if starts <= previous.valid_until || version.valid_until == previous.valid_until {
+                return Err(format!(
+                    "calendar {} overlaps {} (until {})",
+                    version.calendar_id.get_raw_id(),
+                    previous.calendar_id.get_raw_id(),
+                    previous.valid_until,
+                ).into());
+            }
+        }
 
         Ok(TimeTable {
             station_id: raw.station_id,
@@ -32,44 +46,82 @@ impl TimeTable {
         let reader = BufReader::new(f);
         let raw: RawTimetable = serde_json::from_reader(reader)?;
 
-        Ok(Self::from_raw(raw)?)
+        Self::from_raw(raw, &id.get_root_path())
     }
 
+    pub fn versions(&self) -> &[CalendarVersion] {
+        &self.versions
+    }
+
+    /// dateに有効なバージョン。valid_fromがなければversion_startsと同じく前のバージョンの翌日から有効とする
     #[allow(dead_code)]
     pub fn get_valid_calendar(&self, date: NaiveDate) -> Option<CalendarVersion> {
-        let first_valid_version = self.versions
-            .iter()
-            .filter(|x| date <= x.valid_until)
-            .nth(0);
+        let first_valid_version = self.version_starts()
+            .into_iter()
+            .find(|(starts, x)| starts.is_none_or(|y| y <= date) && date <= x.valid_until)
+            .map(|(_, x)| x);
+
+        first_valid_version.cloned()
+    }
+
+    /// versionsを、それぞれが始まる日と一緒に返す
+    /// valid_fromがないバージョンは前のバージョンの翌日から始まるものとする。最初のバージョンのvalid_fromがなければNone
+    pub fn version_starts(&self) -> Vec<(Option<NaiveDate>, &CalendarVersion)> {
+        let mut previous_until: Option<NaiveDate> = None;
+        let mut res = Vec::new();
+        for version in self.versions.iter() {
+            res.push((version.valid_from.or(previous_until.map(|x| x + Days::new(1))), version));
+            previous_until = Some(version.valid_until);
+        }
+        res
+    }
 
-        return first_valid_version.cloned()
+    /// dateより後に始まるバージョンを、始まる日と一緒に始まる順に返す
+    pub fn upcoming_versions(&self, date: NaiveDate) -> Vec<(NaiveDate, &CalendarVersion)> {
+        self.version_starts()
+            .into_iter()
+            .filter_map(|(starts, version)| starts.filter(|x| date < *x).map(|x| (x, version)))
+            .collect()
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CalendarVersion {
     pub calendar_id: CalendarId,
+    /// Noneなら前のバージョンの翌日から (最初のバージョンなら制限なし)
+    pub valid_from: Option<NaiveDate>,
     pub valid_until: NaiveDate,
 }
 
 impl CalendarVersion {
-    fn from_raw(raw: RawVersion) -> Result<Self, chrono::ParseError> {
+    fn from_raw(raw: RawVersion, data_root_path: &str) -> Result<Self, Box<dyn Error + Sync + Send + 'static>> {
+        let valid_from = raw
+            .valid_from
+            .as_deref()
+            .map(|x| NaiveDate::parse_from_str(x, "%Y/%m/%d"))
+            .transpose()?;
         let valid_until = NaiveDate::parse_from_str(&raw.valid_until, "%Y/%m/%d")?;
+        if let Some(valid_from) = valid_from && valid_from > valid_until {
+            return Err(format!("calendar {}: valid_from {} is after valid_until {}", raw.calendar_id, valid_from, valid_until).into());
+        }
         Ok(CalendarVersion {
-            calendar_id: CalendarId::new(raw.calendar_id),
+            calendar_id: CalendarId::new(raw.calendar_id, data_root_path),
+            valid_from,
             valid_until,
         })
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-pub(crate) struct RawTimetable {
-    station_id: String,
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct RawTimetable {
+    pub station_id: String,
     pub versions: Vec<RawVersion>
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq)]
-pub(crate) struct RawVersion {
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RawVersion {
     pub calendar_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<String>,
     pub valid_until: String
 }
@@ -0,0 +1,217 @@
+use std::{error::Error, fs::File, io::BufReader, path::Path};
+use serde::{Deserialize, Serialize};
+
+use crate::{id::{RideId, StationId, ID}, ride::CareerType};
+
+/// データのルート直下に置く乗り換えルールのファイル名
+pub const TRANSFAR_RULES_FILE: &str = "transfar-rules.json";
+
+/// 乗り換えにかかる時間 (分) の下限と上限。既定値は0分以上、上限なし
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransfarLimit {
+    pub min: i64,
+    /// Noneなら上限なし
+    pub max: Option<i64>,
+}
+
+impl TransfarLimit {
+    pub fn contains(&self, transfar_time: i64) -> bool {
+        self.min <= transfar_time && self.max.is_none_or(|max| transfar_time <= max)
+    }
+
+    /// ruleに書かれている値だけ上書きする
+    fn apply(&mut self, rule: &RawTransfarTime) {
+        if let Some(min) = rule.min {
+            self.min = min;
+        }
+        if let Some(max) = rule.max {
+            self.max = Some(max);
+        }
+    }
+}
+
+/// 緯度経度のある駅どうしを歩いて乗り換えるときの条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Walking {
+    /// 直線距離がこれ以下の駅どうしを歩いて移れるものとする (メートル)
+    pub radius_meters: f64,
+    /// 歩く速さ (メートル/分)
+    pub meters_per_minute: f64,
+}
+
+impl Default for Walking {
+    /// 不動産の表示規約と同じく80メートルを1分とする
+    fn default() -> Self {
+        Self { radius_meters: 300.0, meters_per_minute: 80.0 }
+    }
+}
+
+impl Walking {
+    /// distance_metersを歩くのにかかる時間 (分)。1分未満は切り上げる
+    pub fn minutes(&self, distance_meters: f64) -> i64 {
+        (distance_meters / self.meters_per_minute).ceil() as i64
+    }
+}
+
+/// 判定する乗り換え。降りる駅と乗る駅はjoinでつながった別の駅のことがある
+#[derive(Debug, Clone, Copy)]
+pub struct TransfarKind<'a> {
+    pub from_station: &'a StationId,
+    pub to_station: &'a StationId,
+    pub from_mode: CareerType,
+    pub to_mode: CareerType,
+    /// 乗り換え先の便。事業者 (IDの先頭) で絞り込むのに使う
+    pub to_ride: &'a RideId,
+}
+
+/// 乗り換えにかかる時間のルール
+/// 既定値、乗り物の組み合わせ、乗る駅、降りる駅と乗る駅の組の順に、後のものほど優先して当てはめる
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransfarRules {
+    raw: RawTransfarRules,
+}
+
+impl TransfarRules {
+    pub fn from_raw(raw: RawTransfarRules) -> Self {
+        Self { raw }
+    }
+
+    /// data_root_path直下のファイルを読む。ファイルがなければルールなし (0分以上ならどれでも乗り換えられる) として扱う
+    pub fn from_data_root(data_root_path: &str) -> Result<Self, Box<dyn Error + Send + Sync + 'static>> {
+        let path = Path::new(data_root_path).join(TRANSFAR_RULES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let reader = BufReader::new(File::open(path)?);
+        let raw: RawTransfarRules = serde_json::from_reader(reader)?;
+        Ok(Self::from_raw(raw))
+    }
+
+    pub fn raw(&self) -> &RawTransfarRules {
+        &self.raw
+    }
+
+    pub fn limit(&self, transfar: &TransfarKind) -> TransfarLimit {
+        let mut res = TransfarLimit::default();
+        if let Some(default) = &self.raw.default {
+            res.apply(default);
+        }
+        for rule in self.raw.modes.iter() {
+            if rule.from_mode == transfar.from_mode && rule.to_mode == transfar.to_mode {
+                res.apply(&rule.time);
+            }
+        }
+        let to_station = transfar.to_station.get_raw_id();
+        for rule in self.raw.stations.iter() {
+            if rule.station_id == to_station && rule.filter.matches(transfar) {
+                res.apply(&rule.time);
+            }
+        }
+        let from_station = transfar.from_station.get_raw_id();
+        for rule in self.raw.station_pairs.iter() {
+            if rule.from_station_id == from_station && rule.to_station_id == to_station && rule.filter.matches(transfar) {
+                res.apply(&rule.time);
+            }
+        }
+        res
+    }
+
+    pub fn is_acceptable(&self, transfar: &TransfarKind, transfar_time: i64) -> bool {
+        self.limit(transfar).contains(transfar_time)
+    }
+
+    pub fn walking(&self) -> Walking {
+        let mut res = Walking::default();
+        if let Some(walking) = &self.raw.walking {
+            if let Some(radius_meters) = walking.radius_meters {
+                res.radius_meters = radius_meters;
+            }
+            if let Some(meters_per_minute) = walking.meters_per_minute {
+                res.meters_per_minute = meters_per_minute;
+            }
+        }
+        res
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RawTransfarRules {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<RawTransfarTime>,
+    #[serde(default)]
+    pub modes: Vec<RawModeRule>,
+    #[serde(default)]
+    pub stations: Vec<RawStationRule>,
+    #[serde(default)]
+    pub station_pairs: Vec<RawStationPairRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walking: Option<RawWalking>,
+}
+
+/// 歩いて乗り換えるときの条件。書かれていない値は既定値 (Walking::default) を使う
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RawWalking {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius_meters: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meters_per_minute: Option<f64>,
+}
+
+/// 乗り換え時間 (分)。書かれていない値は優先度の低いルールのものを使う
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RawTransfarTime {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RawModeRule {
+    pub from_mode: CareerType,
+    pub to_mode: CareerType,
+    #[serde(flatten)]
+    pub time: RawTransfarTime,
+}
+
+/// 駅や駅の組のルールを、乗り物や事業者で絞り込む条件。書かれていなければすべてに当てはまる
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RawRuleFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_mode: Option<CareerType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_mode: Option<CareerType>,
+    /// 乗り換え先のRideのIDの先頭 (`ANA`など)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_agency: Option<String>,
+}
+
+impl RawRuleFilter {
+    fn matches(&self, transfar: &TransfarKind) -> bool {
+        let to_ride = transfar.to_ride.get_raw_id();
+        self.from_mode.is_none_or(|x| x == transfar.from_mode)
+            && self.to_mode.is_none_or(|x| x == transfar.to_mode)
+            && self.to_agency.as_ref().is_none_or(|x| to_ride.split('_').next() == Some(x.as_str()))
+    }
+}
+
+/// その駅で乗る場合のルール
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RawStationRule {
+    pub station_id: String,
+    #[serde(flatten)]
+    pub filter: RawRuleFilter,
+    #[serde(flatten)]
+    pub time: RawTransfarTime,
+}
+
+/// from_station_idで降りてto_station_idで乗る場合のルール
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RawStationPairRule {
+    pub from_station_id: String,
+    pub to_station_id: String,
+    #[serde(flatten)]
+    pub filter: RawRuleFilter,
+    #[serde(flatten)]
+    pub time: RawTransfarTime,
+}
@@ -24,7 +24,7 @@ impl<F> StationRepository for DevelopmentStationRepository<F>
 where
     F: Fn(StationId) -> Vec<StationId>,
 {
-    fn from_id(
+    fn find_by_id(
         &self,
         id: models::id::StationId,
     ) -> Result<models::station::Station, Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -33,15 +33,20 @@ where
             name: id.get_raw_id().clone(),
             pronounce: "".to_string(),
             join: (self.calc_join)(id.clone()),
+            lat: None,
+            lon: None,
         })
     }
 
-    fn from_raw(&self, raw: models::station::RawStation) -> models::station::Station {
+    fn build_from_raw(&self, raw: models::station::RawStation) -> models::station::Station {
         Station {
-            station_id: StationId::new(raw.station_id),
+            // 開発用なのでファイルは読まず、データセットのルートは持たない
+            station_id: StationId::new(raw.station_id, ""),
             name: raw.name.clone(),
             pronounce: raw.pronounce.clone(),
-            join: raw.join.into_iter().map(|x| StationId::new(x)).collect(),
+            join: raw.join.into_iter().map(|x| StationId::new(x, "")).collect(),
+            lat: raw.lat,
+            lon: raw.lon,
         }
     }
 
@@ -2,24 +2,36 @@ use std::{error::Error, fs::File, io::BufReader, path::Path};
 use models::{id::{StationId, ID}, station::{RawStation, Station}};
 use crate::traits;
 
-pub struct StationRepositoryImpl;
+pub struct StationRepositoryImpl {
+    data_root_path: String,
+}
+
+impl StationRepositoryImpl {
+    pub fn new(data_root_path: &str) -> Self {
+        Self {
+            data_root_path: data_root_path.to_string(),
+        }
+    }
+}
 
 impl traits::station_repository::StationRepository for StationRepositoryImpl {
-    fn from_id(&self, id: models::id::StationId) -> Result<Station, Box<dyn Error + Send + Sync + 'static>> {
+    fn find_by_id(&self, id: models::id::StationId) -> Result<Station, Box<dyn Error + Send + Sync + 'static>> {
         let path = id.build_path();
         let f = File::open(path)?;
         let reader = BufReader::new(f);
         let raw: RawStation = serde_json::from_reader(reader)?;
 
-        Ok(self.from_raw(raw))
+        Ok(self.build_from_raw(raw))
     }
 
-    fn from_raw(&self, raw: models::station::RawStation) -> Station {
+    fn build_from_raw(&self, raw: models::station::RawStation) -> Station {
         Station {
-            station_id: StationId::new(raw.station_id.clone()),
+            station_id: StationId::new(raw.station_id.clone(), &self.data_root_path),
             name: raw.name,
             pronounce: raw.pronounce,
-            join: raw.join.iter().map(|x| StationId::new(x.to_owned())).collect()
+            join: raw.join.iter().map(|x| StationId::new(x.to_owned(), &self.data_root_path)).collect(),
+            lat: raw.lat,
+            lon: raw.lon,
         }
     }
 
@@ -4,7 +4,7 @@ use models::id::StationId;
 use models::station::{RawStation, Station};
 
 pub trait StationRepository {
-    fn from_id(&self, id: StationId) -> Result<Station, Box<dyn Error + Send + Sync + 'static>>;
-    fn from_raw(&self, raw: RawStation) -> Station;
+    fn find_by_id(&self, id: StationId) -> Result<Station, Box<dyn Error + Send + Sync + 'static>>;
+    fn build_from_raw(&self, raw: RawStation) -> Station;
     fn check_is_valid(&self, station: Station) -> bool;
 }
\ No newline at end of file